@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::error;
+
+use crate::events::Severity;
+use super::{AlertContext, AlertSink};
+
+/// `[alerts] batch_windows` keys, one per channel.
+pub const CHANNEL_KEYS: [&str; 7] = ["discord", "telegram", "generic_webhook", "slack", "pagerduty", "matrix", "email"];
+
+/// Config key of a sink's channel: "Generic Webhook" -> "generic_webhook".
+pub fn channel_key(name: &str) -> String {
+    name.to_lowercase().replace(' ', "_")
+}
+
+type Pending = Arc<Mutex<Vec<(Severity, String, AlertContext)>>>;
+
+/// Wraps a sink so alerts arriving within `window` of the first one go out
+/// as a single delivery: the highest severity, one line per alert. A lone
+/// alert is delivered unchanged, with its context. `flush` sends whatever
+/// is waiting right away (shutdown, self-test).
+pub struct BatchingSink {
+    inner: Arc<dyn AlertSink>,
+    window: Duration,
+    pending: Pending,
+}
+
+impl BatchingSink {
+    pub fn new(inner: Box<dyn AlertSink>, window: Duration) -> Self {
+        Self { inner: Arc::from(inner), window, pending: Arc::new(Mutex::new(Vec::new())) }
+    }
+}
+
+#[async_trait]
+impl AlertSink for BatchingSink {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        let first = {
+            let mut pending = self.pending.lock().map_err(|_| eyre::eyre!("batch lock poisoned"))?;
+            pending.push((severity.clone(), message.to_string(), ctx.clone()));
+            pending.len() == 1
+        };
+        // The first alert of a batch starts its window
+        if first {
+            let (inner, pending, window) = (self.inner.clone(), self.pending.clone(), self.window);
+            tokio::spawn(async move {
+                tokio::time::sleep(window).await;
+                if let Err(e) = send_batch(inner.as_ref(), &pending, window).await {
+                    error!("Failed to send {} alert batch: {}", inner.name(), e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> eyre::Result<()> {
+        send_batch(self.inner.as_ref(), &self.pending, self.window).await
+    }
+}
+
+async fn send_batch(inner: &dyn AlertSink, pending: &Pending, window: Duration) -> eyre::Result<()> {
+    let batch = match pending.lock() {
+        Ok(mut pending) => std::mem::take(&mut *pending),
+        Err(_) => eyre::bail!("batch lock poisoned"),
+    };
+    match batch.as_slice() {
+        [] => Ok(()),
+        [(severity, message, ctx)] => inner.deliver(severity, message, ctx).await,
+        _ => {
+            let severity = batch.iter().map(|(s, _, _)| s.clone()).max().unwrap_or(Severity::Low);
+            inner.deliver(&severity, &batch_message(&batch, window), &AlertContext::default()).await
+        }
+    }
+}
+
+/// "3 alerts within 30s:" followed by "- [High] message (chain)" lines.
+fn batch_message(batch: &[(Severity, String, AlertContext)], window: Duration) -> String {
+    let mut text = format!("{} alerts within {}s:", batch.len(), window.as_secs());
+    for (severity, message, ctx) in batch {
+        text.push_str(&format!("\n- [{:?}] {} ({})", severity, message, ctx.chain_name.as_deref().unwrap_or("-")));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sinks::RecordingSink;
+
+    #[tokio::test]
+    async fn alerts_within_the_window_go_out_as_one() {
+        let sink = RecordingSink::default();
+        let batching = BatchingSink::new(Box::new(sink.clone()), Duration::from_secs(1));
+        batching.deliver(&Severity::Medium, "Large Transfer", &AlertContext::for_chain("ethereum")).await.unwrap();
+        batching.deliver(&Severity::Critical, "Ownership transferred", &AlertContext::for_chain("polygon")).await.unwrap();
+        batching.deliver(&Severity::High, "Transfer Burst", &AlertContext::default()).await.unwrap();
+        assert!(sink.delivered().is_empty(), "held for the window");
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let delivered = sink.delivered();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].0, Severity::Critical, "the highest of the batch");
+        assert_eq!(
+            delivered[0].1,
+            "3 alerts within 1s:\n- [Medium] Large Transfer (ethereum)\n- [Critical] Ownership transferred (polygon)\n- [High] Transfer Burst (-)"
+        );
+    }
+
+    #[tokio::test]
+    async fn flush_sends_a_lone_alert_unchanged() {
+        let sink = RecordingSink::default();
+        let batching = BatchingSink::new(Box::new(sink.clone()), Duration::from_secs(60));
+        batching.deliver(&Severity::High, "Large Transfer", &AlertContext::for_chain("ethereum")).await.unwrap();
+
+        batching.flush().await.unwrap();
+        let delivered = sink.delivered();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!((&delivered[0].0, delivered[0].1.as_str()), (&Severity::High, "Large Transfer"));
+        assert_eq!(delivered[0].2.chain_name.as_deref(), Some("ethereum"));
+        // Nothing left for the window to send
+        batching.flush().await.unwrap();
+        assert_eq!(sink.delivered().len(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::events::Severity;
+use crate::state::AppState;
+
+const SEVERITIES: [Severity; 4] = [Severity::Critical, Severity::High, Severity::Medium, Severity::Low];
+
+/// Alert counts behind a periodic digest: alerts per severity and hits per rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Activity {
+    pub severities: HashMap<Severity, u64>,
+    pub rules: HashMap<String, u64>,
+}
+
+impl Activity {
+    /// Running totals since startup.
+    pub fn snapshot(state: &AppState) -> Self {
+        Self {
+            severities: state.severity_counts.lock().map(|c| c.clone()).unwrap_or_default(),
+            rules: state.rule_hits.lock().map(|h| h.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// What happened after `earlier`, a previous snapshot. The shared
+    /// counters only grow, so the interval's counts are the differences.
+    pub fn since(&self, earlier: &Activity) -> Activity {
+        fn diff<K: Clone + Eq + std::hash::Hash>(now: &HashMap<K, u64>, before: &HashMap<K, u64>) -> HashMap<K, u64> {
+            now.iter()
+                .map(|(k, n)| (k.clone(), n.saturating_sub(before.get(k).copied().unwrap_or(0))))
+                .filter(|(_, n)| *n > 0)
+                .collect()
+        }
+        Activity { severities: diff(&self.severities, &earlier.severities), rules: diff(&self.rules, &earlier.rules) }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.severities.values().sum()
+    }
+
+    /// "Digest (last 1 h): 12 alert(s), 1 Critical, 3 High, 8 Medium" plus
+    /// the `top_rules` busiest rules, most hits first (ties by name).
+    /// `period` None labels running totals ("since startup").
+    pub fn message(&self, period: Option<Duration>, top_rules: usize) -> String {
+        let span = match period {
+            Some(period) => format!("last {}", describe_period(period)),
+            None => "since startup".to_string(),
+        };
+        let mut text = format!("Digest ({}): {} alert(s)", span, self.total());
+        for severity in &SEVERITIES {
+            if let Some(n) = self.severities.get(severity).filter(|n| **n > 0) {
+                text.push_str(&format!(", {} {:?}", n, severity));
+            }
+        }
+        let mut rules: Vec<(&String, &u64)> = self.rules.iter().filter(|(_, n)| **n > 0).collect();
+        rules.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        if top_rules > 0 && !rules.is_empty() {
+            text.push_str("\nTop rules:");
+            for (rule, hits) in rules.iter().take(top_rules) {
+                text.push_str(&format!("\n- {}: {}", rule, hits));
+            }
+            if rules.len() > top_rules {
+                text.push_str(&format!("\n… and {} more", rules.len() - top_rules));
+            }
+        }
+        text
+    }
+}
+
+/// "45 s", "60 min" or "24 h", whichever unit divides evenly.
+fn describe_period(period: Duration) -> String {
+    let secs = period.as_secs();
+    if secs >= 3600 && secs.is_multiple_of(3600) {
+        format!("{} h", secs / 3600)
+    } else if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(state: &AppState, severity: Severity, rule: &str, message: &str) {
+        state.add_alert(severity, "ethereum".into(), message.into(), None);
+        state.record_rule_hit(rule.into());
+    }
+
+    #[test]
+    fn digest_counts_the_interval_by_severity_and_rule() {
+        let state = AppState::new();
+        alert(&state, Severity::Low, "ThresholdRule", "Small Transfer");
+        let start = Activity::snapshot(&state);
+
+        alert(&state, Severity::Critical, "OwnershipRule", "Ownership transferred");
+        for i in 0..3 {
+            alert(&state, Severity::High, "ThresholdRule", &format!("Large Transfer #{}", i));
+        }
+        alert(&state, Severity::Medium, "VelocityRule", "Transfer Burst");
+        alert(&state, Severity::Medium, "FanOutRule", "Transfer Fan-Out");
+
+        let interval = Activity::snapshot(&state).since(&start);
+        assert_eq!(interval.total(), 6);
+        assert_eq!(
+            interval.message(Some(Duration::from_secs(3600)), 2),
+            "Digest (last 1 h): 6 alert(s), 1 Critical, 3 High, 2 Medium\n\
+             Top rules:\n- ThresholdRule: 3\n- FanOutRule: 1\n… and 2 more",
+        );
+
+        // Running totals include the alert from before the interval
+        let totals = Activity::snapshot(&state);
+        assert!(totals.message(None, 1).starts_with("Digest (since startup): 7 alert(s), 1 Critical, 3 High, 2 Medium, 1 Low\nTop rules:\n- ThresholdRule: 4"));
+
+        // A quiet interval is empty, so no digest goes out
+        assert_eq!(totals.since(&totals), Activity::default());
+    }
+
+    #[test]
+    fn periods_use_the_largest_even_unit() {
+        assert_eq!(describe_period(Duration::from_secs(45)), "45 s");
+        assert_eq!(describe_period(Duration::from_secs(1800)), "30 min");
+        assert_eq!(describe_period(Duration::from_secs(86_400)), "24 h");
+        assert_eq!(describe_period(Duration::from_secs(5400)), "90 min");
+    }
+}
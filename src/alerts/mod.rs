@@ -1,135 +1,637 @@
-use serde::Serialize;
-use reqwest::Client;
-use tracing::{info, error, warn};
+use tracing::{error, info, warn};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 use tokio::sync::Mutex;
-use std::time::{Instant, Duration};
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
+use alloy::primitives::{Address, B256};
 
-use crate::events::Severity;
+use crate::config::{parse_time_of_day, AlertsConfig};
+use crate::events::{contract_label, NormalizedEvent, Severity};
+use crate::state::AppState;
+use crate::tokens::TokenMetadata;
+use telegram::AckRegistry;
 
-#[derive(Debug, Serialize)]
-struct DiscordEmbed {
-    title: String,
-    description: String,
-    color: u32,
-    fields: Vec<EmbedField>,
+pub mod batch;
+pub mod digest;
+pub mod sinks;
+pub mod telegram;
+pub mod template;
+
+pub use sinks::AlertSink;
+
+/// Alerts listed individually in a quiet-hours digest; the rest are counted.
+const DIGEST_MAX_LINES: usize = 20;
+
+const SELF_TEST_MESSAGE: &str = "Watchdog started: alert channel self-test, no action needed";
+
+/// What an alert is about. On-chain fields are `None` for synthetic alerts
+/// (heartbeats, replays) that have no originating log.
+#[derive(Debug, Clone, Default)]
+pub struct AlertContext {
+    pub chain_name: Option<String>,
+    pub tx_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub contract_address: Option<Address>,
+    // The `[[contracts]]` name, preferred over the token symbol in labels
+    pub contract_name: Option<String>,
+    // The alert as the TUI history has it (before templating), so a channel
+    // acknowledgement marks the same entry
+    pub ack_message: Option<String>,
+    pub data: Option<serde_json::Value>,
+    // Metadata of the contract, when it is a token we could read
+    pub token: Option<TokenMetadata>,
 }
 
-#[derive(Debug, Serialize)]
-struct EmbedField {
-    name: String,
-    value: String,
-    inline: bool,
+impl AlertContext {
+    pub fn from_event(event: &NormalizedEvent) -> Self {
+        Self {
+            chain_name: Some(event.chain_name.clone()),
+            tx_hash: Some(event.tx_hash),
+            block_number: Some(event.block_number),
+            contract_address: Some(event.contract_address),
+            contract_name: Some(event.contract_name.clone()).filter(|name| !name.is_empty()),
+            ack_message: None,
+            data: Some(event.data.clone()),
+            token: None,
+        }
+    }
+
+    pub fn with_token(mut self, token: Option<TokenMetadata>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_ack_message(mut self, message: String) -> Self {
+        self.ack_message = Some(message);
+        self
+    }
+
+    pub fn for_chain(chain_name: &str) -> Self {
+        Self { chain_name: Some(chain_name.to_string()), ..Default::default() }
+    }
+
+    /// Investigation fields that are present, as (label, value) pairs.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if let Some(chain) = &self.chain_name {
+            fields.push(("Chain", chain.clone()));
+        }
+        if let Some(contract) = self.contract_address {
+            let label = match &self.contract_name {
+                Some(name) => contract_label(name, contract),
+                None => TokenMetadata::label(self.token.as_ref(), contract),
+            };
+            fields.push(("Contract", label));
+        }
+        if let Some(block) = self.block_number {
+            fields.push(("Block", block.to_string()));
+        }
+        if let Some(tx_hash) = self.tx_hash {
+            fields.push(("Tx Hash", tx_hash.to_string()));
+        }
+        fields
+    }
+}
+
+/// Parsed `quiet_hours`: minutes after midnight UTC, end exclusive.
+#[derive(Debug, Clone)]
+struct QuietHours {
+    start: u32,
+    end: u32,
+    bypass: Severity,
+}
+
+impl QuietHours {
+    fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute_of_day)
+        } else {
+            // Wraps midnight, e.g. 22:00-07:00
+            minute_of_day >= self.start || minute_of_day < self.end
+        }
+    }
+
+    fn holds(&self, severity: &Severity, minute_of_day: u32) -> bool {
+        *severity < self.bypass && *severity < Severity::Critical && self.contains(minute_of_day)
+    }
+}
+
+/// Current UTC time as minutes after midnight.
+fn utc_minute_of_day() -> u32 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    ((secs % 86_400) / 60) as u32
 }
 
-#[derive(Debug, Serialize)]
-struct DiscordPayload {
-    content: Option<String>,
-    embeds: Vec<DiscordEmbed>,
+/// A Critical alert repeated inside its cooldown window. Instead of going
+/// silent, the repeats are counted and reported once the window elapses.
+struct SuppressedAlert {
+    message: String,
+    ctx: AlertContext,
+    count: u64,
 }
 
-use crate::config::AlertsConfig;
+/// Per-channel outcome of `AlertManager::self_test`, in channel order.
+#[derive(Debug, Default)]
+pub struct SelfTestReport {
+    pub results: Vec<(&'static str, Result<(), String>)>,
+}
 
-#[derive(Debug, Serialize)]
-struct TelegramPayload {
-    chat_id: String,
-    text: String,
-    parse_mode: String,
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.results.is_empty() {
+            return write!(f, "Alert self-test: no channels configured");
+        }
+        let failed = self.results.iter().filter(|(_, result)| result.is_err()).count();
+        write!(f, "Alert self-test: {}/{} channel(s) ok", self.results.len() - failed, self.results.len())?;
+        for (channel, result) in &self.results {
+            match result {
+                Ok(()) => write!(f, "\n  {}: ok", channel)?,
+                Err(e) => write!(f, "\n  {}: FAILED ({})", channel, e)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 pub struct AlertManager {
-    client: Client,
-    config: AlertsConfig,
+    sinks: Vec<Box<dyn AlertSink>>,
     last_alerts: Mutex<HashMap<String, Instant>>,
-    cooldown: Duration,
+    suppressed: Mutex<HashMap<String, SuppressedAlert>>, // Critical repeats awaiting a summary
+    quiet_hours: Option<QuietHours>,
+    held: Mutex<Vec<(Severity, String, AlertContext)>>, // Held during quiet hours for the digest
+    minute_of_day: fn() -> u32, // UTC clock for quiet hours
+    default_cooldown: Duration,
+    cooldowns: HashMap<Severity, Duration>,
+    min_severity: Severity, // Below it alerts stay on the dashboard
+    telegram_acks: Option<Arc<AckRegistry>>, // Alerts behind Telegram Acknowledge buttons
 }
 
 impl AlertManager {
-    pub fn new(config: AlertsConfig) -> Self {
+    /// Sends to every channel configured in `config`.
+    pub fn new(config: AlertsConfig, explorers: HashMap<String, String>) -> Self {
+        let telegram_acks = config.telegram_ack_buttons.then(|| Arc::new(AckRegistry::default()));
+        let sinks = sinks::from_config(&config, explorers, telegram_acks.clone());
+        Self { telegram_acks, ..Self::with_sinks(config, sinks) }
+    }
+
+    /// Applies `config`'s cooldowns, quiet hours and `min_severity`,
+    /// delivering to `sinks` instead of the configured channels.
+    pub fn with_sinks(config: AlertsConfig, sinks: Vec<Box<dyn AlertSink>>) -> Self {
+        let mut cooldowns = HashMap::new();
+        for (name, secs) in &config.cooldowns {
+            match Severity::from_name(name) {
+                Some(severity) => { cooldowns.insert(severity, Duration::from_secs(*secs)); }
+                None => warn!("Ignoring cooldown for unknown severity: {}", name),
+            }
+        }
+
+        let quiet_hours = config.quiet_hours.as_ref().and_then(|q| {
+            match (parse_time_of_day(&q.start), parse_time_of_day(&q.end)) {
+                (Some(start), Some(end)) => Some(QuietHours {
+                    start,
+                    end,
+                    bypass: Severity::from_name(&q.bypass_severity).unwrap_or(Severity::High),
+                }),
+                _ => {
+                    warn!("Ignoring quiet_hours with invalid times: {} - {}", q.start, q.end);
+                    None
+                }
+            }
+        });
+
         Self {
-            client: Client::new(),
-            config,
+            sinks,
+            quiet_hours,
+            held: Mutex::new(Vec::new()),
+            minute_of_day: utc_minute_of_day,
+            default_cooldown: Duration::from_secs(config.cooldown_secs),
+            cooldowns,
+            min_severity: config.min_severity.as_deref().and_then(Severity::from_name).unwrap_or(Severity::Low),
             last_alerts: Mutex::new(HashMap::new()),
-            cooldown: Duration::from_secs(60),
+            suppressed: Mutex::new(HashMap::new()),
+            telegram_acks: None,
         }
     }
 
-    pub async fn send_alert(&self, severity: Severity, message: String) {
+    /// Alerts sent with a Telegram Acknowledge button, for `telegram::poll_acks`.
+    pub fn telegram_acks(&self) -> Option<Arc<AckRegistry>> {
+        self.telegram_acks.clone()
+    }
+
+    fn cooldown_for(&self, severity: &Severity) -> Duration {
+        self.cooldowns.get(severity).copied().unwrap_or(self.default_cooldown)
+    }
+
+    /// Whether `severity` clears `[alerts] min_severity`; alerts below it are
+    /// only recorded, never sent.
+    pub fn sends(&self, severity: &Severity) -> bool {
+        *severity >= self.min_severity
+    }
+
+    pub async fn send_alert(&self, severity: Severity, message: String, ctx: &AlertContext) {
+        if !self.sends(&severity) {
+            return;
+        }
         // Rate Limit Key: Severity + Message
         let key = format!("{:?}:{}", severity, message);
-        
-        let mut history = self.last_alerts.lock().await;
-        if let Some(last_time) = history.get(&key) {
-            if last_time.elapsed() < self.cooldown {
-                warn!("Alert suppressed (Rate Limit): {}", message);
-                return;
+        let cooldown = self.cooldown_for(&severity);
+
+        // A zero cooldown never suppresses
+        let mut message = message;
+        if !cooldown.is_zero() {
+            let mut history = self.last_alerts.lock().await;
+            if let Some(last_time) = history.get(&key) {
+                if last_time.elapsed() < cooldown {
+                    warn!("Alert suppressed (Rate Limit): {}", message);
+                    if severity == Severity::Critical {
+                        let mut suppressed = self.suppressed.lock().await;
+                        let entry = suppressed.entry(key).or_insert_with(|| SuppressedAlert {
+                            message: message.clone(),
+                            ctx: ctx.clone(),
+                            count: 0,
+                        });
+                        entry.count += 1;
+                    }
+                    return;
+                }
             }
+            // Window over before the flush task got to it: fold the count in
+            if let Some(pending) = self.suppressed.lock().await.remove(&key) {
+                message = format!("{} (+{} more occurrences in the last {}s)", message, pending.count, cooldown.as_secs());
+            }
+            history.insert(key, Instant::now());
+        }
+
+        if self.quiet_hours.as_ref().is_some_and(|q| q.holds(&severity, (self.minute_of_day)())) {
+            info!("Quiet hours, holding alert for digest: {}", message);
+            self.held.lock().await.push((severity, message, ctx.clone()));
+            return;
+        }
+
+        self.dispatch(&severity, &message, ctx).await;
+    }
+
+    /// Once quiet hours are over, sends everything held during them as one
+    /// digest at the highest held severity. Call periodically.
+    pub async fn flush_quiet_digest(&self) {
+        let Some(quiet) = &self.quiet_hours else { return };
+        if quiet.contains((self.minute_of_day)()) {
+            return;
+        }
+        let held = std::mem::take(&mut *self.held.lock().await);
+        let Some(severity) = held.iter().map(|(s, _, _)| s.clone()).max() else { return };
+
+        let mut digest = format!("Quiet hours digest: {} alert(s) held", held.len());
+        for (severity, message, ctx) in held.iter().take(DIGEST_MAX_LINES) {
+            digest.push_str(&format!("\n- [{:?}] {} ({})", severity, message, ctx.chain_name.as_deref().unwrap_or("-")));
+        }
+        if held.len() > DIGEST_MAX_LINES {
+            digest.push_str(&format!("\n… and {} more", held.len() - DIGEST_MAX_LINES));
         }
-        history.insert(key, Instant::now());
+        self.dispatch(&severity, &digest, &AlertContext::default()).await;
+    }
 
+    /// Sends a "N more occurrences" summary for every Critical alert whose
+    /// cooldown window has elapsed since it was last sent, and restarts the
+    /// window. Call periodically; further repeats are counted again. Repeats
+    /// of alerts `acknowledged(severity, chain, message)` are dropped unsent.
+    pub async fn flush_suppressed(&self, acknowledged: impl Fn(&Severity, &str, &str) -> bool) {
+        let cooldown = self.cooldown_for(&Severity::Critical);
+        let due: Vec<SuppressedAlert> = {
+            let mut history = self.last_alerts.lock().await;
+            let mut suppressed = self.suppressed.lock().await;
+            let keys: Vec<String> = suppressed
+                .keys()
+                .filter(|key| history.get(*key).is_none_or(|last| last.elapsed() >= cooldown))
+                .cloned()
+                .collect();
+            keys.into_iter()
+                .filter_map(|key| {
+                    history.insert(key.clone(), Instant::now());
+                    suppressed.remove(&key)
+                })
+                .collect()
+        };
+
+        for pending in due {
+            let chain = pending.ctx.chain_name.as_deref().unwrap_or_default();
+            if acknowledged(&Severity::Critical, chain, &pending.message) {
+                info!("Dropping {} repeat(s) of acknowledged alert: {}", pending.count, pending.message);
+                continue;
+            }
+            let message = format!(
+                "{} more occurrences in the last {}s: {}",
+                pending.count,
+                cooldown.as_secs(),
+                pending.message
+            );
+            self.dispatch(&Severity::Critical, &message, &pending.ctx).await;
+        }
+    }
+
+    /// Re-sends an already recorded alert (e.g. after a channel outage).
+    /// Bypasses the cooldown; callers must not re-record it in `AppState`.
+    pub async fn replay_alert(&self, severity: Severity, message: &str, ctx: &AlertContext) {
+        let message = format!("[REPLAY] {}", message);
+        self.dispatch(&severity, &message, ctx).await;
+    }
+
+    /// Replays the last `n` alerts recorded in `state`, skipping those below
+    /// `min_severity` as live dispatch would. Returns how many were sent.
+    pub async fn replay_recent(&self, state: &AppState, n: usize) -> usize {
+        let recent = state.recent_alerts(n);
+        info!("Replaying {} recorded alert(s)", recent.len());
+        let mut sent = 0;
+        for (severity, chain, msg) in recent.into_iter().filter(|(s, _, _)| self.sends(s)) {
+            self.replay_alert(severity, &msg, &AlertContext::for_chain(&chain)).await;
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Sends a periodic digest straight to every channel; it is never a
+    /// repeat, so cooldowns and quiet hours don't apply.
+    pub async fn send_digest(&self, severity: Severity, message: &str) {
+        self.dispatch(&severity, message, &AlertContext::default()).await;
+    }
+
+    /// Sends a notice about the watchdog itself (e.g. a rule auto-muted)
+    /// straight to every channel, so `min_severity`, cooldowns and quiet
+    /// hours can't swallow it.
+    pub async fn send_notice(&self, severity: Severity, message: &str, ctx: &AlertContext) {
+        self.dispatch(&severity, message, ctx).await;
+    }
+
+    async fn dispatch(&self, severity: &Severity, message: &str, ctx: &AlertContext) {
         info!("Sending Alert: [{:?}] {}", severity, message);
-        
+
         // Dispatch to all configured providers
-        self.send_discord_alert(&severity, &message).await;
-        self.send_telegram_alert(&severity, &message).await;
+        for sink in &self.sinks {
+            if let Err(e) = sink.deliver(severity, message, ctx).await {
+                error!("Failed to send {} alert: {}", sink.name(), e);
+            }
+        }
     }
 
-    async fn send_discord_alert(&self, severity: &Severity, message: &str) {
-        if self.config.webhook_url.is_empty() { return; }
+    /// Sends every batch still waiting for its window to close; call on
+    /// shutdown so no alert is left behind.
+    pub async fn flush(&self) {
+        for sink in &self.sinks {
+            if let Err(e) = sink.flush().await {
+                error!("Failed to send {} alert batch: {}", sink.name(), e);
+            }
+        }
+    }
 
-        let color = match severity {
-            Severity::Critical => 0xFF0000,
-            Severity::High => 0xE67E22,
-            Severity::Medium => 0xF1C40F,
-            Severity::Low => 0x3498DB,
-        };
+    /// Sends a benign "Watchdog started" message through every channel and
+    /// reports how each one fared (`--test-alerts`, `alerts.self_test`). It
+    /// goes out as Critical so channels routed by severity are tested too.
+    pub async fn self_test(&self) -> SelfTestReport {
+        let mut results = Vec::new();
+        for sink in &self.sinks {
+            // Flushed straight away, so a batching channel reports the real outcome
+            let result = match sink.deliver(&Severity::Critical, SELF_TEST_MESSAGE, &AlertContext::default()).await {
+                Ok(()) => sink.flush().await,
+                Err(e) => Err(e),
+            };
+            results.push((sink.name(), result.map_err(|e| e.to_string())));
+        }
+        SelfTestReport { results }
+    }
+}
 
-        let embed = DiscordEmbed {
-            title: format!("🚨 EVM Watchdog Alert: {:?}", severity),
-            description: message.to_string(),
-            color,
-            fields: vec![
-                EmbedField { name: "Severity".to_string(), value: format!("{:?}", severity), inline: true },
-                EmbedField { name: "Timestamp".to_string(), value: format!("{:?}", Instant::now()), inline: true },
-            ],
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sinks::RecordingSink;
+    use crate::events::EventType;
+    use crate::rules::{RuleEngine, Threshold, ThresholdRule};
+    use alloy::primitives::U256;
+    use serde_json::json;
 
-        let payload = DiscordPayload {
-            content: None,
-            embeds: vec![embed],
-        };
+    /// A manager with the `[alerts]` settings in `toml` delivering to `sink`.
+    fn manager(toml: &str, sink: &RecordingSink) -> AlertManager {
+        let config = AlertsConfig::from_toml(&format!("webhook_url = \"\"\n{}", toml));
+        AlertManager::with_sinks(config, vec![Box::new(sink.clone())])
+    }
 
-        if let Err(e) = self.client.post(&self.config.webhook_url).json(&payload).send().await {
-            error!("Failed to send Discord alert: {}", e);
-        } else {
-             info!("Discord Alert Sent");
+    #[tokio::test]
+    async fn rule_engine_alerts_reach_the_sink() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1_000_000_000u64)), Severity::High)));
+        let state = AppState::new();
+        let sink = RecordingSink::default();
+        let alerts = manager("", &sink);
+
+        let small = NormalizedEvent::sample(EventType::Transfer, json!({ "value": "999" }));
+        let large = NormalizedEvent::sample(EventType::Transfer, json!({ "value": "5000000000" }));
+        for event in [&small, &large] {
+            for (_, message, severity) in engine.process(event, &state) {
+                alerts.send_alert(severity, message, &AlertContext::from_event(event)).await;
+            }
         }
+
+        assert_eq!(sink.messages(), ["Large Transfer Detected: 5000.0 > 1000.0"]);
+        let (severity, _, ctx) = &sink.delivered()[0];
+        assert_eq!(*severity, Severity::High);
+        assert_eq!(ctx.tx_hash, Some(large.tx_hash));
+        assert_eq!(ctx.contract_name.as_deref(), Some("USDT"));
     }
 
-    async fn send_telegram_alert(&self, severity: &Severity, message: &str) {
-        let token = match &self.config.telegram_bot_token {
-            Some(t) if !t.is_empty() => t,
-            _ => return,
-        };
-        let chat_id = match &self.config.telegram_chat_id {
-            Some(id) if !id.is_empty() => id,
-            _ => return,
-        };
+    #[tokio::test]
+    async fn alerts_below_min_severity_are_dropped() {
+        let sink = RecordingSink::default();
+        let alerts = manager("min_severity = \"High\"", &sink);
+        let ctx = AlertContext::for_chain("ethereum");
+        for (severity, message) in [(Severity::Low, "low"), (Severity::Medium, "medium"), (Severity::High, "high"), (Severity::Critical, "critical")] {
+            alerts.send_alert(severity, message.into(), &ctx).await;
+        }
+        assert_eq!(sink.messages(), ["high", "critical"]);
+        assert!(!alerts.sends(&Severity::Medium));
+    }
 
-        let telegram_msg = format!("🚨 *EVM Watchdog Alert* 🚨\n\n*Severity:* {:?}\n*Message:* {}\n*Time:* {:?}", severity, message, Instant::now());
-        
-        let payload = TelegramPayload {
-            chat_id: chat_id.clone(),
-            text: telegram_msg,
-            parse_mode: "Markdown".to_string(),
-        };
+    #[tokio::test]
+    async fn cooldown_applies_per_severity() {
+        let sink = RecordingSink::default();
+        let alerts = manager("cooldown_secs = 60\ncooldowns = { Critical = 0 }", &sink);
+        let ctx = AlertContext::for_chain("ethereum");
+        for _ in 0..2 {
+            alerts.send_alert(Severity::Critical, "Ownership transferred".into(), &ctx).await;
+            alerts.send_alert(Severity::Low, "Small Transfer".into(), &ctx).await;
+        }
+        assert_eq!(sink.messages(), ["Ownership transferred", "Small Transfer", "Ownership transferred"]);
+    }
 
-        let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
-        
-        if let Err(e) = self.client.post(&url).json(&payload).send().await {
-            error!("Failed to send Telegram alert: {}", e);
-        } else {
-             info!("Telegram Alert Sent");
+    #[tokio::test]
+    async fn critical_bursts_send_first_then_a_summary() {
+        let sink = RecordingSink::default();
+        let alerts = manager("cooldowns = { Critical = 1 }", &sink);
+        let ctx = AlertContext::for_chain("ethereum");
+        for _ in 0..4 {
+            alerts.send_alert(Severity::Critical, "Ownership transferred".into(), &ctx).await;
+        }
+        assert_eq!(sink.messages(), ["Ownership transferred"]);
+
+        // Nothing summarized until the window has elapsed
+        alerts.flush_suppressed(|_, _, _| false).await;
+        assert_eq!(sink.messages().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(1050)).await;
+        alerts.flush_suppressed(|_, _, _| false).await;
+        assert_eq!(sink.messages(), ["Ownership transferred", "3 more occurrences in the last 1s: Ownership transferred"]);
+        assert_eq!(sink.delivered()[1].0, Severity::Critical);
+        // The count starts over
+        alerts.flush_suppressed(|_, _, _| false).await;
+        assert_eq!(sink.messages().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn acknowledged_alerts_get_no_repeat_summary() {
+        let sink = RecordingSink::default();
+        let alerts = manager("cooldowns = { Critical = 1 }", &sink);
+        let state = AppState::new();
+        let ctx = AlertContext::for_chain("ethereum");
+        for message in ["Ownership transferred", "Ownership transferred", "Code changed", "Code changed"] {
+            alerts.send_alert(Severity::Critical, message.into(), &ctx).await;
         }
+        state.acknowledge(&Severity::Critical, "ethereum", "Ownership transferred");
+
+        tokio::time::sleep(Duration::from_millis(1050)).await;
+        alerts.flush_suppressed(|severity, chain, message| state.is_acknowledged(severity, chain, message)).await;
+        assert_eq!(sink.messages(), ["Ownership transferred", "Code changed", "1 more occurrences in the last 1s: Code changed"]);
+    }
+
+    #[tokio::test]
+    async fn self_test_reports_every_channel_and_fails_on_any() {
+        let (working, broken) = (RecordingSink::default(), RecordingSink::failing());
+        let config = AlertsConfig::from_toml("webhook_url = \"\"");
+        let alerts = AlertManager::with_sinks(config.clone(), vec![Box::new(working.clone()), Box::new(broken)]);
+
+        let report = alerts.self_test().await;
+        assert!(!report.passed());
+        assert_eq!(report.to_string(), "Alert self-test: 1/2 channel(s) ok\n  Recording: ok\n  Recording: FAILED (rejected with 401 Unauthorized)");
+        assert_eq!(working.messages(), [SELF_TEST_MESSAGE]);
+        assert_eq!(working.delivered()[0].0, Severity::Critical);
+
+        let alerts = AlertManager::with_sinks(config.clone(), vec![Box::new(working)]);
+        assert!(alerts.self_test().await.passed());
+        let alerts = AlertManager::with_sinks(config, Vec::new());
+        let report = alerts.self_test().await;
+        assert!(report.passed());
+        assert_eq!(report.to_string(), "Alert self-test: no channels configured");
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_hold_alerts_for_a_digest() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static MINUTE: AtomicU32 = AtomicU32::new(23 * 60);
+
+        let sink = RecordingSink::default();
+        let mut alerts = manager("quiet_hours = { start = \"22:00\", end = \"07:00\" }", &sink);
+        alerts.minute_of_day = || MINUTE.load(Ordering::SeqCst);
+        let ctx = AlertContext::for_chain("ethereum");
+
+        alerts.send_alert(Severity::Low, "Small Transfer".into(), &ctx).await;
+        alerts.send_alert(Severity::Medium, "Large Transfer".into(), &ctx).await;
+        // High (the default bypass) and Critical go out at once
+        alerts.send_alert(Severity::High, "Drainer Approval".into(), &ctx).await;
+        alerts.send_alert(Severity::Critical, "Ownership transferred".into(), &ctx).await;
+        assert_eq!(sink.messages(), ["Drainer Approval", "Ownership transferred"]);
+
+        // Still quiet at 06:59
+        MINUTE.store(6 * 60 + 59, Ordering::SeqCst);
+        alerts.flush_quiet_digest().await;
+        assert_eq!(sink.messages().len(), 2);
+
+        MINUTE.store(7 * 60, Ordering::SeqCst);
+        alerts.flush_quiet_digest().await;
+        let delivered = sink.delivered();
+        assert_eq!(delivered.len(), 3);
+        assert_eq!(delivered[2].0, Severity::Medium, "at the highest held severity");
+        assert_eq!(
+            delivered[2].1,
+            "Quiet hours digest: 2 alert(s) held\n- [Low] Small Transfer (ethereum)\n- [Medium] Large Transfer (ethereum)"
+        );
+        // Nothing left to flush
+        alerts.flush_quiet_digest().await;
+        assert_eq!(sink.messages().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn discord_routes_take_what_clears_their_severity() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        let config = AlertsConfig::from_toml(&format!(
+            "webhook_url = [{{ url = \"{0}/all\" }}, {{ url = \"{0}/critical\", min_severity = \"Critical\" }}]",
+            server.uri()
+        ));
+        let alerts = AlertManager::new(config, HashMap::new());
+        let ctx = AlertContext::for_chain("ethereum");
+        alerts.send_alert(Severity::Critical, "Ownership transferred".into(), &ctx).await;
+        alerts.send_alert(Severity::Low, "Small Transfer".into(), &ctx).await;
+
+        let mut hits: Vec<(String, String)> = server.received_requests().await.unwrap().iter()
+            .map(|r| {
+                let body: serde_json::Value = r.body_json().unwrap();
+                (r.url.path().to_string(), body["embeds"][0]["description"].as_str().unwrap_or_default().to_string())
+            })
+            .collect();
+        hits.sort();
+        assert_eq!(hits, [
+            ("/all".to_string(), "Ownership transferred".to_string()),
+            ("/all".to_string(), "Small Transfer".to_string()),
+            ("/critical".to_string(), "Ownership transferred".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn replay_resends_recent_alerts_to_the_channels() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "severity": "High", "message": "[REPLAY] second", "chain": "ethereum" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "message": "[REPLAY] third" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = AlertsConfig::from_toml(&format!(
+            "webhook_url = \"\"\ngeneric_webhook_url = \"{}/hook\"\nmin_severity = \"Medium\"",
+            server.uri()
+        ));
+        let alerts = AlertManager::new(config, HashMap::new());
+        let state = AppState::new();
+        state.add_alert(Severity::High, "ethereum".into(), "first".into(), None);
+        state.add_alert(Severity::High, "ethereum".into(), "second".into(), None);
+        state.add_alert(Severity::Medium, "polygon".into(), "third".into(), None);
+        state.add_alert(Severity::Low, "polygon".into(), "fourth".into(), None);
+
+        let counts = state.severity_counts.lock().unwrap().clone();
+
+        // The last three, minus the Low one under min_severity
+        assert_eq!(alerts.replay_recent(&state, 3).await, 2);
+        server.verify().await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        // Replays go out again without being recorded as new alerts
+        assert_eq!(state.alert_history.lock().unwrap().len(), 4);
+        assert_eq!(*state.severity_counts.lock().unwrap(), counts);
     }
 }
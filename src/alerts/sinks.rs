@@ -0,0 +1,1093 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::json;
+use reqwest::Client;
+use reqwest::multipart::{Form, Part};
+use tracing::{info, error, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use alloy::primitives::{keccak256, Address, B256};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+use crate::config::{AlertsConfig, SmtpTls};
+use crate::events::Severity;
+use super::batch::{channel_key, BatchingSink};
+use super::telegram::{ack_keyboard, AckRegistry, InlineKeyboardMarkup};
+use super::AlertContext;
+
+/// A destination for alerts. `AlertManager` decides *whether* to send
+/// (cooldowns, quiet hours); sinks only decide *how*. Delivery failures are
+/// returned for the manager to log (or the self-test to report); an alert
+/// the sink skips on purpose (below its severity bar, dry run) is `Ok`.
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// Channel name for logs and the self-test report, e.g. "Discord".
+    fn name(&self) -> &'static str;
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()>;
+
+    /// Sends anything the sink is holding back (see `BatchingSink`).
+    async fn flush(&self) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+/// In-memory sink for tests: keeps every delivered alert. Clones share the
+/// same record, so a test can keep one while the manager owns another.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSink {
+    delivered: Arc<std::sync::Mutex<Vec<(Severity, String, AlertContext)>>>,
+    failing: bool,
+}
+
+#[cfg(test)]
+impl RecordingSink {
+    /// A sink whose every delivery fails, as a misconfigured channel would.
+    pub fn failing() -> Self {
+        Self { failing: true, ..Self::default() }
+    }
+
+    /// (Severity, Message, Context) of each delivery, in order.
+    pub fn delivered(&self) -> Vec<(Severity, String, AlertContext)> {
+        self.delivered.lock().unwrap().clone()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.delivered().into_iter().map(|(_, message, _)| message).collect()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl AlertSink for RecordingSink {
+    fn name(&self) -> &'static str {
+        "Recording"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        if self.failing {
+            eyre::bail!("rejected with 401 Unauthorized");
+        }
+        self.delivered.lock().unwrap().push((severity.clone(), message.to_string(), ctx.clone()));
+        Ok(())
+    }
+}
+
+/// Builds a sink for every channel configured in `[alerts]`.
+/// `telegram_acks` puts an Acknowledge button on Critical Telegram alerts.
+pub fn from_config(config: &AlertsConfig, explorers: HashMap<String, String>, telegram_acks: Option<Arc<AckRegistry>>) -> Vec<Box<dyn AlertSink>> {
+    let http = Http {
+        client: Client::new(),
+        dry_run: config.dry_run,
+        explorers: Arc::new(explorers),
+        attach_event_data: config.attach_event_data,
+        attachment_threshold: config.attachment_threshold,
+    };
+    let set = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+    let mut sinks: Vec<Box<dyn AlertSink>> = Vec::new();
+
+    for route in config.webhook_url.routes() {
+        let min_severity = Severity::from_name(&route.min_severity).unwrap_or(Severity::Low);
+        sinks.push(Box::new(DiscordSink { http: http.clone(), url: route.url, min_severity }));
+    }
+    if let (Some(token), Some(chat_id)) = (set(&config.telegram_bot_token), set(&config.telegram_chat_id)) {
+        sinks.push(Box::new(TelegramSink { http: http.clone(), token, chat_id, acks: telegram_acks }));
+    }
+    if let Some(url) = set(&config.generic_webhook_url) {
+        sinks.push(Box::new(GenericWebhookSink { http: http.clone(), url, secret: set(&config.webhook_secret) }));
+    }
+    if let Some(url) = set(&config.slack_webhook_url) {
+        sinks.push(Box::new(SlackSink { http: http.clone(), url }));
+    }
+    if let Some(routing_key) = set(&config.pagerduty_routing_key) {
+        let min_severity = Severity::from_name(&config.pagerduty_min_severity).unwrap_or_else(|| {
+            tracing::warn!("Unknown pagerduty_min_severity '{}', paging on Critical only", config.pagerduty_min_severity);
+            Severity::Critical
+        });
+        sinks.push(Box::new(PagerDutySink { http: http.clone(), url: PAGERDUTY_EVENTS_URL.to_string(), routing_key, min_severity }));
+    }
+    if let (Some(homeserver), Some(token), Some(room_id)) =
+        (set(&config.matrix_homeserver), set(&config.matrix_access_token), set(&config.matrix_room_id))
+    {
+        sinks.push(Box::new(MatrixSink {
+            http: http.clone(),
+            homeserver,
+            token,
+            room_id,
+            txn_prefix: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default(),
+            txn: AtomicU64::new(0),
+        }));
+    }
+    if let Some(host) = set(&config.smtp_host) {
+        match email_sink(config, &host, http.clone()) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(e) => error!("Email alerts disabled: {}", e),
+        }
+    }
+    // Channels with a batch window coalesce their alerts
+    sinks.into_iter()
+        .map(|sink| match config.batch_windows.get(&channel_key(sink.name())).filter(|secs| **secs > 0) {
+            Some(secs) => Box::new(BatchingSink::new(sink, Duration::from_secs(*secs))) as Box<dyn AlertSink>,
+            None => sink,
+        })
+        .collect()
+}
+
+fn email_sink(config: &AlertsConfig, host: &str, http: Http) -> eyre::Result<EmailSink> {
+    let builder = match config.smtp_tls {
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+    };
+    let builder = match config.smtp_port {
+        Some(port) => builder.port(port),
+        None => builder,
+    };
+    let builder = match (&config.smtp_user, &config.smtp_pass) {
+        (Some(user), Some(pass)) => builder.credentials(Credentials::new(user.clone(), pass.clone())),
+        _ => builder,
+    };
+
+    let from = config.email_from.as_deref().unwrap_or_default().parse()?;
+    let to = config.email_to.iter().map(|to| to.parse()).collect::<Result<_, _>>()?;
+    let min_severity = Severity::from_name(&config.email_min_severity).unwrap_or(Severity::High);
+    Ok(EmailSink { http, transport: builder.build(), from, to, min_severity })
+}
+
+/// What the HTTP sinks share: the client, dry-run mode, explorer links and
+/// the event-data attachment policy.
+#[derive(Clone)]
+struct Http {
+    client: Client,
+    dry_run: bool,
+    explorers: Arc<HashMap<String, String>>, // ChainName -> explorer base URL
+    attach_event_data: bool,
+    attachment_threshold: usize,
+}
+
+impl Http {
+    /// `{explorer_url}/tx/{hash}`, or None for chains without an explorer and
+    /// for alerts with no real transaction (zero hash, e.g. simulated events).
+    fn tx_link(&self, ctx: &AlertContext) -> Option<String> {
+        let explorer = self.explorers.get(ctx.chain_name.as_ref()?)?;
+        let tx_hash = ctx.tx_hash.filter(|h| !h.is_zero())?;
+        Some(format!("{}/tx/{}", explorer.trim_end_matches('/'), tx_hash))
+    }
+
+    /// In dry-run mode, logs the rendered payload in place of sending it and
+    /// returns true; the caller must then skip the HTTP request.
+    fn dry_run<T: Serialize>(&self, channel: &str, payload: &T) -> bool {
+        if self.dry_run {
+            info!("[DRY RUN] {} payload: {}", channel, serde_json::to_string(payload).unwrap_or_default());
+        }
+        self.dry_run
+    }
+
+    fn event_details(&self, data: Option<&serde_json::Value>) -> EventDetails {
+        let data = match data {
+            Some(d) if self.attach_event_data && !d.is_null() => d,
+            _ => return EventDetails::None,
+        };
+        let json = serde_json::to_string_pretty(data).unwrap_or_default();
+        if json.len() > self.attachment_threshold {
+            EventDetails::Attachment(json)
+        } else {
+            EventDetails::Inline(json)
+        }
+    }
+}
+
+/// How the decoded event data travels with an alert.
+enum EventDetails {
+    None,
+    Inline(String),
+    Attachment(String),
+}
+
+/// Retries after a Discord 429 before the alert is given up.
+const DISCORD_MAX_RETRIES: u32 = 3;
+/// Longest `Retry-After` waited out; a longer ban would stall every alert
+/// queued behind this one, so the alert is dropped instead.
+const DISCORD_MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+struct DiscordSink {
+    http: Http,
+    url: String,
+    min_severity: Severity,
+}
+
+/// Ok for a 2xx response; otherwise the status and response body as the
+/// error, so a revoked token or mistyped URL reads as more than a drop.
+async fn check_status(resp: reqwest::Response) -> eyre::Result<()> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = resp.text().await.unwrap_or_default();
+    eyre::bail!("rejected with {} {}", status, body.trim())
+}
+
+/// Discord's `Retry-After` (seconds, possibly fractional). A missing or
+/// garbled header waits one second.
+fn retry_after(resp: &reqwest::Response) -> Duration {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or(Duration::from_secs(1))
+}
+
+#[async_trait]
+impl AlertSink for DiscordSink {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        // Each routed webhook only takes what clears its bar
+        if *severity < self.min_severity { return Ok(()); }
+
+        let details = &self.http.event_details(ctx.data.as_ref());
+        let color = severity_color(severity);
+
+        let mut embed = DiscordEmbed {
+            title: format!("🚨 EVM Watchdog Alert: {:?}", severity),
+            description: message.to_string(),
+            color,
+            fields: vec![
+                EmbedField { name: "Severity".to_string(), value: format!("{:?}", severity), inline: true },
+                EmbedField { name: "Timestamp".to_string(), value: format!("{:?}", Instant::now()), inline: true },
+            ],
+        };
+        for (name, value) in ctx.fields() {
+            // Hashes are too long to sit side by side
+            let inline = name != "Tx Hash";
+            embed.fields.push(EmbedField { name: name.to_string(), value, inline });
+        }
+        if let Some(link) = self.http.tx_link(ctx) {
+            embed.fields.push(EmbedField { name: "Explorer".to_string(), value: format!("[View Transaction]({})", link), inline: false });
+        }
+        if let EventDetails::Inline(json) = details {
+            embed.fields.push(EmbedField { name: "Event Data".to_string(), value: format!("```json\n{}\n```", json), inline: false });
+        }
+
+        let payload = DiscordPayload {
+            content: None,
+            embeds: vec![embed],
+        };
+        if self.http.dry_run("Discord", &payload) { return Ok(()); }
+
+        // Rebuilt per attempt: a multipart body can't be sent twice
+        let request = || {
+            let request = self.http.client.post(&self.url);
+            match details {
+                // Discord takes the message as `payload_json` alongside `files[n]` parts
+                EventDetails::Attachment(json) => {
+                    let form = Form::new()
+                        .text("payload_json", serde_json::to_string(&payload).unwrap_or_default())
+                        .part("files[0]", json_file(json));
+                    request.multipart(form)
+                }
+                _ => request.json(&payload),
+            }
+        };
+
+        let mut attempt = 0;
+        loop {
+            let resp = request().send().await?;
+            if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let wait = retry_after(&resp);
+                if attempt == DISCORD_MAX_RETRIES || wait > DISCORD_MAX_RETRY_AFTER {
+                    eyre::bail!("still rate limited (retry after {:?}), alert dropped", wait);
+                }
+                warn!("Discord rate limited, retrying in {:?}", wait);
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+                continue;
+            }
+            check_status(resp).await?;
+            info!("Discord Alert Sent");
+            return Ok(());
+        }
+    }
+}
+
+struct TelegramSink {
+    http: Http,
+    token: String,
+    chat_id: String,
+    acks: Option<Arc<AckRegistry>>,
+}
+
+#[async_trait]
+impl AlertSink for TelegramSink {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        let (token, chat_id) = (&self.token, &self.chat_id);
+        let details = &self.http.event_details(ctx.data.as_ref());
+
+        let mut telegram_msg = format!("🚨 *EVM Watchdog Alert* 🚨\n\n*Severity:* {:?}\n*Message:* {}\n*Time:* {:?}", severity, message, Instant::now());
+        for (name, value) in ctx.fields() {
+            telegram_msg.push_str(&format!("\n*{}:* `{}`", name, value));
+        }
+        if let Some(link) = self.http.tx_link(ctx) {
+            telegram_msg.push_str(&format!("\n[View Transaction]({})", link));
+        }
+        if let EventDetails::Inline(json) = details {
+            telegram_msg.push_str(&format!("\n```\n{}\n```", json));
+        }
+        // Critical alerts from a chain can be acknowledged from the chat
+        let reply_markup = match (&self.acks, &ctx.chain_name) {
+            (Some(acks), Some(chain)) if *severity == Severity::Critical => {
+                let ack_message = ctx.ack_message.clone().unwrap_or_else(|| message.to_string());
+                Some(ack_keyboard(acks.register(severity.clone(), chain.clone(), ack_message)))
+            }
+            _ => None,
+        };
+        if self.http.dry_run("Telegram", &json!({ "chat_id": chat_id, "text": telegram_msg, "reply_markup": reply_markup })) { return Ok(()); }
+
+        let request = match details {
+            // Oversized data goes out as a document with the alert as its caption
+            EventDetails::Attachment(json) => {
+                let mut form = Form::new()
+                    .text("chat_id", chat_id.clone())
+                    .text("caption", telegram_msg)
+                    .text("parse_mode", "Markdown")
+                    .part("document", json_file(json));
+                if let Some(markup) = &reply_markup {
+                    form = form.text("reply_markup", serde_json::to_string(markup)?);
+                }
+                self.http.client.post(format!("https://api.telegram.org/bot{}/sendDocument", token)).multipart(form)
+            }
+            _ => {
+                let payload = TelegramPayload {
+                    chat_id: chat_id.clone(),
+                    text: telegram_msg,
+                    parse_mode: "Markdown".to_string(),
+                    reply_markup,
+                };
+                self.http.client.post(format!("https://api.telegram.org/bot{}/sendMessage", token)).json(&payload)
+            }
+        };
+
+        check_status(request.send().await?).await?;
+        info!("Telegram Alert Sent");
+        Ok(())
+    }
+}
+
+struct GenericWebhookSink {
+    http: Http,
+    url: String,
+    secret: Option<String>,
+}
+
+/// Signature for a generic webhook body: lowercase hex of
+/// HMAC-SHA256(key = `webhook_secret`, message = the exact body bytes), sent
+/// as `X-Watchdog-Signature`. Receivers recompute it over the raw request
+/// body (before any JSON parsing) and compare in constant time.
+fn sign_body(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    alloy::hex::encode(mac.finalize().into_bytes())
+}
+
+#[async_trait]
+impl AlertSink for GenericWebhookSink {
+    fn name(&self) -> &'static str {
+        "Generic Webhook"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        let payload = GenericWebhookPayload {
+            severity: severity.clone(),
+            message: message.to_string(),
+            chain: ctx.chain_name.clone(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            tx_hash: ctx.tx_hash,
+            contract_address: ctx.contract_address,
+            contract_name: ctx.contract_name.clone(),
+        };
+        if self.http.dry_run("Generic Webhook", &payload) { return Ok(()); }
+
+        // Serialize once so the signed bytes are the bytes on the wire
+        let body = serde_json::to_vec(&payload)?;
+        let mut request = self.http.client.post(&self.url).header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.secret {
+            request = request.header("X-Watchdog-Signature", sign_body(secret, &body));
+        }
+        check_status(request.body(body).send().await?).await?;
+        info!("Generic Webhook Alert Sent");
+        Ok(())
+    }
+}
+
+struct SlackSink {
+    http: Http,
+    url: String,
+}
+
+#[async_trait]
+impl AlertSink for SlackSink {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        let emoji = match severity {
+            Severity::Critical => "🚨",
+            Severity::High => "🔴",
+            Severity::Medium => "🟠",
+            Severity::Low => "🔵",
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let chain = ctx.chain_name.as_deref().unwrap_or("-");
+
+        let payload = SlackPayload {
+            attachments: vec![SlackAttachment {
+                color: format!("#{:06X}", severity_color(severity)),
+                blocks: vec![
+                    json!({
+                        "type": "header",
+                        "text": { "type": "plain_text", "text": format!("{} EVM Watchdog Alert: {:?}", emoji, severity), "emoji": true },
+                    }),
+                    json!({
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": message },
+                    }),
+                    json!({
+                        "type": "context",
+                        "elements": [
+                            // Slack renders <!date^...> in the reader's local time
+                            { "type": "mrkdwn", "text": format!("*Chain:* {} | <!date^{}^{{date_short_pretty}} {{time_secs}}|{}>", chain, timestamp, timestamp) },
+                        ],
+                    }),
+                ],
+            }],
+        };
+        if self.http.dry_run("Slack", &payload) { return Ok(()); }
+
+        check_status(self.http.client.post(&self.url).json(&payload).send().await?).await?;
+        info!("Slack Alert Sent");
+        Ok(())
+    }
+}
+
+/// PagerDuty Events API v2 endpoint.
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Pages only at `min_severity` and above.
+struct PagerDutySink {
+    http: Http,
+    url: String,
+    routing_key: String,
+    min_severity: Severity,
+}
+
+#[async_trait]
+impl AlertSink for PagerDutySink {
+    fn name(&self) -> &'static str {
+        "PagerDuty"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        // Only page on-call for what clears the bar
+        if *severity < self.min_severity { return Ok(()); }
+
+        let payload = PagerDutyPayload {
+            routing_key: self.routing_key.clone(),
+            event_action: "trigger",
+            // Same alert re-firing updates the open incident instead of opening another
+            dedup_key: keccak256(format!("{:?}:{}", severity, message)).to_string(),
+            payload: PagerDutyDetails {
+                summary: message.to_string(),
+                source: ctx.chain_name.clone().unwrap_or_else(|| "evm-watchdog".to_string()),
+                severity: pagerduty_severity(severity),
+                custom_details: ctx.fields().into_iter().map(|(k, v)| (k.to_string(), json!(v))).collect(),
+            },
+        };
+        if self.http.dry_run("PagerDuty", &payload) { return Ok(()); }
+
+        check_status(self.http.client.post(&self.url).json(&payload).send().await?).await?;
+        info!("PagerDuty Alert Sent");
+        Ok(())
+    }
+}
+
+/// Posts into a Matrix room. Transaction ids are `<startup ms>-<counter>`,
+/// unique across restarts so the homeserver never drops a new alert as a
+/// retransmission.
+struct MatrixSink {
+    http: Http,
+    homeserver: String,
+    token: String,
+    room_id: String,
+    txn_prefix: u128,
+    txn: AtomicU64,
+}
+
+#[async_trait]
+impl AlertSink for MatrixSink {
+    fn name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        let txn_id = format!("{}-{}", self.txn_prefix, self.txn.fetch_add(1, Ordering::Relaxed));
+        let Some(url) = matrix_url(&self.homeserver, &self.room_id, &txn_id) else {
+            eyre::bail!("invalid matrix_homeserver URL: {}", self.homeserver);
+        };
+
+        let payload = matrix_payload(severity, message, ctx, self.http.tx_link(ctx).as_deref());
+        if self.http.dry_run("Matrix", &payload) { return Ok(()); }
+
+        check_status(self.http.client.put(url).bearer_auth(&self.token).json(&payload).send().await?).await?;
+        info!("Matrix Alert Sent");
+        Ok(())
+    }
+}
+
+/// `PUT {homeserver}/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}`,
+/// or None if the homeserver URL can't be parsed.
+fn matrix_url(homeserver: &str, room_id: &str, txn_id: &str) -> Option<url::Url> {
+    let mut url = url::Url::parse(homeserver).ok()?;
+    // Room ids (`!abc:server`) need percent-encoding as a path segment
+    url.path_segments_mut().ok()?
+        .pop_if_empty()
+        .extend(["_matrix", "client", "v3", "rooms", room_id, "send", "m.room.message", txn_id]);
+    Some(url)
+}
+
+/// High/Critical go out as `m.text` (notifies); lower severities as
+/// `m.notice`, which clients treat as bot chatter.
+fn matrix_payload(severity: &Severity, message: &str, ctx: &AlertContext, tx_link: Option<&str>) -> MatrixPayload {
+    let msgtype = if *severity >= Severity::High { "m.text" } else { "m.notice" };
+    let chain = ctx.chain_name.as_deref().unwrap_or("-");
+    let mut body = format!("[{:?}] {} (chain: {})", severity, message, chain);
+    let mut formatted_body = format!("<b>[{:?}]</b> {} <i>(chain: {})</i>", severity, html_escape(message), html_escape(chain));
+    if let Some(link) = tx_link {
+        body.push_str(&format!("\n{}", link));
+        formatted_body.push_str(&format!("<br><a href=\"{}\">View Transaction</a>", html_escape(link)));
+    }
+    MatrixPayload { msgtype, body, format: "org.matrix.custom.html", formatted_body }
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordEmbed {
+    title: String,
+    description: String,
+    color: u32,
+    fields: Vec<EmbedField>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordPayload {
+    content: Option<String>,
+    embeds: Vec<DiscordEmbed>,
+}
+
+/// Plain-text email through an SMTP relay; the transport pools connections.
+struct EmailSink {
+    http: Http,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    min_severity: Severity,
+}
+
+#[async_trait]
+impl AlertSink for EmailSink {
+    fn name(&self) -> &'static str {
+        "Email"
+    }
+
+    async fn deliver(&self, severity: &Severity, message: &str, ctx: &AlertContext) -> eyre::Result<()> {
+        if *severity < self.min_severity { return Ok(()); }
+
+        let (subject, body) = email_content(severity, message, ctx, self.http.tx_link(ctx), self.http.event_details(ctx.data.as_ref()));
+        let to: Vec<String> = self.to.iter().map(|to| to.to_string()).collect();
+        if self.http.dry_run("Email", &json!({ "to": to, "subject": subject, "body": body })) { return Ok(()); }
+
+        let mut email = Message::builder()
+            .from(self.from.clone())
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            email = email.to(to.clone());
+        }
+        self.transport.send(email.body(body)?).await?;
+        Ok(())
+    }
+}
+
+/// (Subject, Body) of an alert email. The subject carries the severity and
+/// the first line of the message; mail has no size limit, so event data is
+/// always inlined when enabled.
+fn email_content(
+    severity: &Severity,
+    message: &str,
+    ctx: &AlertContext,
+    tx_link: Option<String>,
+    details: EventDetails,
+) -> (String, String) {
+    let first_line = message.lines().next().unwrap_or_default();
+    let mut headline: String = first_line.chars().take(80).collect();
+    if headline.len() < first_line.len() {
+        headline.push_str("...");
+    }
+    let subject = format!("[EVM Watchdog] {:?}: {}", severity, headline);
+
+    let mut body = format!("Severity: {:?}\n\n{}\n", severity, message);
+    for (name, value) in ctx.fields() {
+        body.push_str(&format!("\n{}: {}", name, value));
+    }
+    if let Some(link) = tx_link {
+        body.push_str(&format!("\nExplorer: {}", link));
+    }
+    if let EventDetails::Inline(json) | EventDetails::Attachment(json) = details {
+        body.push_str(&format!("\n\nEvent Data:\n{}", json));
+    }
+    (subject, body)
+}
+
+/// Body POSTed to `generic_webhook_url`. The schema is stable; receivers can
+/// rely on every key being present:
+///
+/// ```json
+/// {
+///   "severity": "Critical",          // Low | Medium | High | Critical
+///   "message": "Ownership Transferred!",
+///   "chain": "ethereum",             // null when the alert has no chain
+///   "timestamp": 1700000000,         // unix seconds when dispatched
+///   "tx_hash": "0x…",                // null for synthetic alerts
+///   "contract_address": "0x…",       // null for synthetic alerts
+///   "contract_name": "USDT"          // the [[contracts]] name; null when unknown
+/// }
+/// ```
+#[derive(Debug, Serialize)]
+struct GenericWebhookPayload {
+    severity: Severity,
+    message: String,
+    chain: Option<String>,
+    timestamp: u64,
+    tx_hash: Option<B256>,
+    contract_address: Option<Address>,
+    contract_name: Option<String>,
+}
+
+/// Slack incoming-webhook message: Block Kit blocks inside a single
+/// attachment so the severity color renders as the side bar.
+#[derive(Debug, Serialize)]
+struct SlackPayload {
+    attachments: Vec<SlackAttachment>,
+}
+
+#[derive(Debug, Serialize)]
+struct SlackAttachment {
+    color: String,
+    blocks: Vec<serde_json::Value>,
+}
+
+/// PagerDuty Events API v2 trigger.
+#[derive(Debug, Serialize)]
+struct PagerDutyPayload {
+    routing_key: String,
+    event_action: &'static str,
+    dedup_key: String,
+    payload: PagerDutyDetails,
+}
+
+#[derive(Debug, Serialize)]
+struct PagerDutyDetails {
+    summary: String,
+    source: String,
+    severity: &'static str,
+    custom_details: serde_json::Value,
+}
+
+/// Matrix `m.room.message` event content.
+#[derive(Debug, Serialize)]
+struct MatrixPayload {
+    msgtype: &'static str,
+    body: String,
+    format: &'static str,
+    formatted_body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TelegramPayload {
+    chat_id: String,
+    text: String,
+    parse_mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_markup: Option<InlineKeyboardMarkup>,
+}
+
+/// Embed/attachment color per severity, shared by Discord and Slack.
+fn severity_color(severity: &Severity) -> u32 {
+    match severity {
+        Severity::Critical => 0xFF0000,
+        Severity::High => 0xE67E22,
+        Severity::Medium => 0xF1C40F,
+        Severity::Low => 0x3498DB,
+    }
+}
+
+/// Watchdog severity -> PagerDuty event severity.
+fn pagerduty_severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "critical",
+        Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "info",
+    }
+}
+
+/// Escapes text for Matrix `formatted_body` HTML.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn json_file(json: &str) -> Part {
+    Part::bytes(json.as_bytes().to_vec())
+        .file_name("event.json")
+        .mime_str("application/json")
+        .expect("static mime type is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventType, NormalizedEvent};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// The one sink configured by `toml` (an `[alerts]` body without Discord
+    /// unless it sets `webhook_url`), with `server` accepting every POST.
+    async fn sink(server: &MockServer, toml: &str) -> Box<dyn AlertSink> {
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(204)).mount(server).await;
+        let toml = if toml.lines().any(|line| line.starts_with("webhook_url")) { toml.to_string() } else { format!("webhook_url = \"\"\n{}", toml) };
+        let mut sinks = from_config(&AlertsConfig::from_toml(&toml), HashMap::new(), None);
+        assert_eq!(sinks.len(), 1);
+        sinks.remove(0)
+    }
+
+    /// A Discord sink posting to `server`, with event data attached past
+    /// 40 characters.
+    async fn discord(server: &MockServer) -> Box<dyn AlertSink> {
+        let toml = format!("webhook_url = \"{}\"\nattach_event_data = true\nattachment_threshold = 40", server.uri());
+        sink(server, &toml).await
+    }
+
+    /// The JSON body of the only request `server` received.
+    async fn posted(server: &MockServer) -> serde_json::Value {
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        requests[0].body_json().unwrap()
+    }
+
+    fn context(data: serde_json::Value) -> AlertContext {
+        AlertContext { chain_name: Some("ethereum".into()), data: Some(data), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn oversized_event_data_is_uploaded_as_a_file() {
+        let server = MockServer::start().await;
+        let sink = discord(&server).await;
+        let data = json!({ "from": "0x28C6c06298d514Db089934071355E5743bf21d60", "value": "5000000000" });
+        sink.deliver(&Severity::High, "Large Transfer", &context(data)).await.unwrap();
+
+        let request = &server.received_requests().await.unwrap()[0];
+        let content_type = request.headers.get("content-type").unwrap().to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="), "{}", content_type);
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains("name=\"payload_json\""));
+        assert!(body.contains("name=\"files[0]\"; filename=\"event.json\""));
+        assert!(body.contains("\"value\": \"5000000000\""), "pretty-printed data in the file part");
+        assert!(!body.contains("Event Data"), "the message itself stays concise");
+    }
+
+    #[tokio::test]
+    async fn small_event_data_stays_inline() {
+        let server = MockServer::start().await;
+        let sink = discord(&server).await;
+        sink.deliver(&Severity::High, "Approval", &context(json!({ "value": "1" }))).await.unwrap();
+
+        let payload = posted(&server).await;
+        let fields = payload["embeds"][0]["fields"].as_array().unwrap();
+        assert!(fields.iter().any(|f| f["name"] == "Event Data"));
+    }
+
+    #[tokio::test]
+    async fn discord_embed_names_the_transaction() {
+        let server = MockServer::start().await;
+        let sink = sink(&server, &format!("webhook_url = \"{}\"", server.uri())).await;
+        let event = NormalizedEvent::sample(EventType::Transfer, json!({ "value": "5000000000" }));
+        sink.deliver(&Severity::High, "Large Transfer", &AlertContext::from_event(&event)).await.unwrap();
+
+        let payload = posted(&server).await;
+        let fields = payload["embeds"][0]["fields"].as_array().unwrap();
+        let field = |name: &str| fields.iter().find(|f| f["name"] == name).map(|f| f["value"].clone());
+        assert_eq!(field("Tx Hash"), Some(json!(event.tx_hash.to_string())));
+        assert_eq!(field("Block"), Some(json!("1")));
+        assert_eq!(field("Chain"), Some(json!("ethereum")));
+    }
+
+    #[tokio::test]
+    async fn slack_payload_follows_the_blocks_schema() {
+        let server = MockServer::start().await;
+        let sink = sink(&server, &format!("slack_webhook_url = \"{}/services/T0/B0/x\"", server.uri())).await;
+        sink.deliver(&Severity::Critical, "Ownership transferred", &AlertContext::for_chain("ethereum")).await.unwrap();
+
+        let payload = posted(&server).await;
+        let attachment = &payload["attachments"][0];
+        let color = attachment["color"].as_str().unwrap();
+        assert!(color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit()), "{}", color);
+
+        let blocks = attachment["blocks"].as_array().unwrap();
+        let kinds: Vec<&str> = blocks.iter().map(|b| b["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["header", "section", "context"]);
+        // Headers only take plain_text, at most 150 characters
+        assert_eq!(blocks[0]["text"]["type"], "plain_text");
+        assert!(blocks[0]["text"]["text"].as_str().unwrap().chars().count() <= 150);
+        assert_eq!(blocks[1]["text"], json!({ "type": "mrkdwn", "text": "Ownership transferred" }));
+        let context = blocks[2]["elements"].as_array().unwrap();
+        assert!(context.iter().all(|e| e["type"] == "mrkdwn" && e["text"].is_string()));
+        assert!(context[0]["text"].as_str().unwrap().starts_with("*Chain:* ethereum | <!date^"));
+    }
+
+    #[test]
+    fn unconfigured_channels_are_skipped() {
+        let config = AlertsConfig::from_toml("webhook_url = \"\"\nslack_webhook_url = \"\"");
+        assert!(from_config(&config, HashMap::new(), None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn generic_webhook_posts_flat_json() {
+        let server = MockServer::start().await;
+        let sink = sink(&server, &format!("generic_webhook_url = \"{}/hook\"", server.uri())).await;
+        let ctx = AlertContext {
+            chain_name: Some("ethereum".into()),
+            tx_hash: Some(B256::repeat_byte(1)),
+            contract_name: Some("USDT".into()),
+            ..Default::default()
+        };
+        sink.deliver(&Severity::Critical, "Ownership transferred", &ctx).await.unwrap();
+
+        let payload = posted(&server).await;
+        assert_eq!(payload["severity"], "Critical");
+        assert_eq!(payload["message"], "Ownership transferred");
+        assert_eq!(payload["chain"], "ethereum");
+        assert_eq!(payload["tx_hash"], format!("{}", B256::repeat_byte(1)));
+        assert_eq!(payload["contract_address"], serde_json::Value::Null);
+        assert_eq!(payload["contract_name"], "USDT");
+        assert!(payload["timestamp"].as_u64().unwrap() > 1_700_000_000);
+    }
+
+    /// A Discord sink whose first `limited` posts get a 429 with `retry_after`.
+    async fn rate_limited_discord(server: &MockServer, limited: u64, retry_after: &str) -> Box<dyn AlertSink> {
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", retry_after))
+            .up_to_n_times(limited)
+            .with_priority(1)
+            .mount(server)
+            .await;
+        sink(server, &format!("webhook_url = \"{}\"", server.uri())).await
+    }
+
+    #[tokio::test]
+    async fn discord_waits_out_a_rate_limit_then_delivers() {
+        let server = MockServer::start().await;
+        let sink = rate_limited_discord(&server, 1, "0.2").await;
+        let start = std::time::Instant::now();
+        sink.deliver(&Severity::High, "Large Transfer", &AlertContext::default()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200), "honours Retry-After");
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body, requests[1].body, "the same alert, resent");
+    }
+
+    #[tokio::test]
+    async fn discord_gives_up_on_a_persistent_or_long_rate_limit() {
+        let server = MockServer::start().await;
+        let limited = rate_limited_discord(&server, 10, "0").await;
+        let err = limited.deliver(&Severity::High, "Large Transfer", &AlertContext::default()).await.unwrap_err();
+        assert!(err.to_string().starts_with("still rate limited"), "{}", err);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1 + DISCORD_MAX_RETRIES as usize);
+
+        let server = MockServer::start().await;
+        let limited = rate_limited_discord(&server, 1, "3600").await;
+        assert!(limited.deliver(&Severity::High, "Large Transfer", &AlertContext::default()).await.is_err());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1, "too long to wait");
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(404).set_body_string("Unknown Webhook")).mount(&server).await;
+        let rejected = sink(&server, &format!("webhook_url = \"{}\"", server.uri())).await;
+        let err = rejected.deliver(&Severity::High, "Large Transfer", &AlertContext::default()).await.unwrap_err();
+        assert_eq!(err.to_string(), "rejected with 404 Not Found Unknown Webhook");
+    }
+
+    #[test]
+    fn body_signature_is_hex_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign_body("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+
+    #[tokio::test]
+    async fn generic_webhook_signs_the_bytes_it_sends() {
+        let server = MockServer::start().await;
+        let toml = format!("generic_webhook_url = \"{}/hook\"\nwebhook_secret = \"s3cret\"", server.uri());
+        sink(&server, &toml).await.deliver(&Severity::High, "Large Transfer", &AlertContext::for_chain("ethereum")).await.unwrap();
+
+        let request = &server.received_requests().await.unwrap()[0];
+        let signature = request.headers.get("X-Watchdog-Signature").unwrap().to_str().unwrap();
+        assert_eq!(signature, sign_body("s3cret", &request.body));
+        assert_ne!(signature, sign_body("other", &request.body));
+
+        // No secret, no header
+        let server = MockServer::start().await;
+        let toml = format!("generic_webhook_url = \"{}/hook\"", server.uri());
+        sink(&server, &toml).await.deliver(&Severity::High, "Large Transfer", &AlertContext::for_chain("ethereum")).await.unwrap();
+        assert!(server.received_requests().await.unwrap()[0].headers.get("X-Watchdog-Signature").is_none());
+    }
+
+    #[tokio::test]
+    async fn matrix_puts_into_the_room_with_fresh_transaction_ids() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let config = AlertsConfig::from_toml(&format!(
+            "webhook_url = \"\"\nmatrix_homeserver = \"{}/\"\nmatrix_access_token = \"syt_token\"\nmatrix_room_id = \"!ops:example.org\"",
+            server.uri()
+        ));
+        let explorers = HashMap::from([("ethereum".to_string(), "https://etherscan.io".to_string())]);
+        let sinks = from_config(&config, explorers, None);
+        let ctx = AlertContext { tx_hash: Some(B256::repeat_byte(1)), ..AlertContext::for_chain("ethereum") };
+        sinks[0].deliver(&Severity::Critical, "Ownership <transferred>", &ctx).await.unwrap();
+        sinks[0].deliver(&Severity::Low, "Small Transfer", &AlertContext::for_chain("ethereum")).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let paths: Vec<&str> = requests.iter().map(|r| r.url.path()).collect();
+        let prefix = "/_matrix/client/v3/rooms/!ops:example.org/send/m.room.message/";
+        assert!(paths.iter().all(|p| p.starts_with(prefix)), "{:?}", paths);
+        assert_ne!(paths[0], paths[1], "each alert gets its own transaction id");
+        assert_eq!(requests[0].headers.get("authorization").unwrap(), "Bearer syt_token");
+
+        let critical: serde_json::Value = requests[0].body_json().unwrap();
+        assert_eq!(critical["msgtype"], "m.text");
+        assert_eq!(critical["format"], "org.matrix.custom.html");
+        let link = format!("https://etherscan.io/tx/{}", B256::repeat_byte(1));
+        assert_eq!(critical["body"], format!("[Critical] Ownership <transferred> (chain: ethereum)\n{}", link));
+        assert!(critical["formatted_body"].as_str().unwrap().starts_with("<b>[Critical]</b> Ownership &lt;transferred&gt;"));
+        let low: serde_json::Value = requests[1].body_json().unwrap();
+        assert_eq!(low["msgtype"], "m.notice");
+    }
+
+    /// A one-shot SMTP relay on localhost: accepts a single message and
+    /// returns its DATA section (headers and body).
+    async fn smtp_relay() -> (u16, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let relay = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut data = None;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = match line.to_uppercase().split(' ').next().unwrap() {
+                    "EHLO" => b"250 localhost\r\n",
+                    "DATA" => {
+                        write.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.unwrap();
+                        let mut message = String::new();
+                        while let Some(line) = lines.next_line().await.unwrap() {
+                            if line == "." { break; }
+                            message.push_str(&line);
+                            message.push('\n');
+                        }
+                        data = Some(message);
+                        b"250 Queued\r\n"
+                    }
+                    "QUIT" => b"221 Bye\r\n",
+                    _ => b"250 OK\r\n",
+                };
+                write.write_all(reply).await.unwrap();
+                if data.is_some() { break; }
+            }
+            data.expect("a message")
+        });
+        (port, relay)
+    }
+
+    #[tokio::test]
+    async fn email_carries_severity_in_subject_and_context_in_body() {
+        let (port, relay) = smtp_relay().await;
+        let sink = sink(&MockServer::start().await, &format!(
+            "smtp_host = \"127.0.0.1\"\nsmtp_port = {}\nsmtp_tls = \"none\"\nemail_from = \"watchdog@example.org\"\nemail_to = [\"oncall@example.org\"]",
+            port
+        )).await;
+        let ctx = AlertContext { tx_hash: Some(B256::repeat_byte(1)), contract_name: Some("USDT".into()), ..AlertContext::for_chain("ethereum") };
+
+        // Below email_min_severity (High): nothing is sent
+        sink.deliver(&Severity::Medium, "Large Transfer", &ctx).await.unwrap();
+        sink.deliver(&Severity::Critical, "Ownership transferred\nfrom 0xA to 0xB", &ctx).await.unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), relay).await.unwrap().unwrap();
+        assert!(message.contains("Subject: [EVM Watchdog] Critical: Ownership transferred\n"), "{}", message);
+        assert!(message.contains("From: watchdog@example.org"));
+        assert!(message.contains("To: oncall@example.org"));
+        let body = message.split_once("\n\n").unwrap().1;
+        assert!(body.starts_with("Severity: Critical\n\nOwnership transferred\nfrom 0xA to 0xB\n"), "{}", body);
+        assert!(body.contains("Chain: ethereum"));
+        assert!(body.contains(&format!("Tx Hash: {}", B256::repeat_byte(1))));
+    }
+
+    #[tokio::test]
+    async fn pagerduty_triggers_with_mapped_severity() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(202)).mount(&server).await;
+        let http = Http { client: Client::new(), dry_run: false, explorers: Arc::default(), attach_event_data: false, attachment_threshold: 0 };
+        let sink = PagerDutySink { http, url: server.uri(), routing_key: "R0UT1NG".into(), min_severity: Severity::Medium };
+        let ctx = AlertContext::for_chain("ethereum");
+        for severity in [Severity::Critical, Severity::High, Severity::Medium, Severity::Low] {
+            sink.deliver(&severity, "Ownership transferred", &ctx).await.unwrap();
+        }
+
+        // Low is below the paging threshold
+        let requests = server.received_requests().await.unwrap();
+        let payloads: Vec<serde_json::Value> = requests.iter().map(|r| r.body_json().unwrap()).collect();
+        let severities: Vec<&str> = payloads.iter().map(|p| p["payload"]["severity"].as_str().unwrap()).collect();
+        assert_eq!(severities, ["critical", "error", "warning"]);
+        for payload in &payloads {
+            assert_eq!(payload["event_action"], "trigger");
+            assert_eq!(payload["routing_key"], "R0UT1NG");
+            assert_eq!(payload["payload"]["source"], "ethereum");
+        }
+        // The dedup key follows severity and message
+        assert_ne!(payloads[0]["dedup_key"], payloads[1]["dedup_key"]);
+        assert_eq!(pagerduty_severity(&Severity::Low), "info");
+    }
+}
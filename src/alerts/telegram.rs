@@ -0,0 +1,308 @@
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::events::Severity;
+use crate::listener::backoff_delay;
+use crate::state::AppState;
+
+/// `callback_data` prefix of the Acknowledge button; the rest is the
+/// `AckRegistry` id.
+const ACK_PREFIX: &str = "ack:";
+
+/// Alerts whose button still resolves; older presses are answered as expired.
+const ACK_CAPACITY: usize = 256;
+
+/// Seconds Telegram holds a `getUpdates` request open waiting for a press.
+const POLL_TIMEOUT_SECS: u64 = 30;
+
+/// (Severity, ChainName, Message) of the alerts sent with an Acknowledge
+/// button, by the id in their `callback_data` (which Telegram caps at 64
+/// bytes, too small for the key itself).
+#[derive(Debug, Default)]
+pub struct AckRegistry {
+    next_id: AtomicU64,
+    alerts: Mutex<VecDeque<(u64, Severity, String, String)>>,
+}
+
+impl AckRegistry {
+    /// Remembers an alert and returns the id for its button.
+    pub fn register(&self, severity: Severity, chain: String, message: String) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut alerts) = self.alerts.lock() {
+            if alerts.len() >= ACK_CAPACITY {
+                alerts.pop_front();
+            }
+            alerts.push_back((id, severity, chain, message));
+        }
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<(Severity, String, String)> {
+        let alerts = self.alerts.lock().ok()?;
+        alerts.iter()
+            .find(|(i, _, _, _)| *i == id)
+            .map(|(_, severity, chain, message)| (severity.clone(), chain.clone(), message.clone()))
+    }
+}
+
+/// Telegram `reply_markup`: rows of buttons under the message.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InlineKeyboardMarkup {
+    pub inline_keyboard: Vec<Vec<InlineKeyboardButton>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InlineKeyboardButton {
+    pub text: String,
+    pub callback_data: String,
+}
+
+/// A single "Acknowledge" button for the alert registered as `id`.
+pub fn ack_keyboard(id: u64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup {
+        inline_keyboard: vec![vec![InlineKeyboardButton {
+            text: "✅ Acknowledge".to_string(),
+            callback_data: format!("{}{}", ACK_PREFIX, id),
+        }]],
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Updates {
+    #[serde(default)]
+    result: Vec<Update>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: User,
+    message: Option<CallbackMessage>,
+    data: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    #[serde(default)]
+    username: Option<String>,
+    first_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackMessage {
+    message_id: i64,
+    chat: Chat,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+    #[serde(default)]
+    username: Option<String>,
+}
+
+impl Chat {
+    /// Whether this is the configured `telegram_chat_id`, given as a
+    /// numeric id or as `@channelname`.
+    fn is(&self, chat_id: &str) -> bool {
+        match chat_id.strip_prefix('@') {
+            Some(name) => self.username.as_deref() == Some(name),
+            None => chat_id == self.id.to_string(),
+        }
+    }
+}
+
+/// Long-polls the bot's updates and acknowledges alerts in `state` (as the
+/// TUI's `a` key does) when their button is pressed in `chat_id`. Returns
+/// when Telegram refuses the polling for good: a bad token, or a webhook or
+/// another process already consuming this bot's updates.
+pub async fn poll_acks(client: Client, token: String, chat_id: String, acks: Arc<AckRegistry>, state: Arc<AppState>) {
+    let api = format!("https://api.telegram.org/bot{}", token);
+    let mut offset = 0;
+    let mut failures = 0;
+    loop {
+        let request = client.post(format!("{}/getUpdates", api))
+            .json(&json!({ "offset": offset, "timeout": POLL_TIMEOUT_SECS, "allowed_updates": ["callback_query"] }))
+            .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 10));
+        let updates = match request.send().await {
+            Ok(resp) if resp.status().is_success() => resp.json::<Updates>().await.map_err(|e| e.to_string()),
+            Ok(resp) => {
+                let status = resp.status();
+                let body = resp.text().await.unwrap_or_default();
+                if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::NOT_FOUND | StatusCode::CONFLICT) {
+                    error!("Telegram acknowledgements disabled, getUpdates rejected with {} {}", status, body.trim());
+                    return;
+                }
+                Err(format!("rejected with {} {}", status, body.trim()))
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        let updates = match updates {
+            Ok(updates) => {
+                failures = 0;
+                updates.result
+            }
+            Err(e) => {
+                let delay = backoff_delay(Duration::from_secs(1), failures);
+                failures += 1;
+                warn!("Telegram getUpdates failed, retrying in {:?}: {}", delay, e);
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = offset.max(update.update_id + 1);
+            if let Some(query) = update.callback_query {
+                handle_callback(&client, &api, &chat_id, &acks, &state, query).await;
+            }
+        }
+    }
+}
+
+async fn handle_callback(client: &Client, api: &str, chat_id: &str, acks: &AckRegistry, state: &AppState, query: CallbackQuery) {
+    let Some(message) = query.message.filter(|m| m.chat.is(chat_id)) else {
+        return;
+    };
+    let id: Option<u64> = query.data.as_deref().and_then(|d| d.strip_prefix(ACK_PREFIX)).and_then(|id| id.parse().ok());
+    let who = query.from.username.map(|u| format!("@{}", u)).unwrap_or(query.from.first_name);
+    let reply = match id.map(|id| acks.get(id)) {
+        Some(Some((severity, chain, alert))) => {
+            state.acknowledge(&severity, &chain, &alert);
+            info!("Alert acknowledged on Telegram by {}: {}", who, alert);
+            // Swap the button for who pressed it. Editing can fail (e.g. the bot
+            // lacks rights in the chat); the button stays, the ack still counts
+            let done = json!({
+                "chat_id": message.chat.id,
+                "message_id": message.message_id,
+                "reply_markup": { "inline_keyboard": [[{ "text": format!("Acknowledged by {}", who), "callback_data": "acked" }]] },
+            });
+            if let Err(e) = call(client, api, "editMessageReplyMarkup", &done).await {
+                warn!("Acknowledged, but could not update the Telegram message: {}", e);
+            }
+            "Acknowledged"
+        }
+        Some(None) => "This alert is too old to acknowledge here",
+        // The "Acknowledged by" button left after an earlier press
+        None => "Already acknowledged",
+    };
+    let answer = json!({ "callback_query_id": query.id, "text": reply });
+    if let Err(e) = call(client, api, "answerCallbackQuery", &answer).await {
+        warn!("Could not answer Telegram button press: {}", e);
+    }
+}
+
+async fn call(client: &Client, api: &str, method: &str, body: &serde_json::Value) -> eyre::Result<()> {
+    let resp = client.post(format!("{}/{}", api, method)).json(body).send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        eyre::bail!("{} rejected with {} {}", method, status, body.trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const CHAT: i64 = -1001234;
+
+    /// A press of the button carrying `data` on a message in `chat`.
+    fn press(chat: i64, data: &str) -> CallbackQuery {
+        serde_json::from_value(json!({
+            "id": "q1",
+            "from": { "first_name": "Ana", "username": "ana" },
+            "message": { "message_id": 42, "chat": { "id": chat } },
+            "data": data,
+        }))
+        .unwrap()
+    }
+
+    /// (Method, Body) of each Bot API call the server received.
+    async fn calls(server: &MockServer) -> Vec<(String, serde_json::Value)> {
+        server.received_requests().await.unwrap().iter()
+            .map(|r| (r.url.path().trim_start_matches('/').to_string(), serde_json::from_slice(&r.body).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn ack_keyboard_is_one_button_carrying_the_id() {
+        assert_eq!(
+            serde_json::to_value(ack_keyboard(7)).unwrap(),
+            json!({ "inline_keyboard": [[{ "text": "✅ Acknowledge", "callback_data": "ack:7" }]] }),
+        );
+    }
+
+    #[test]
+    fn registry_forgets_the_oldest_alerts() {
+        let acks = AckRegistry::default();
+        let first = acks.register(Severity::Critical, "ethereum".into(), "drained".into());
+        assert_eq!(acks.get(first), Some((Severity::Critical, "ethereum".to_string(), "drained".to_string())));
+        for _ in 0..ACK_CAPACITY {
+            acks.register(Severity::Critical, "ethereum".into(), "later".into());
+        }
+        assert_eq!(acks.get(first), None);
+        assert!(acks.get(first + ACK_CAPACITY as u64).is_some());
+    }
+
+    #[tokio::test]
+    async fn pressing_acknowledge_marks_the_alert_even_if_the_edit_fails() {
+        let server = MockServer::start().await;
+        // As when the bot lacks rights to edit messages in the chat
+        Mock::given(method("POST")).and(path("/editMessageReplyMarkup"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("not enough rights"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let (client, api, chat_id) = (Client::new(), server.uri(), CHAT.to_string());
+        let acks = AckRegistry::default();
+        let state = AppState::new();
+        let id = acks.register(Severity::Critical, "ethereum".into(), "drained".into());
+
+        // Presses in other chats are ignored
+        handle_callback(&client, &api, &chat_id, &acks, &state, press(CHAT - 1, &format!("ack:{}", id))).await;
+        assert!(calls(&server).await.is_empty());
+        assert!(!state.is_acknowledged(&Severity::Critical, "ethereum", "drained"));
+
+        handle_callback(&client, &api, &chat_id, &acks, &state, press(CHAT, &format!("ack:{}", id))).await;
+        assert!(state.is_acknowledged(&Severity::Critical, "ethereum", "drained"));
+        let calls = calls(&server).await;
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].0, "editMessageReplyMarkup");
+        assert_eq!(calls[0].1["reply_markup"]["inline_keyboard"][0][0]["text"], "Acknowledged by @ana");
+        assert_eq!(calls[1].0, "answerCallbackQuery");
+        assert_eq!(calls[1].1, json!({ "callback_query_id": "q1", "text": "Acknowledged" }));
+    }
+
+    #[tokio::test]
+    async fn stale_and_spent_buttons_are_answered_without_acknowledging() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let (client, api, chat_id) = (Client::new(), server.uri(), CHAT.to_string());
+        let acks = AckRegistry::default();
+        let state = AppState::new();
+
+        handle_callback(&client, &api, &chat_id, &acks, &state, press(CHAT, "ack:99")).await;
+        handle_callback(&client, &api, &chat_id, &acks, &state, press(CHAT, "acked")).await;
+        let texts: Vec<_> = calls(&server).await.into_iter().map(|(method, body)| (method, body["text"].clone())).collect();
+        assert_eq!(texts, [
+            ("answerCallbackQuery".to_string(), json!("This alert is too old to acknowledge here")),
+            ("answerCallbackQuery".to_string(), json!("Already acknowledged")),
+        ]);
+    }
+}
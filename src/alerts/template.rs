@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use alloy::primitives::U256;
+
+use crate::config::AlertsConfig;
+use crate::events::{NormalizedEvent, Severity};
+
+/// Placeholders a message template may use.
+pub const PLACEHOLDERS: &[&str] = &["message", "rule", "severity", "chain", "event", "tx", "block", "address", "contract", "value"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(String),
+}
+
+/// Alert text with `{placeholder}` substitutions (`{{` and `}}` for literal
+/// braces), e.g. `"[{chain}] {rule}: {message} (tx {tx})"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageTemplate {
+    parts: Vec<Part>,
+}
+
+impl MessageTemplate {
+    /// Parses `text`, rejecting unknown placeholders and unbalanced braces.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("unclosed '{{{}' (write '{{{{' for a literal brace)", name));
+                    }
+                    if !PLACEHOLDERS.contains(&name.as_str()) {
+                        return Err(format!("unknown placeholder '{{{}}}' (expected one of {})", name, PLACEHOLDERS.join(", ")));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(name));
+                }
+                '}' => return Err("unmatched '}' (write '}}' for a literal brace)".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fills the template from the rule's own `message` and the event that
+    /// raised it. `{value}` is the event's `value` in token units, or `-`.
+    pub fn render(&self, rule: &str, message: &str, severity: &Severity, event: &NormalizedEvent) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Field(name) => out.push_str(&match name.as_str() {
+                    "message" => message.to_string(),
+                    "rule" => rule.to_string(),
+                    "severity" => format!("{:?}", severity),
+                    "chain" => event.chain_name.clone(),
+                    "event" => event.event_type.name().to_string(),
+                    // Synthetic events carry no transaction
+                    "tx" if event.tx_hash.is_zero() => "-".to_string(),
+                    "tx" => event.tx_hash.to_string(),
+                    "block" => event.block_number.to_string(),
+                    "address" => event.contract_address.to_string(),
+                    "contract" => event.contract_label(),
+                    "value" => match event.data.get("value").and_then(|v| v.as_str()) {
+                        Some(raw) => raw.parse::<U256>().map(|v| event.format_amount(v)).unwrap_or_else(|_| raw.to_string()),
+                        None => "-".to_string(),
+                    },
+                    _ => String::new(),
+                }),
+            }
+        }
+        out
+    }
+}
+
+/// The configured templates: one per rule name, falling back to the global
+/// one. With neither, rule messages go out unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct MessageTemplates {
+    default: Option<MessageTemplate>,
+    per_rule: HashMap<String, MessageTemplate>,
+}
+
+impl MessageTemplates {
+    /// Parses `[alerts] message_template(s)`; errors name the offending key.
+    pub fn from_config(config: &AlertsConfig) -> Result<Self, String> {
+        let default = config.message_template.as_deref()
+            .map(MessageTemplate::parse)
+            .transpose()
+            .map_err(|e| format!("alerts.message_template: {}", e))?;
+        let per_rule = config.message_templates.iter()
+            .map(|(rule, text)| {
+                MessageTemplate::parse(text)
+                    .map(|t| (rule.clone(), t))
+                    .map_err(|e| format!("alerts.message_templates.{}: {}", rule, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { default, per_rule })
+    }
+
+    pub fn apply(&self, rule: &str, message: String, severity: &Severity, event: &NormalizedEvent) -> String {
+        match self.per_rule.get(rule).or(self.default.as_ref()) {
+            Some(template) => template.render(rule, &message, severity, event),
+            None => message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use serde_json::json;
+
+    #[test]
+    fn templates_render_event_fields() {
+        let event = NormalizedEvent::sample(EventType::Transfer, json!({ "value": "2500000" }));
+        let template = MessageTemplate::parse("[{chain}] {{{rule}}} {message}: {value} in {event} at block {block} (tx {tx})").unwrap();
+        assert_eq!(
+            template.render("ThresholdRule", "Large Transfer", &Severity::High, &event),
+            format!("[ethereum] {{ThresholdRule}} Large Transfer: 2.5 in Transfer at block 1 (tx {})", event.tx_hash),
+        );
+
+        // Events without a value or a transaction render placeholders as "-"
+        let mut code = NormalizedEvent::sample(EventType::CodeChanged, json!({}));
+        code.tx_hash = alloy::primitives::B256::ZERO;
+        let template = MessageTemplate::parse("{value} {tx} {severity}").unwrap();
+        assert_eq!(template.render("CodeChangeRule", "", &Severity::Critical, &code), "- - Critical");
+    }
+
+    #[test]
+    fn bad_templates_are_rejected() {
+        assert!(MessageTemplate::parse("{wallet}").unwrap_err().contains("unknown placeholder '{wallet}'"));
+        assert!(MessageTemplate::parse("{chain").unwrap_err().starts_with("unclosed '{chain'"));
+        assert!(MessageTemplate::parse("chain}").unwrap_err().starts_with("unmatched '}'"));
+
+        let config = AlertsConfig::from_toml("webhook_url = \"\"\nmessage_templates = { ThresholdRule = \"{nope}\" }");
+        assert!(MessageTemplates::from_config(&config).unwrap_err().starts_with("alerts.message_templates.ThresholdRule: unknown placeholder"));
+    }
+
+    #[test]
+    fn per_rule_templates_override_the_default_and_unset_keeps_messages() {
+        let event = NormalizedEvent::sample(EventType::Transfer, json!({}));
+        let message = || "Large Transfer".to_string();
+
+        let templates = MessageTemplates::default();
+        assert_eq!(templates.apply("ThresholdRule", message(), &Severity::High, &event), "Large Transfer");
+
+        let config = AlertsConfig::from_toml(
+            "webhook_url = \"\"\nmessage_template = \"{chain}: {message}\"\nmessage_templates = { ThresholdRule = \"{rule} -> {message}\" }",
+        );
+        let templates = MessageTemplates::from_config(&config).unwrap();
+        assert_eq!(templates.apply("ThresholdRule", message(), &Severity::High, &event), "ThresholdRule -> Large Transfer");
+        assert_eq!(templates.apply("VelocityRule", message(), &Severity::High, &event), "ethereum: Large Transfer");
+    }
+}
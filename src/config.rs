@@ -1,61 +1,1362 @@
 use serde::Deserialize;
 use std::collections::HashMap;
-use config::{Config, ConfigError, File};
-use alloy::primitives::Address;
+use config::{Config, ConfigError, File, Value, ValueKind};
+use alloy::primitives::{Address, U256};
+use url::Url;
+use lettre::message::Mailbox;
+use crate::alerts::batch::CHANNEL_KEYS;
+use crate::alerts::template::MessageTemplate;
+use crate::events::{parse_units, Severity};
+use std::path::Path;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ChainConfig {
     pub rpc_url: String,
     pub chain_id: u64,
+    /// Block explorer base URL (e.g. `https://etherscan.io`) for tx links in alerts.
+    #[serde(default)]
+    pub explorer_url: Option<String>,
+    /// `Authorization` header for the WebSocket upgrade, for nodes that
+    /// don't take the key in the URL: `Bearer <token>`, `Basic <base64>`
+    /// or `user:pass`.
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    /// HTTP endpoints only: how often to poll, and the widest `eth_getLogs` range per request.
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(default = "default_max_block_range")]
+    pub max_block_range: u64,
+    /// How often to re-check each contract's bytecode for changes; 0 disables.
+    #[serde(default = "default_code_poll_interval_secs")]
+    pub code_poll_interval_secs: u64,
+    /// Blocks to wait on top of an event's block before alerting (reorg
+    /// protection). 0 = alert on `latest`.
+    #[serde(default)]
+    pub confirmations: u64,
+    /// Typical seconds between blocks; the TUI marks the chain stale after
+    /// 3x this without a new block.
+    #[serde(default = "default_expected_block_time")]
+    pub expected_block_time: u64,
+    /// WebSocket chains only: with no new block for this many expected block
+    /// times, the subscriptions are assumed stalled and reconnected. 0 = never.
+    #[serde(default = "default_stall_timeout_blocks")]
+    pub stall_timeout_blocks: u32,
+    /// Replay this many past blocks of logs on startup to catch what happened
+    /// while the watchdog was down. 0 = live only.
+    #[serde(default)]
+    pub backfill_blocks: u64,
+    /// Alert when a block's base fee jumps well above the recent average.
+    #[serde(default)]
+    pub gas_spike: Option<GasSpikeConfig>,
+    /// Most RPC requests (polls, backfill pages, code and metadata reads) in
+    /// flight at once on this chain, for rate-limited nodes. 0 = no limit.
+    #[serde(default)]
+    pub max_concurrent_requests: usize,
+    /// Per-contract reads (code and supply polls) arriving within this many
+    /// milliseconds go out as one JSON-RPC batch. 0 = one request each.
+    #[serde(default = "default_rpc_batch_window_ms")]
+    pub rpc_batch_window_ms: u64,
 }
 
-#[derive(Debug, Deserialize)]
+/// Base-fee spike detection for one chain (EIP-1559 chains only; blocks
+/// without a base fee are ignored).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GasSpikeConfig {
+    /// Fire when the base fee exceeds this multiple of the trailing average.
+    #[serde(default = "default_gas_spike_multiplier")]
+    pub multiplier: f64,
+    /// Blocks in the trailing average; nothing fires until it is full.
+    #[serde(default = "default_gas_spike_window")]
+    pub window_blocks: usize,
+    #[serde(default = "default_gas_spike_severity")]
+    pub severity: String,
+}
+
+fn default_gas_spike_multiplier() -> f64 {
+    3.0
+}
+
+fn default_gas_spike_window() -> usize {
+    20
+}
+
+fn default_gas_spike_severity() -> String {
+    "High".to_string()
+}
+
+fn default_poll_interval_secs() -> u64 {
+    12
+}
+
+fn default_max_block_range() -> u64 {
+    500
+}
+
+fn default_expected_block_time() -> u64 {
+    12
+}
+
+fn default_stall_timeout_blocks() -> u32 {
+    10
+}
+
+fn default_code_poll_interval_secs() -> u64 {
+    60
+}
+
+fn default_rpc_batch_window_ms() -> u64 {
+    50
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ContractConfig {
     pub name: String,
     pub address: Address,
     pub chain: String,
     pub events: Vec<String>,
+    /// Starting severity per event name (e.g. `OwnershipTransferred = "High"`),
+    /// applied before rules run. Unlisted events start at Low.
+    #[serde(default)]
+    pub base_severity: HashMap<String, String>,
+    /// Only forward Transfer/Approval logs involving these addresses
+    /// (from/to/owner/spender). Empty = everything.
+    #[serde(default)]
+    pub watch_addresses: Vec<Address>,
+    /// Path to a JSON ABI (or build artifact) used to decode events beyond
+    /// the built-in Transfer/Approval/OwnershipTransferred.
+    #[serde(default)]
+    pub abi: Option<String>,
+    /// Forward logs no decoder recognises as `Unknown(<topic0>)` events
+    /// carrying the raw topics and data.
+    #[serde(default)]
+    pub forward_unknown: bool,
+    /// Token decimals (e.g. 6 for USDT/USDC, 18 for most ERC-20s) so alerts
+    /// show "1500.25" instead of raw base units. Unset = raw values.
+    #[serde(default)]
+    pub decimals: Option<u8>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct TransferRuleConfig {
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
     pub min_value: String,
+    /// Whether `min_value` is in base units or whole tokens.
+    #[serde(default)]
+    pub threshold_unit: ThresholdUnit,
     pub severity: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Unit of `rules.transfer_threshold.min_value`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdUnit {
+    /// Base units, compared as-is (e.g. "1000000000" = 1000 USDT).
+    #[default]
+    Raw,
+    /// Whole tokens, possibly fractional ("1000", "0.5"), scaled by each
+    /// transfer's token decimals; tokens without known decimals are skipped.
+    Token,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct OwnershipRuleConfig {
+    #[serde(default = "default_rule_enabled")]
     pub enabled: bool,
     pub severity: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Approvals of at least `threshold` base units: a whole number, or "max"
+/// for exactly unlimited (uint256 max). Unset = half of uint256, which
+/// catches "infinite" approvals however the dapp encodes them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApprovalRuleConfig {
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub threshold: Option<String>,
+    #[serde(default = "default_approval_severity")]
+    pub severity: String,
+}
+
+impl Default for ApprovalRuleConfig {
+    fn default() -> Self {
+        Self { enabled: default_rule_enabled(), threshold: None, severity: default_approval_severity() }
+    }
+}
+
+impl ApprovalRuleConfig {
+    /// The configured threshold, or None if it doesn't parse.
+    pub fn threshold(&self) -> Option<U256> {
+        match self.threshold.as_deref().map(str::trim) {
+            None => Some(U256::MAX >> 1),
+            Some(t) if t.eq_ignore_ascii_case("max") => Some(U256::MAX),
+            Some(t) => t.parse().ok(),
+        }
+    }
+}
+
+fn default_approval_severity() -> String {
+    "Critical".to_string()
+}
+
+/// Built-in rules are on unless their table says `enabled = false`.
+fn default_rule_enabled() -> bool {
+    true
+}
+
+/// Alert when one sender makes more than `max_transfers` within `window_secs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VelocityRuleConfig {
+    pub max_transfers: usize,
+    pub window_secs: u64,
+    pub severity: String,
+}
+
+/// Fan-out detection: one sender paying more than `max_recipients` distinct
+/// addresses within `window_secs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FanOutRuleConfig {
+    pub max_recipients: usize,
+    pub window_secs: u64,
+    pub severity: String,
+}
+
+/// Ownership ping-pong: more than `max_changes` ownership transfers on one
+/// contract within `window_secs`, or ownership returning to a previous owner.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OwnershipPingPongRuleConfig {
+    #[serde(default = "default_ping_pong_max_changes")]
+    pub max_changes: usize,
+    #[serde(default = "default_ping_pong_window_secs")]
+    pub window_secs: u64,
+    #[serde(default = "default_approval_severity")]
+    pub severity: String,
+}
+
+fn default_ping_pong_max_changes() -> usize {
+    2
+}
+
+fn default_ping_pong_window_secs() -> u64 {
+    3600
+}
+
+/// Alert on every transfer touching one of `addresses`, whatever the amount.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchlistRuleConfig {
+    pub addresses: Vec<Address>,
+    pub severity: String,
+}
+
+/// ERC-721 transfers in `collections` (contract addresses) and/or of
+/// `token_ids` (decimal or 0x hex); an empty list matches any.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NftTransferRuleConfig {
+    #[serde(default)]
+    pub collections: Vec<Address>,
+    #[serde(default)]
+    pub token_ids: Vec<String>,
+    #[serde(default = "default_nft_transfer_severity")]
+    pub severity: String,
+}
+
+fn default_nft_transfer_severity() -> String {
+    "Medium".to_string()
+}
+
+/// Known-malicious addresses, one per line (`#` comments allowed).
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlacklistRuleConfig {
+    pub file: String,
+}
+
+/// Known drainer/scam spender contracts, one per line, plus the smallest
+/// approval worth escalating (base units; default any non-zero amount).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DrainerRuleConfig {
+    pub file: String,
+    #[serde(default = "default_drainer_min_value")]
+    pub min_value: String,
+}
+
+fn default_drainer_min_value() -> String {
+    "1".to_string()
+}
+
+/// Correlate an Approval with a later Transfer from the same owner to the
+/// same spender within `window_secs` (always Critical).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DrainPatternRuleConfig {
+    #[serde(default = "default_drain_pattern_window_secs")]
+    pub window_secs: u64,
+}
+
+fn default_drain_pattern_window_secs() -> u64 {
+    300
+}
+
+/// Stop forwarding a rule's alerts for `cooldown_secs` once it fires more
+/// than `max_hits` times within `window_secs`. Critical alerts are never muted.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoMuteConfig {
+    #[serde(default = "default_auto_mute_max_hits")]
+    pub max_hits: usize,
+    #[serde(default = "default_auto_mute_window_secs")]
+    pub window_secs: u64,
+    #[serde(default = "default_auto_mute_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_auto_mute_max_hits() -> usize {
+    100
+}
+
+fn default_auto_mute_window_secs() -> u64 {
+    60
+}
+
+fn default_auto_mute_cooldown_secs() -> u64 {
+    600
+}
+
+/// Alert on mints (from the zero address) and/or burns (to it).
+/// `direction` is "mint", "burn" or "both"; `min_value` is in base units.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MintBurnRuleConfig {
+    #[serde(default = "default_mint_burn_direction")]
+    pub direction: String,
+    #[serde(default = "default_mint_burn_min_value")]
+    pub min_value: String,
+    pub severity: String,
+}
+
+fn default_mint_burn_direction() -> String {
+    "both".to_string()
+}
+
+fn default_mint_burn_min_value() -> String {
+    "0".to_string()
+}
+
+/// Alert on Uniswap V2/V3 swaps moving at least this much of either pool
+/// token, in that token's base units. Set one or both.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LargeSwapRuleConfig {
+    #[serde(default)]
+    pub min_amount0: Option<String>,
+    #[serde(default)]
+    pub min_amount1: Option<String>,
+    pub severity: String,
+}
+
+/// Alert when the signed integer `field` of an event (e.g. a rebase delta or
+/// a custom ABI event's `int256`) moves by at least `min_magnitude` base units
+/// either way. `event` limits it to one event name; unset checks every event
+/// carrying the field.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignedThresholdRuleConfig {
+    #[serde(default)]
+    pub event: Option<String>,
+    pub field: String,
+    pub min_magnitude: String,
+    pub severity: String,
+}
+
+/// Alert when a watched token's `totalSupply()` moves by more than
+/// `max_change_pct` percent between two polls `poll_interval_secs` apart
+/// (stealth mints, inflation exploits). Setting this table turns the polling on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SupplyChangeRuleConfig {
+    #[serde(default = "default_supply_max_change_pct")]
+    pub max_change_pct: f64,
+    #[serde(default = "default_supply_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    pub severity: String,
+}
+
+fn default_supply_max_change_pct() -> f64 {
+    5.0
+}
+
+fn default_supply_poll_interval_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct RulesConfig {
     pub transfer_threshold: TransferRuleConfig,
     pub ownership_change: OwnershipRuleConfig,
+    #[serde(default)]
+    pub high_approval: ApprovalRuleConfig,
+    #[serde(default)]
+    pub ownership_ping_pong: Option<OwnershipPingPongRuleConfig>,
+    #[serde(default)]
+    pub velocity: Option<VelocityRuleConfig>,
+    #[serde(default)]
+    pub fan_out: Option<FanOutRuleConfig>,
+    #[serde(default)]
+    pub blacklist: Option<BlacklistRuleConfig>,
+    #[serde(default)]
+    pub watchlist: Option<WatchlistRuleConfig>,
+    #[serde(default)]
+    pub nft_transfer: Option<NftTransferRuleConfig>,
+    #[serde(default)]
+    pub drainer_spenders: Option<DrainerRuleConfig>,
+    #[serde(default)]
+    pub drain_pattern: Option<DrainPatternRuleConfig>,
+    #[serde(default)]
+    pub mint_burn: Option<MintBurnRuleConfig>,
+    #[serde(default)]
+    pub large_swap: Option<LargeSwapRuleConfig>,
+    #[serde(default)]
+    pub signed_threshold: Option<SignedThresholdRuleConfig>,
+    #[serde(default)]
+    pub supply_change: Option<SupplyChangeRuleConfig>,
+    #[serde(default)]
+    pub auto_mute: Option<AutoMuteConfig>,
+    /// Rule name -> contract addresses it applies to (e.g.
+    /// `ThresholdRule = ["0xdAC1..."]`). Unlisted rules run on every contract.
+    #[serde(default)]
+    pub scope: HashMap<String, Vec<Address>>,
+    /// Rule name -> priority (default 0) for `stop_on_first`.
+    #[serde(default)]
+    pub priority: HashMap<String, i32>,
+    /// Alert once per event, from the highest-priority matching rule,
+    /// instead of once per matching rule.
+    #[serde(default)]
+    pub stop_on_first: bool,
+    /// Evaluate rules on rayon's thread pool instead of one after another.
+    #[serde(default)]
+    pub parallel: bool,
+}
+
+/// Inverse monitoring: alert when `event` from `contract` (a `[[contracts]]`
+/// name) has not been seen for `interval_secs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpectedEventConfig {
+    pub contract: String,
+    pub event: String,
+    pub interval_secs: u64,
+    pub severity: String,
+}
+
+/// Discord webhooks: one URL that gets every alert, or a list of routes
+/// that each get alerts at or above their `min_severity`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DiscordWebhooks {
+    Single(String),
+    Routes(Vec<WebhookRoute>),
+}
+
+impl DiscordWebhooks {
+    /// Every configured route; an empty single URL means Discord is off.
+    pub fn routes(&self) -> Vec<WebhookRoute> {
+        match self {
+            DiscordWebhooks::Single(url) if url.is_empty() => Vec::new(),
+            DiscordWebhooks::Single(url) => vec![WebhookRoute { url: url.clone(), min_severity: default_route_min_severity() }],
+            DiscordWebhooks::Routes(routes) => routes.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookRoute {
+    pub url: String,
+    #[serde(default = "default_route_min_severity")]
+    pub min_severity: String,
+}
+
+fn default_telegram_ack_buttons() -> bool {
+    true
 }
 
-#[derive(Debug, Deserialize)]
+fn default_route_min_severity() -> String {
+    "Low".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct AlertsConfig {
-    pub webhook_url: String, // Discord
+    pub webhook_url: DiscordWebhooks, // Discord
     pub telegram_bot_token: Option<String>,
     pub telegram_chat_id: Option<String>,
+    /// Critical Telegram alerts get an Acknowledge button, served by polling
+    /// the bot's updates. Turn off when something else (a webhook, another
+    /// bot process) consumes this bot's updates.
+    #[serde(default = "default_telegram_ack_buttons")]
+    pub telegram_ack_buttons: bool,
+    /// Any HTTP endpoint accepting the JSON body documented on `GenericWebhookPayload`.
+    #[serde(default)]
+    pub generic_webhook_url: Option<String>,
+    /// When set, generic webhook requests carry `X-Watchdog-Signature`, the
+    /// hex HMAC-SHA256 of the raw body under this key.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    #[serde(default)]
+    pub slack_webhook_url: Option<String>,
+    /// PagerDuty Events API v2 integration key; only alerts at or above
+    /// `pagerduty_min_severity` page.
+    #[serde(default)]
+    pub pagerduty_routing_key: Option<String>,
+    #[serde(default = "default_pagerduty_min_severity")]
+    pub pagerduty_min_severity: String,
+    /// Matrix (Element) room to post into, e.g. `https://matrix.example.org`,
+    /// a bot user's access token and `!roomid:example.org`.
+    #[serde(default)]
+    pub matrix_homeserver: Option<String>,
+    #[serde(default)]
+    pub matrix_access_token: Option<String>,
+    #[serde(default)]
+    pub matrix_room_id: Option<String>,
+    /// SMTP relay for email alerts; only alerts at or above
+    /// `email_min_severity` are mailed. The port defaults by `smtp_tls`
+    /// (587 starttls, 465 tls, 25 none).
+    #[serde(default)]
+    pub smtp_host: Option<String>,
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub smtp_user: Option<String>,
+    #[serde(default)]
+    pub smtp_pass: Option<String>,
+    #[serde(default)]
+    pub smtp_tls: SmtpTls,
+    #[serde(default)]
+    pub email_from: Option<String>,
+    #[serde(default)]
+    pub email_to: Vec<String>,
+    #[serde(default = "default_email_min_severity")]
+    pub email_min_severity: String,
+    /// Include decoded event data with alerts: inline when it fits within
+    /// `attachment_threshold` chars, otherwise uploaded as a JSON file.
+    #[serde(default)]
+    pub attach_event_data: bool,
+    #[serde(default = "default_attachment_threshold")]
+    pub attachment_threshold: usize,
+    /// Rate-limit window for repeated alerts, overridable per severity
+    /// (e.g. `cooldowns = { Critical = 0 }`). 0 means never suppress.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    #[serde(default)]
+    pub cooldowns: HashMap<String, u64>,
+    /// Alerts below this severity are recorded (TUI, history) but never sent
+    /// to the channels. Unset = send everything.
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// Severity of the alert raised when a chain's blocks go stale or its
+    /// RPC goes down, and of the RESOLVED follow-up once it recovers.
+    #[serde(default = "default_stale_chain_severity")]
+    pub stale_chain_severity: String,
+    /// Log rendered payloads instead of sending them (also `--dry-run`).
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// Rewrites outgoing alert text, for every rule (`message_template`) or
+    /// per rule name (`message_templates = { ThresholdRule = "..." }`).
+    /// Placeholders are listed in `alerts::template`; unset keeps the rule's
+    /// own message.
+    #[serde(default)]
+    pub message_template: Option<String>,
+    #[serde(default)]
+    pub message_templates: HashMap<String, String>,
+    #[serde(default)]
+    pub digest: Option<DigestConfig>,
+    /// Channel -> seconds to collect alerts before sending them as one
+    /// message (e.g. `batch_windows = { email = 60 }`); keys are listed in
+    /// `alerts::batch::CHANNEL_KEYS`. Unlisted channels send each alert.
+    #[serde(default)]
+    pub batch_windows: HashMap<String, u64>,
+    /// Send a test message through every channel at startup and refuse to
+    /// start if one fails (also `--test-alerts`, which exits afterwards).
+    #[serde(default)]
+    pub self_test: bool,
+}
+
+/// Periodic rollup of alert counts by severity and the busiest rules, sent
+/// to every channel at `severity`. Intervals with no alerts send nothing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestConfig {
+    #[serde(default = "default_digest_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_digest_interval_secs")]
+    pub interval_secs: u64,
+    /// Rules listed by hit count; the rest are left out.
+    #[serde(default = "default_digest_top_rules")]
+    pub top_rules: usize,
+    #[serde(default = "default_digest_severity")]
+    pub severity: String,
+    /// Count only the alerts since the previous digest (default); false
+    /// reports running totals since startup.
+    #[serde(default = "default_digest_reset")]
+    pub reset: bool,
+}
+
+/// A `[alerts.digest]` table turns the digest on unless it says otherwise.
+fn default_digest_enabled() -> bool {
+    true
+}
+
+/// Digests cover the last interval unless `reset = false`.
+fn default_digest_reset() -> bool {
+    true
+}
+
+fn default_digest_interval_secs() -> u64 {
+    3600
+}
+
+fn default_digest_top_rules() -> usize {
+    5
+}
+
+fn default_digest_severity() -> String {
+    "Low".to_string()
+}
+
+/// Off-hours window (UTC, `HH:MM`, may wrap midnight) during which alerts
+/// below `bypass_severity` are held and sent as one digest when it ends.
+/// Critical always goes out immediately.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuietHoursConfig {
+    pub start: String,
+    pub end: String,
+    #[serde(default = "default_quiet_bypass_severity")]
+    pub bypass_severity: String,
+}
+
+fn default_quiet_bypass_severity() -> String {
+    "High".to_string()
+}
+
+/// `"HH:MM"` -> minutes after midnight.
+pub fn parse_time_of_day(s: &str) -> Option<u32> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+fn default_pagerduty_min_severity() -> String {
+    "High".to_string()
+}
+
+fn default_stale_chain_severity() -> String {
+    "High".to_string()
+}
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plaintext connection upgraded with STARTTLS (required).
+    #[default]
+    Starttls,
+    /// TLS from the first byte (SMTPS).
+    Tls,
+    /// No encryption, e.g. a local relay.
+    None,
+}
+
+fn default_email_min_severity() -> String {
+    "High".to_string()
+}
+
+fn default_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_attachment_threshold() -> usize {
+    1000 // Discord embed fields cap at 1024 chars
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersistenceConfig {
+    /// JSON-lines file every alert is appended to.
+    pub history_file: String,
+    /// How many of the most recent records to reload into the TUI on startup.
+    #[serde(default = "default_restore_last")]
+    pub restore_last: usize,
+}
+
+fn default_restore_last() -> usize {
+    50
+}
+
+/// In-memory alert history shown in the TUI.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryConfig {
+    /// Repeats of the same alert (severity, chain, message) within this many
+    /// seconds bump its count instead of adding a row.
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+    /// Drop rows older than this many seconds. Unset = keep until evicted.
+    #[serde(default)]
+    pub retention_secs: Option<u64>,
+    /// Rows kept before the oldest is evicted.
+    #[serde(default = "default_history_capacity")]
+    pub capacity: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            dedup_window_secs: default_dedup_window_secs(),
+            retention_secs: None,
+            capacity: default_history_capacity(),
+        }
+    }
+}
+
+fn default_dedup_window_secs() -> u64 {
+    60
+}
+
+fn default_history_capacity() -> usize {
+    crate::state::DEFAULT_HISTORY_CAPACITY
+}
+
+/// Prometheus scrape endpoint (`GET /metrics`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+}
+
+fn default_metrics_port() -> u16 {
+    9100
+}
+
+/// Kubernetes liveness (`/healthz`) and readiness (`/readyz`) probes.
+/// Readiness needs a chain whose last block is at most `max_block_age_secs`
+/// old; unset uses each chain's expected block time (stale after 3 misses).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HealthConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_health_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub max_block_age_secs: Option<u64>,
+}
+
+fn default_health_port() -> u16 {
+    8080
+}
+
+/// What happens when events arrive faster than rules and alerts drain them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Listeners wait for room (no event is lost, but a stuck alert channel
+    /// stalls the subscriptions behind it).
+    #[default]
+    Block,
+    /// The oldest queued event is dropped to make room, so listening never stalls.
+    DropOldest,
+}
+
+/// The queue between the chain listeners and the rule engine.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueueConfig {
+    /// Events held before `on_full` kicks in.
+    #[serde(default = "default_queue_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub on_full: QueuePolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self { capacity: default_queue_capacity(), on_full: QueuePolicy::default() }
+    }
+}
+
+fn default_queue_capacity() -> usize {
+    100
+}
+
+/// `text` (default) or `json` lines with event fields as structured
+/// attributes, for Loki/ELK ingestion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Minimum level: trace, debug, info, warn or error. `RUST_LOG`, when
+    /// set, replaces this and `directives`.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Per-module levels, e.g. `{ "evm_event_watchdog::listener" = "warn" }`
+    /// to drop the per-block lines; "off" silences a module.
+    #[serde(default)]
+    pub directives: HashMap<String, String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self { format: LogFormat::default(), level: default_log_level(), directives: HashMap::new() }
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+/// Optional OpenTelemetry span export (OTLP over HTTP/protobuf), one trace
+/// per event: decode -> rules -> dispatch.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Collector traces URL, e.g. `http://localhost:4318/v1/traces`.
+    #[serde(default = "default_otlp_endpoint")]
+    pub otlp_endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { enabled: false, otlp_endpoint: default_otlp_endpoint(), service_name: default_service_name() }
+    }
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://localhost:4318/v1/traces".to_string()
+}
+
+fn default_service_name() -> String {
+    "evm_event_watchdog".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub chains: HashMap<String, ChainConfig>,
     pub contracts: Vec<ContractConfig>,
     pub rules: RulesConfig,
     pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub expected_events: Vec<ExpectedEventConfig>,
+    #[serde(default)]
+    pub persistence: Option<PersistenceConfig>,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub health: HealthConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
 }
 
 impl AppConfig {
-    pub fn new() -> Result<Self, ConfigError> {
-        let builder = Config::builder()
-            .add_source(File::with_name("config"));
-        
-        let cfg = builder.build()?;
+    pub fn new(profile: Option<&str>) -> Result<Self, ConfigError> {
+        Self::load("config", profile)
+    }
+
+    /// Loads the base config at `path`, optionally layering a profile file
+    /// (e.g. `config.prod.toml`) on top of it. Profile values win on conflict.
+    /// `${NAME}` in any string is replaced by the environment variable (see
+    /// `interpolate_env`).
+    pub fn load(path: &str, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let mut builder = Config::builder()
+            .add_source(File::with_name(path));
+
+        if let Some(profile) = profile {
+            let profile_path = profile_path(path, profile);
+            builder = builder.add_source(File::with_name(&profile_path).required(true));
+        }
+
+        let mut cfg = builder.build()?;
+        interpolate_tree(&mut cfg.cache, "")?;
         cfg.try_deserialize()
     }
+
+    /// Semantic checks serde can't express. Returns every problem found (not
+    /// just the first) as a message naming the offending key.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        let mut check_severity = |key: String, value: &str| {
+            if Severity::from_name(value).is_none() {
+                problems.push(format!("{}: unknown severity '{}' (expected Low, Medium, High or Critical)", key, value));
+            }
+        };
+
+        check_severity("rules.transfer_threshold.severity".into(), &self.rules.transfer_threshold.severity);
+        check_severity("rules.ownership_change.severity".into(), &self.rules.ownership_change.severity);
+        check_severity("rules.high_approval.severity".into(), &self.rules.high_approval.severity);
+        if let Some(ping_pong) = &self.rules.ownership_ping_pong {
+            check_severity("rules.ownership_ping_pong.severity".into(), &ping_pong.severity);
+        }
+        if let Some(velocity) = &self.rules.velocity {
+            check_severity("rules.velocity.severity".into(), &velocity.severity);
+        }
+        if let Some(fan_out) = &self.rules.fan_out {
+            check_severity("rules.fan_out.severity".into(), &fan_out.severity);
+        }
+        if let Some(watchlist) = &self.rules.watchlist {
+            check_severity("rules.watchlist.severity".into(), &watchlist.severity);
+        }
+        if let Some(nft) = &self.rules.nft_transfer {
+            check_severity("rules.nft_transfer.severity".into(), &nft.severity);
+        }
+        if let Some(mint_burn) = &self.rules.mint_burn {
+            check_severity("rules.mint_burn.severity".into(), &mint_burn.severity);
+        }
+        if let Some(large_swap) = &self.rules.large_swap {
+            check_severity("rules.large_swap.severity".into(), &large_swap.severity);
+        }
+        if let Some(signed) = &self.rules.signed_threshold {
+            check_severity("rules.signed_threshold.severity".into(), &signed.severity);
+        }
+        if let Some(supply) = &self.rules.supply_change {
+            check_severity("rules.supply_change.severity".into(), &supply.severity);
+        }
+        for (name, chain) in &self.chains {
+            if let Some(gas_spike) = &chain.gas_spike {
+                check_severity(format!("chains.{}.gas_spike.severity", name), &gas_spike.severity);
+            }
+        }
+        for (i, expected) in self.expected_events.iter().enumerate() {
+            check_severity(format!("expected_events[{}].severity", i), &expected.severity);
+        }
+        for contract in &self.contracts {
+            for (event, severity) in &contract.base_severity {
+                check_severity(format!("contracts.{}.base_severity.{}", contract.name, event), severity);
+            }
+        }
+        for name in self.alerts.cooldowns.keys() {
+            check_severity(format!("alerts.cooldowns.{}", name), name);
+        }
+        if let Some(min) = &self.alerts.min_severity {
+            check_severity("alerts.min_severity".into(), min);
+        }
+        check_severity("alerts.pagerduty_min_severity".into(), &self.alerts.pagerduty_min_severity);
+        check_severity("alerts.stale_chain_severity".into(), &self.alerts.stale_chain_severity);
+        if self.alerts.smtp_host.is_some() {
+            check_severity("alerts.email_min_severity".into(), &self.alerts.email_min_severity);
+        }
+        for (i, route) in self.alerts.webhook_url.routes().iter().enumerate() {
+            check_severity(format!("alerts.webhook_url[{}].min_severity", i), &route.min_severity);
+        }
+        if let Some(quiet) = &self.alerts.quiet_hours {
+            check_severity("alerts.quiet_hours.bypass_severity".into(), &quiet.bypass_severity);
+        }
+        if let Some(digest) = &self.alerts.digest {
+            check_severity("alerts.digest.severity".into(), &digest.severity);
+        }
+
+        for channel in self.alerts.batch_windows.keys().filter(|c| !CHANNEL_KEYS.contains(&c.as_str())) {
+            problems.push(format!("alerts.batch_windows.{}: unknown channel (expected one of {})", channel, CHANNEL_KEYS.join(", ")));
+        }
+
+        for (name, chain) in &self.chains {
+            match Url::parse(&chain.rpc_url) {
+                Ok(url) if matches!(url.scheme(), "ws" | "wss" | "http" | "https") => {}
+                Ok(url) => problems.push(format!("chains.{}.rpc_url: unsupported scheme '{}' (use ws, wss, http or https)", name, url.scheme())),
+                Err(e) => problems.push(format!("chains.{}.rpc_url: not a valid URL ({})", name, e)),
+            }
+        }
+
+        if self.contracts.is_empty() {
+            problems.push("contracts: no [[contracts]] configured, nothing to watch".to_string());
+        }
+        for contract in &self.contracts {
+            if !self.chains.contains_key(&contract.chain) {
+                problems.push(format!("contracts.{}.chain: '{}' is not defined under [chains]", contract.name, contract.chain));
+            }
+        }
+        for (i, expected) in self.expected_events.iter().enumerate() {
+            if !self.contracts.iter().any(|c| c.name == expected.contract) {
+                problems.push(format!("expected_events[{}].contract: no contract named '{}'", i, expected.contract));
+            }
+        }
+
+        let threshold = &self.rules.transfer_threshold;
+        if threshold.threshold_unit == ThresholdUnit::Token {
+            let frac_digits = threshold.min_value.split_once('.').map_or(0, |(_, frac)| frac.len());
+            if u8::try_from(frac_digits).ok().and_then(|d| parse_units(&threshold.min_value, d)).is_none() {
+                problems.push(format!(
+                    "rules.transfer_threshold.min_value: '{}' is not a token amount (threshold_unit = \"token\")",
+                    threshold.min_value
+                ));
+            }
+        } else if threshold.min_value.parse::<U256>().is_err() {
+            problems.push(format!(
+                "rules.transfer_threshold.min_value: '{}' is not a whole number of base units",
+                self.rules.transfer_threshold.min_value
+            ));
+        }
+        if self.rules.high_approval.threshold().is_none() {
+            problems.push(format!(
+                "rules.high_approval.threshold: '{}' is not a whole number of base units or \"max\"",
+                self.rules.high_approval.threshold.as_deref().unwrap_or_default()
+            ));
+        }
+        if let Some(drainers) = &self.rules.drainer_spenders {
+            if drainers.min_value.parse::<U256>().is_err() {
+                problems.push(format!(
+                    "rules.drainer_spenders.min_value: '{}' is not a whole number of base units",
+                    drainers.min_value
+                ));
+            }
+        }
+        if let Some(mint_burn) = &self.rules.mint_burn {
+            if !matches!(mint_burn.direction.as_str(), "mint" | "burn" | "both") {
+                problems.push(format!("rules.mint_burn.direction: '{}' (expected mint, burn or both)", mint_burn.direction));
+            }
+            if mint_burn.min_value.parse::<U256>().is_err() {
+                problems.push(format!(
+                    "rules.mint_burn.min_value: '{}' is not a whole number of base units",
+                    mint_burn.min_value
+                ));
+            }
+        }
+        if let Some(large_swap) = &self.rules.large_swap {
+            let bounds = [("min_amount0", &large_swap.min_amount0), ("min_amount1", &large_swap.min_amount1)];
+            if bounds.iter().all(|(_, b)| b.is_none()) {
+                problems.push("rules.large_swap: set min_amount0 and/or min_amount1".to_string());
+            }
+            for (key, bound) in bounds {
+                if let Some(bound) = bound.as_ref().filter(|b| b.parse::<U256>().is_err()) {
+                    problems.push(format!("rules.large_swap.{}: '{}' is not a whole number of base units", key, bound));
+                }
+            }
+        }
+        if let Some(nft) = &self.rules.nft_transfer {
+            for id in nft.token_ids.iter().filter(|id| id.parse::<U256>().is_err()) {
+                problems.push(format!("rules.nft_transfer.token_ids: '{}' is not a token id", id));
+            }
+        }
+        if let Some(signed) = &self.rules.signed_threshold {
+            if signed.min_magnitude.parse::<U256>().is_err() {
+                problems.push(format!(
+                    "rules.signed_threshold.min_magnitude: '{}' is not a whole number of base units",
+                    signed.min_magnitude
+                ));
+            }
+        }
+        if self.rules.ownership_ping_pong.as_ref().is_some_and(|p| p.max_changes == 0) {
+            problems.push("rules.ownership_ping_pong.max_changes: must be at least 1".to_string());
+        }
+        if self.rules.fan_out.as_ref().is_some_and(|f| f.max_recipients == 0) {
+            problems.push("rules.fan_out.max_recipients: must be at least 1".to_string());
+        }
+        if let Some(auto_mute) = &self.rules.auto_mute {
+            if auto_mute.max_hits == 0 {
+                problems.push("rules.auto_mute.max_hits: must be at least 1".to_string());
+            }
+            if auto_mute.window_secs == 0 {
+                problems.push("rules.auto_mute.window_secs: must be at least 1".to_string());
+            }
+        }
+        if self.alerts.digest.as_ref().is_some_and(|d| d.enabled && d.interval_secs == 0) {
+            problems.push("alerts.digest.interval_secs: must be at least 1".to_string());
+        }
+        if let Some(quiet) = &self.alerts.quiet_hours {
+            for (key, value) in [("start", &quiet.start), ("end", &quiet.end)] {
+                if parse_time_of_day(value).is_none() {
+                    problems.push(format!("alerts.quiet_hours.{}: '{}' is not a UTC time like \"22:30\"", key, value));
+                }
+            }
+        }
+        for (name, chain) in &self.chains {
+            let Some(gas_spike) = &chain.gas_spike else { continue };
+            if gas_spike.multiplier.is_nan() || gas_spike.multiplier <= 1.0 {
+                problems.push(format!("chains.{}.gas_spike.multiplier: must be greater than 1", name));
+            }
+            if gas_spike.window_blocks == 0 {
+                problems.push(format!("chains.{}.gas_spike.window_blocks: must be at least 1", name));
+            }
+        }
+        if self.alerts.smtp_host.as_deref().is_some_and(|h| !h.is_empty()) {
+            match &self.alerts.email_from {
+                Some(from) if from.parse::<Mailbox>().is_err() => {
+                    problems.push(format!("alerts.email_from: '{}' is not an email address", from));
+                }
+                Some(_) => {}
+                None => problems.push("alerts.email_from: required when smtp_host is set".to_string()),
+            }
+            if self.alerts.email_to.is_empty() {
+                problems.push("alerts.email_to: required when smtp_host is set".to_string());
+            }
+            for to in self.alerts.email_to.iter().filter(|to| to.parse::<Mailbox>().is_err()) {
+                problems.push(format!("alerts.email_to: '{}' is not an email address", to));
+            }
+        }
+        if let Some(template) = &self.alerts.message_template {
+            if let Err(e) = MessageTemplate::parse(template) {
+                problems.push(format!("alerts.message_template: {}", e));
+            }
+        }
+        for (rule, template) in &self.alerts.message_templates {
+            if let Err(e) = MessageTemplate::parse(template) {
+                problems.push(format!("alerts.message_templates.{}: {}", rule, e));
+            }
+        }
+        if self.telemetry.enabled {
+            let endpoint = &self.telemetry.otlp_endpoint;
+            if !Url::parse(endpoint).is_ok_and(|u| matches!(u.scheme(), "http" | "https")) {
+                problems.push(format!("telemetry.otlp_endpoint: '{}' is not an http(s) URL", endpoint));
+            }
+        }
+        if self.health.enabled && self.metrics.enabled && self.health.port == self.metrics.port {
+            problems.push(format!("health.port: {} is already used by [metrics]", self.health.port));
+        }
+        if let Some(supply) = &self.rules.supply_change {
+            if !(supply.max_change_pct.is_finite() && supply.max_change_pct >= 0.0) {
+                problems.push(format!("rules.supply_change.max_change_pct: must be a percentage >= 0, got {}", supply.max_change_pct));
+            }
+            if supply.poll_interval_secs == 0 {
+                problems.push("rules.supply_change.poll_interval_secs: must be at least 1".to_string());
+            }
+        }
+        if self.queue.capacity == 0 {
+            problems.push("queue.capacity: must be at least 1".to_string());
+        }
+        if self.history.capacity == 0 {
+            problems.push("history.capacity: must be at least 1".to_string());
+        }
+        let is_level = |level: &str| level.parse::<tracing::level_filters::LevelFilter>().is_ok();
+        if !is_level(&self.logging.level) {
+            problems.push(format!("logging.level: unknown level '{}' (expected trace, debug, info, warn or error)", self.logging.level));
+        }
+        for (target, level) in &self.logging.directives {
+            if target.is_empty() || target.contains([',', '=', ' ']) {
+                problems.push(format!("logging.directives: invalid module '{}'", target));
+            } else if !is_level(level) {
+                problems.push(format!("logging.directives.{}: unknown level '{}' (expected trace, debug, info, warn, error or off)", target, level));
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+}
+
+#[cfg(test)]
+impl AlertsConfig {
+    /// Parses the body of an `[alerts]` table, for tests.
+    pub fn from_toml(text: &str) -> Self {
+        Config::builder()
+            .add_source(File::from_str(text, config::FileFormat::Toml))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .expect("valid [alerts] table")
+    }
+}
+
+#[cfg(test)]
+impl AppConfig {
+    /// Parses a whole config file, for tests.
+    pub fn from_toml(text: &str) -> Self {
+        Config::builder()
+            .add_source(File::from_str(text, config::FileFormat::Toml))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .expect("valid config")
+    }
+}
+
+/// Interpolates every string under `value`; `key` is its dotted path, for errors.
+fn interpolate_tree(value: &mut Value, key: &str) -> Result<(), ConfigError> {
+    match &mut value.kind {
+        ValueKind::String(text) => {
+            *text = interpolate_env(text).map_err(|e| ConfigError::Message(format!("{}: {}", key, e)))?;
+        }
+        ValueKind::Table(table) => {
+            for (name, child) in table.iter_mut() {
+                let child_key = if key.is_empty() { name.clone() } else { format!("{}.{}", key, name) };
+                interpolate_tree(child, &child_key)?;
+            }
+        }
+        ValueKind::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                interpolate_tree(child, &format!("{}[{}]", key, i))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces each `${NAME}` in `text` with the environment variable `NAME`
+/// (`.env` is loaded first); `$${` is a literal `${`. A variable that isn't
+/// set is an error rather than an empty string, so a typo can't silently
+/// disable a channel.
+fn interpolate_env(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| "unclosed '${' (write '$${' for a literal)".to_string())?;
+        let name = &after[..end];
+        match std::env::var(name) {
+            Ok(value) => out.push_str(&value),
+            Err(_) => return Err(format!("environment variable '{}' is not set", name)),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// `config` + `prod` -> `config.prod`, resolved next to the base file.
+fn profile_path(path: &str, profile: &str) -> String {
+    let base = Path::new(path);
+    let stem = base.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    base.with_file_name(format!("{}.{}", stem, profile))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest config that deserializes: one chain, one contract, the
+    /// required rule tables and a Discord webhook.
+    const BASE: &str = r#"
+[chains]
+ethereum = { rpc_url = "wss://eth.example", chain_id = 1 }
+polygon = { rpc_url = "wss://polygon.example", chain_id = 137 }
+
+[[contracts]]
+name = "USDT"
+address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+chain = "ethereum"
+events = ["Transfer", "Approval"]
+
+[rules.transfer_threshold]
+min_value = "1000000000"
+severity = "Medium"
+
+[rules.ownership_change]
+severity = "High"
+
+[alerts]
+webhook_url = "https://discord.example/base"
+"#;
+
+    /// Writes `files` (name, contents) into a temp dir and loads its
+    /// `config.toml` with `profile` layered on top.
+    fn load_files(files: &[(&str, &str)], profile: Option<&str>) -> Result<AppConfig, ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, contents) in files {
+            std::fs::write(dir.path().join(name), contents).unwrap();
+        }
+        AppConfig::load(dir.path().join("config").to_str().unwrap(), profile)
+    }
+
+    #[test]
+    fn profile_overrides_webhook_and_inherits_chains() {
+        let profile = "[alerts]\nwebhook_url = \"https://discord.example/prod\"\n";
+        let config = load_files(&[("config.toml", BASE), ("config.prod.toml", profile)], Some("prod")).unwrap();
+
+        let routes = config.alerts.webhook_url.routes();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].url, "https://discord.example/prod");
+        assert_eq!(config.chains.len(), 2);
+        assert_eq!(config.chains["polygon"].chain_id, 137);
+        assert_eq!(config.contracts[0].name, "USDT");
+    }
+
+    #[test]
+    fn missing_profile_file_is_an_error() {
+        assert!(load_files(&[("config.toml", BASE)], Some("staging")).is_err());
+        let config = load_files(&[("config.toml", BASE)], None).unwrap();
+        assert_eq!(config.alerts.webhook_url.routes()[0].url, "https://discord.example/base");
+    }
+
+    /// The validation problems of `BASE` with each (from, to) replacement.
+    fn problems(edits: &[(&str, &str)]) -> Vec<String> {
+        let text = edits.iter().fold(BASE.to_string(), |text, (from, to)| {
+            assert!(text.contains(from), "{}", from);
+            text.replace(from, to)
+        });
+        let config = load_files(&[("config.toml", &text)], None).unwrap();
+        config.validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn invalid_configs_report_every_problem() {
+        assert!(problems(&[]).is_empty());
+
+        let found = problems(&[
+            ("wss://eth.example", "ftp://eth.example"),
+            ("rpc_url = \"wss://polygon.example\"", "rpc_url = \"not a url\""),
+            ("chain = \"ethereum\"", "chain = \"arbitrum\""),
+            ("severity = \"Medium\"", "severity = \"Severe\""),
+            ("min_value = \"1000000000\"", "min_value = \"lots\""),
+        ]);
+        let mut keys: Vec<&str> = found.iter().map(|p| p.split(':').next().unwrap()).collect();
+        keys.sort();
+        assert_eq!(keys, [
+            "chains.ethereum.rpc_url",
+            "chains.polygon.rpc_url",
+            "contracts.USDT.chain",
+            "rules.transfer_threshold.min_value",
+            "rules.transfer_threshold.severity",
+        ], "{:#?}", found);
+        assert!(found.iter().any(|p| p.contains("unsupported scheme 'ftp'")));
+        assert!(found.iter().any(|p| p.contains("'arbitrum' is not defined under [chains]")));
+
+        assert_eq!(problems(&[("[alerts]", "[history]\ncapacity = 0\n\n[alerts]")]), ["history.capacity: must be at least 1"]);
+    }
+
+    #[test]
+    fn approval_thresholds_parse_max_decimals_and_default() {
+        let approval = |table: &str| {
+            let config = load_files(&[("config.toml", &format!("{}\n[rules.high_approval]\n{}", BASE, table))], None).unwrap();
+            (config.rules.high_approval.threshold(), config.rules.high_approval.severity.clone(), config.validate().err().unwrap_or_default())
+        };
+
+        assert_eq!(approval(""), (Some(U256::MAX >> 1), "Critical".to_string(), vec![]));
+        assert_eq!(load_files(&[("config.toml", BASE)], None).unwrap().rules.high_approval.threshold(), Some(U256::MAX >> 1), "no table at all");
+        assert_eq!(approval("threshold = \"max\"").0, Some(U256::MAX));
+        assert_eq!(approval("threshold = \" MAX \"").0, Some(U256::MAX));
+        assert_eq!(approval("threshold = \"1000000\"\nseverity = \"High\"").0, Some(U256::from(1_000_000)));
+        assert_eq!(approval("threshold = \"1000000\"\nseverity = \"High\"").1, "High");
+
+        let (threshold, _, problems) = approval("threshold = \"1.5\"");
+        assert_eq!(threshold, None);
+        assert_eq!(problems, ["rules.high_approval.threshold: '1.5' is not a whole number of base units or \"max\""]);
+    }
+
+    #[test]
+    fn env_references_are_filled_in_and_missing_ones_named() {
+        let with_hook = |url: &str| BASE.replace("[alerts]\n", &format!("[alerts]\ngeneric_webhook_url = \"{}\"\n", url));
+        // Names unique to this test, as the environment is process-wide
+        std::env::set_var("WATCHDOG_TEST_HOOK_TOKEN", "abc/123");
+        let config = load_files(&[("config.toml", &with_hook("https://hooks.example/${WATCHDOG_TEST_HOOK_TOKEN}?v=1"))], None).unwrap();
+        assert_eq!(config.alerts.generic_webhook_url.as_deref(), Some("https://hooks.example/abc/123?v=1"));
+
+        let config = load_files(&[("config.toml", &with_hook("price: $${USD}"))], None).unwrap();
+        assert_eq!(config.alerts.generic_webhook_url.as_deref(), Some("price: ${USD}"), "a doubled $ is literal");
+
+        let err = load_files(&[("config.toml", &with_hook("${WATCHDOG_TEST_NEVER_SET}"))], None).unwrap_err();
+        assert_eq!(err.to_string(), "alerts.generic_webhook_url: environment variable 'WATCHDOG_TEST_NEVER_SET' is not set");
+
+        assert_eq!(interpolate_env("${UNCLOSED").unwrap_err(), "unclosed '${' (write '$${' for a literal)");
+    }
 }
@@ -0,0 +1,170 @@
+//! Helpers for turning ABI-decoded values into the JSON stored in
+//! `NormalizedEvent::data`.
+//!
+//! EVM limitation: an `indexed` parameter of dynamic type (`string`, `bytes`,
+//! arrays, tuples) is not stored in the log. The topic only holds
+//! `keccak256` of the encoded value, so the original value is unrecoverable.
+//! Such params are surfaced as the raw topic hash, explicitly marked as
+//! hashed, rather than being passed off as a decoded `bytes32`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use alloy::dyn_abi::{DynSolType, DynSolValue, EventExt, Specifier};
+use alloy::hex;
+use alloy::json_abi::{Event, JsonAbi};
+use alloy::primitives::{LogData, B256};
+use eyre::{Result, WrapErr};
+use serde_json::{json, Map, Value};
+
+/// Events from a user-supplied JSON ABI, keyed by topic0. Anonymous events
+/// have no selector topic and are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct AbiEvents {
+    events: HashMap<B256, Event>,
+}
+
+impl AbiEvents {
+    /// Reads a JSON ABI file: either a bare ABI array or a build artifact
+    /// (Hardhat/Foundry) with the ABI under `"abi"`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Cannot read ABI file {}", path.display()))?;
+        let json: Value = serde_json::from_str(&raw)
+            .wrap_err_with(|| format!("ABI file {} is not valid JSON", path.display()))?;
+        let abi_json = match json {
+            Value::Object(mut artifact) => artifact.remove("abi").unwrap_or_default(),
+            other => other,
+        };
+        let abi: JsonAbi = serde_json::from_value(abi_json)
+            .wrap_err_with(|| format!("ABI file {} is not a valid ABI", path.display()))?;
+
+        let events = abi.events()
+            .filter(|e| !e.anonymous)
+            .map(|e| (e.selector(), e.clone()))
+            .collect();
+        Ok(Self { events })
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// topic0 of every event called `name` (overloads included).
+    pub fn selectors(&self, name: &str) -> Vec<B256> {
+        self.events.iter().filter(|(_, e)| e.name == name).map(|(selector, _)| *selector).collect()
+    }
+
+    /// Decodes a log whose topic0 matches one of the ABI's events into
+    /// (EventName, JSON object of named params). Unnamed params become `arg<N>`.
+    pub fn decode(&self, log: &LogData) -> Option<(String, Value)> {
+        let event = self.events.get(log.topics().first()?)?;
+        let decoded = event.decode_log(log, true).ok()?;
+
+        let mut params = Map::new();
+        let (mut indexed, mut body) = (decoded.indexed.iter(), decoded.body.iter());
+        let mut topics = log.topics().iter().skip(1);
+        for (i, input) in event.inputs.iter().enumerate() {
+            let name = if input.name.is_empty() { format!("arg{}", i) } else { input.name.clone() };
+            let value = if input.indexed {
+                let ty = input.resolve().ok()?;
+                indexed_param_json(&ty, *topics.next()?, indexed.next()?)
+            } else {
+                value_json(body.next()?)
+            };
+            params.insert(name, value);
+        }
+        Some((event.name.clone(), Value::Object(params)))
+    }
+}
+
+/// True when an indexed param of this type is stored as a hash in its topic.
+pub fn is_hashed_topic(ty: &DynSolType) -> bool {
+    !matches!(
+        ty,
+        DynSolType::Address
+            | DynSolType::Function
+            | DynSolType::Bool
+            | DynSolType::FixedBytes(_)
+            | DynSolType::Int(_)
+            | DynSolType::Uint(_)
+    )
+}
+
+/// JSON for an indexed param given its declared type and decoded topic value.
+pub fn indexed_param_json(ty: &DynSolType, topic: B256, decoded: &DynSolValue) -> Value {
+    if is_hashed_topic(ty) {
+        json!({
+            "hashed": true,
+            "type": ty.sol_type_name(),
+            "topic": topic.to_string(),
+            "note": "indexed dynamic value; topic is keccak256 of the value and is not recoverable",
+        })
+    } else {
+        value_json(decoded)
+    }
+}
+
+/// Converts a decoded value to JSON. Integers are decimal strings so rules can
+/// `parse::<U256>()` them the same way as the typed decoders' output.
+pub fn value_json(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(b) => Value::Bool(*b),
+        DynSolValue::Int(i, _) => Value::String(i.to_string()),
+        DynSolValue::Uint(u, _) => Value::String(u.to_string()),
+        DynSolValue::FixedBytes(word, size) => Value::String(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Address(a) => Value::String(a.to_string()),
+        DynSolValue::Function(f) => Value::String(hex::encode_prefixed(f.as_slice())),
+        DynSolValue::Bytes(b) => Value::String(hex::encode_prefixed(b)),
+        DynSolValue::String(s) => Value::String(s.clone()),
+        DynSolValue::Array(items) | DynSolValue::FixedArray(items) | DynSolValue::Tuple(items) => {
+            Value::Array(items.iter().map(value_json).collect())
+        }
+        #[allow(unreachable_patterns)]
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, keccak256, U256};
+
+    /// Loads `abi` (JSON text) through a temp file, as `[[contracts]] abi` would.
+    fn load_abi(abi: &str) -> AbiEvents {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), abi).unwrap();
+        AbiEvents::load(file.path()).unwrap()
+    }
+
+    #[test]
+    fn indexed_string_is_marked_as_hash() {
+        let events = load_abi(r#"[{"type":"event","name":"Registered","anonymous":false,"inputs":[
+            {"name":"name","type":"string","indexed":true},
+            {"name":"owner","type":"address","indexed":true},
+            {"name":"fee","type":"uint256","indexed":false}]}]"#);
+        let owner = address!("28C6c06298d514Db089934071355E5743bf21d60");
+        let name_hash = keccak256("alice.eth");
+        let log = LogData::new_unchecked(
+            vec![keccak256("Registered(string,address,uint256)"), name_hash, owner.into_word()],
+            U256::from(42).to_be_bytes_vec().into(),
+        );
+
+        let (event, data) = events.decode(&log).unwrap();
+        assert_eq!(event, "Registered");
+        assert_eq!(data["name"]["hashed"], true);
+        assert_eq!(data["name"]["type"], "string");
+        assert_eq!(data["name"]["topic"], name_hash.to_string());
+        assert_eq!(data["owner"], owner.to_string());
+        assert_eq!(data["fee"], "42");
+    }
+
+    #[test]
+    fn static_indexed_types_are_decoded() {
+        assert!(!is_hashed_topic(&DynSolType::Address));
+        assert!(!is_hashed_topic(&DynSolType::Uint(256)));
+        assert!(!is_hashed_topic(&DynSolType::FixedBytes(32)));
+        assert!(is_hashed_topic(&DynSolType::String));
+        assert!(is_hashed_topic(&DynSolType::Bytes));
+        assert!(is_hashed_topic(&DynSolType::Array(Box::new(DynSolType::Address))));
+    }
+}
@@ -1,7 +1,12 @@
-use alloy::primitives::{Address, B256, U256};
+use alloy::primitives::{Address, LogData, B256, I256, U256};
+use alloy::rpc::types::Log as RpcLog;
+use alloy::sol_types::SolEvent;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use alloy::sol;
 
+pub mod abi;
+
 sol! {
     #[derive(Debug, Serialize, Deserialize)]
     event OwnershipTransferred(address indexed previousOwner, address indexed newOwner);
@@ -13,15 +18,166 @@ sol! {
     event Approval(address indexed owner, address indexed spender, uint256 value);
 }
 
+// ERC-721 `Transfer` has ERC-20's signature hash but indexes the token id,
+// so only the topic count (4 vs 3) tells the two apart
+pub mod erc721 {
+    use serde::{Deserialize, Serialize};
+
+    alloy::sol! {
+        #[derive(Debug, Serialize, Deserialize)]
+        event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+    }
+}
+
+// Both pool versions name their event `Swap`, so each gets its own module
+pub mod uniswap_v2 {
+    alloy::sol! {
+        #[derive(Debug)]
+        event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to);
+    }
+}
+
+pub mod uniswap_v3 {
+    alloy::sol! {
+        #[derive(Debug)]
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+    }
+}
+
+/// topic0 values of a built-in event by config name (`Swap` covers both
+/// Uniswap versions). Empty for names only an ABI can resolve.
+pub fn builtin_signatures(name: &str) -> Vec<B256> {
+    match name {
+        "Transfer" | "NftTransfer" => vec![Transfer::SIGNATURE_HASH],
+        "Approval" => vec![Approval::SIGNATURE_HASH],
+        "OwnershipTransferred" => vec![OwnershipTransferred::SIGNATURE_HASH],
+        "Swap" => vec![uniswap_v2::Swap::SIGNATURE_HASH, uniswap_v3::Swap::SIGNATURE_HASH],
+        _ => Vec::new(),
+    }
+}
+
+/// Decodes a `Transfer` log by its topic layout: ERC-20 (`from`, `to`
+/// indexed, `value` in data) has 3 topics, ERC-721 (`tokenId` indexed too,
+/// no data) has 4 and comes back as `NftTransfer`.
+pub fn decode_transfer(log: &RpcLog) -> Option<(EventType, serde_json::Value)> {
+    match log.topics().len() {
+        3 => {
+            let decoded = Transfer::decode_log(&log.inner, true).ok()?;
+            Some((EventType::Transfer, event_data(&decoded, log)))
+        }
+        4 => {
+            let decoded = erc721::Transfer::decode_log(&log.inner, true).ok()?;
+            Some((EventType::NftTransfer, event_data(&decoded, log)))
+        }
+        _ => None,
+    }
+}
+
+/// A decoded event as rule data. Should serialization fail, the event still
+/// goes through with its raw `topics` and `data` (as `forward_unknown` sends
+/// them) rather than as `null`, which no rule would match.
+pub fn event_data<T: Serialize>(decoded: &T, log: &RpcLog) -> serde_json::Value {
+    serde_json::to_value(decoded).unwrap_or_else(|e| {
+        tracing::warn!(
+            "Could not serialize decoded event (tx {:?}, topic {:?}), forwarding raw log data: {}",
+            log.transaction_hash, log.topics().first(), e
+        );
+        raw_log_data(log)
+    })
+}
+
+/// `{"topics": [...], "data": "0x..."}` of an undecoded log.
+pub fn raw_log_data(log: &RpcLog) -> serde_json::Value {
+    json!({
+        "topics": log.topics().iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+        "data": log.data().data.to_string(),
+    })
+}
+
+/// Decodes a Uniswap V2 or V3 pool `Swap` into one shape for both versions:
+/// `amount{0,1}_{in,out}` as decimal base-unit strings (V3's signed deltas
+/// split by sign: positive flowed into the pool), plus `sender`,
+/// `recipient` and `version`.
+pub fn decode_swap(log: &LogData) -> Option<serde_json::Value> {
+    let topic0 = *log.topics().first()?;
+    if topic0 == uniswap_v2::Swap::SIGNATURE_HASH {
+        let swap = uniswap_v2::Swap::decode_raw_log(log.topics().iter().copied(), &log.data, true).ok()?;
+        return Some(json!({
+            "version": "v2",
+            "sender": swap.sender,
+            "recipient": swap.to,
+            "amount0_in": swap.amount0In.to_string(),
+            "amount1_in": swap.amount1In.to_string(),
+            "amount0_out": swap.amount0Out.to_string(),
+            "amount1_out": swap.amount1Out.to_string(),
+        }));
+    }
+    if topic0 == uniswap_v3::Swap::SIGNATURE_HASH {
+        let swap = uniswap_v3::Swap::decode_raw_log(log.topics().iter().copied(), &log.data, true).ok()?;
+        let (amount0_in, amount0_out) = split_delta(swap.amount0);
+        let (amount1_in, amount1_out) = split_delta(swap.amount1);
+        return Some(json!({
+            "version": "v3",
+            "sender": swap.sender,
+            "recipient": swap.recipient,
+            "amount0_in": amount0_in.to_string(),
+            "amount1_in": amount1_in.to_string(),
+            "amount0_out": amount0_out.to_string(),
+            "amount1_out": amount1_out.to_string(),
+            "sqrt_price_x96": swap.sqrtPriceX96.to_string(),
+            "liquidity": swap.liquidity.to_string(),
+            "tick": swap.tick,
+        }));
+    }
+    None
+}
+
+/// A V3 pool balance delta as (in, out): positive is paid into the pool.
+fn split_delta(delta: I256) -> (U256, U256) {
+    if delta.is_negative() {
+        (U256::ZERO, delta.unsigned_abs())
+    } else {
+        (delta.unsigned_abs(), U256::ZERO)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventType {
     Transfer,
+    /// ERC-721 `Transfer`: `from`, `to` and `tokenId` (see `decode_transfer`).
+    NftTransfer,
     OwnershipTransferred,
     Approval,
+    /// Uniswap V2/V3 pool swap (see `decode_swap` for the data shape).
+    Swap,
+    /// Synthetic: the contract's bytecode hash changed between polls.
+    CodeChanged,
+    /// Synthetic: a block's base fee spiked above the chain's recent average.
+    GasSpike,
+    /// Synthetic: the token's `totalSupply()` differs from the last poll.
+    SupplyChanged,
     Unknown(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+impl EventType {
+    /// Name as written in config (e.g. `events = ["Transfer"]`).
+    pub fn name(&self) -> &str {
+        match self {
+            EventType::Transfer => "Transfer",
+            EventType::NftTransfer => "NftTransfer",
+            EventType::OwnershipTransferred => "OwnershipTransferred",
+            EventType::Approval => "Approval",
+            EventType::Swap => "Swap",
+            EventType::CodeChanged => "CodeChanged",
+            EventType::GasSpike => "GasSpike",
+            EventType::SupplyChanged => "SupplyChanged",
+            EventType::Unknown(sig) => sig,
+        }
+    }
+}
+
+// Variant order defines escalation: Low < Medium < High < Critical
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Severity {
     Low,
     Medium,
@@ -29,14 +185,260 @@ pub enum Severity {
     Critical,
 }
 
+impl Severity {
+    /// Parses a config severity name ("Low", "Medium", "High", "Critical").
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "Low" => Some(Severity::Low),
+            "Medium" => Some(Severity::Medium),
+            "High" => Some(Severity::High),
+            "Critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NormalizedEvent {
     pub chain_id: u64,
     pub chain_name: String,
     pub contract_address: Address,
+    /// The `[[contracts]]` name of the emitter ("USDC"), empty when unknown
+    /// (synthetic events, recordings from older versions).
+    #[serde(default)]
+    pub contract_name: String,
     pub tx_hash: B256,
     pub block_number: u64,
     pub event_type: EventType,
     pub severity: Severity,
     pub data: serde_json::Value, // Flexible payload for rule engine
+    /// Set when a reorg removed the log this event was decoded from.
+    #[serde(default)]
+    pub removed: bool,
+    /// Token decimals of the emitting contract, when known, for display.
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    /// Replayed by the startup backfill rather than seen live.
+    #[serde(default)]
+    pub historical: bool,
+    /// Lifecycle span (decode -> rules -> dispatch), exported over OTLP when
+    /// `[telemetry]` is enabled.
+    #[serde(skip, default = "tracing::Span::none")]
+    pub span: tracing::Span,
+}
+
+impl NormalizedEvent {
+    /// Root span for one event; `event_type` is recorded once it is decoded.
+    pub fn new_span(chain_id: u64, tx_hash: B256) -> tracing::Span {
+        tracing::info_span!("event", chain_id, tx_hash = %tx_hash, event_type = tracing::field::Empty)
+    }
+
+    /// "USDC (0xA0b8…)" for a named contract, else the bare address.
+    pub fn contract_label(&self) -> String {
+        contract_label(&self.contract_name, self.contract_address)
+    }
+
+    /// A raw token amount in human units when decimals are known, else as-is.
+    pub fn format_amount(&self, value: U256) -> String {
+        match self.decimals {
+            Some(decimals) => format_units(value, decimals),
+            None => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl NormalizedEvent {
+    /// An ethereum USDT event in block 1 of tx 0x0101…, for tests.
+    pub fn sample(event_type: EventType, data: serde_json::Value) -> Self {
+        Self {
+            chain_id: 1,
+            chain_name: "ethereum".to_string(),
+            contract_address: alloy::primitives::address!("dAC17F958D2ee523a2206206994597C13D831ec7"),
+            contract_name: "USDT".to_string(),
+            tx_hash: B256::repeat_byte(1),
+            block_number: 1,
+            event_type,
+            severity: Severity::Low,
+            data,
+            removed: false,
+            decimals: Some(6),
+            historical: false,
+            span: tracing::Span::none(),
+        }
+    }
+}
+
+/// "name (address)", or just the address when `name` is empty.
+pub fn contract_label(name: &str, address: Address) -> String {
+    if name.is_empty() {
+        address.to_string()
+    } else {
+        format!("{} ({})", name, address)
+    }
+}
+
+/// Inverse of `format_units`: "1.5" with 6 decimals -> 1500000. None for
+/// malformed text, amounts too large for U256, or more significant
+/// fractional digits than `decimals` can hold.
+pub fn parse_units(text: &str, decimals: u8) -> Option<U256> {
+    let text = text.trim();
+    let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+    let frac = frac.trim_end_matches('0');
+    let digits_only = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !digits_only(int) || !digits_only(frac) || frac.len() > decimals as usize {
+        return None;
+    }
+    let digits = format!("{}{:0<width$}", int, frac, width = decimals as usize);
+    if digits.is_empty() {
+        return Some(U256::ZERO);
+    }
+    digits.parse().ok()
+}
+
+/// Renders a base-unit amount as a decimal string, e.g. 5e18 with 18
+/// decimals -> "5.0", 1234500 with 6 -> "1.2345". Works on the full U256 range.
+pub fn format_units(value: U256, decimals: u8) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (int, frac) = padded.split_at(padded.len() - decimals);
+    let frac = frac.trim_end_matches('0');
+    format!("{}.{}", int, if frac.is_empty() { "0" } else { frac })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_orders_by_escalation() {
+        assert!(Severity::Low < Severity::Medium && Severity::Medium < Severity::High && Severity::High < Severity::Critical);
+        let mut severities = vec![Severity::High, Severity::Low, Severity::Critical, Severity::Medium];
+        severities.sort();
+        assert_eq!(severities, [Severity::Low, Severity::Medium, Severity::High, Severity::Critical]);
+        assert_eq!(Severity::Medium.max(Severity::High), Severity::High, "escalation keeps the higher one");
+        assert_eq!(Severity::from_name("Critical"), Some(Severity::Critical));
+        assert_eq!(Severity::from_name("critical"), None);
+    }
+
+    #[test]
+    fn transfers_decode_by_topic_layout() {
+        let (token, from, to) = (Address::repeat_byte(0xaa), Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let log = |data: LogData| RpcLog { inner: alloy::primitives::Log { address: token, data }, ..Default::default() };
+
+        let erc20 = Transfer { from, to, value: U256::from(5_000_000u64) }.encode_log_data();
+        assert_eq!(erc20.topics().len(), 3);
+        let (event_type, data) = decode_transfer(&log(erc20)).unwrap();
+        assert!(matches!(event_type, EventType::Transfer));
+        assert_eq!(data["from"].as_str().unwrap().parse::<Address>().unwrap(), from);
+        assert_eq!(data["to"].as_str().unwrap().parse::<Address>().unwrap(), to);
+        assert_eq!(data["value"].as_str().unwrap().parse::<U256>().unwrap(), U256::from(5_000_000u64));
+
+        // Same signature, but tokenId is indexed and there is no data
+        let erc721 = erc721::Transfer { from, to, tokenId: U256::from(42) }.encode_log_data();
+        assert_eq!(erc721.topics().len(), 4);
+        assert!(erc721.data.is_empty());
+        let (event_type, data) = decode_transfer(&log(erc721)).unwrap();
+        assert!(matches!(event_type, EventType::NftTransfer));
+        assert_eq!(data["tokenId"].as_str().unwrap().parse::<U256>().unwrap(), U256::from(42));
+        assert_eq!(data["to"].as_str().unwrap().parse::<Address>().unwrap(), to);
+        assert!(data.get("value").is_none());
+
+        let malformed = LogData::new_unchecked(vec![Transfer::SIGNATURE_HASH, from.into_word()], Default::default());
+        assert!(decode_transfer(&log(malformed)).is_none());
+    }
+
+    /// Serializes like a map with non-string keys, which JSON can't hold.
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            std::collections::HashMap::from([((1u8, 2u8), 3u8)]).serialize(serializer)
+        }
+    }
+
+    #[test]
+    fn unserializable_events_fall_back_to_the_raw_log() {
+        let (from, to) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let data = Transfer { from, to, value: U256::from(0x2a) }.encode_log_data();
+        let log = RpcLog {
+            inner: alloy::primitives::Log { address: Address::repeat_byte(0xaa), data },
+            transaction_hash: Some(B256::repeat_byte(7)),
+            ..Default::default()
+        };
+
+        // Rules still get the log's topics and data rather than `null`
+        let fallback = event_data(&Unserializable, &log);
+        assert_eq!(fallback, raw_log_data(&log));
+        assert_eq!(fallback["topics"], json!([Transfer::SIGNATURE_HASH.to_string(), from.into_word().to_string(), to.into_word().to_string()]));
+        assert_eq!(fallback["data"], B256::from(U256::from(0x2a)).to_string());
+
+        // Serializable events come through decoded
+        let decoded = Transfer::decode_log(&log.inner, true).unwrap();
+        assert_eq!(event_data(&decoded, &log)["value"], "0x2a");
+    }
+
+    #[test]
+    fn v2_and_v3_swaps_decode_to_one_shape() {
+        let (router, trader) = (Address::repeat_byte(0x7a), Address::repeat_byte(0x11));
+        // 1 WETH in, 3000 USDC out
+        let v2 = uniswap_v2::Swap {
+            sender: router,
+            amount0In: U256::ZERO,
+            amount1In: U256::from(10u64).pow(U256::from(18)),
+            amount0Out: U256::from(3_000_000_000u64),
+            amount1Out: U256::ZERO,
+            to: trader,
+        }.encode_log_data();
+        let swap = decode_swap(&v2).unwrap();
+        assert_eq!(swap["version"], "v2");
+        assert_eq!(swap["sender"].as_str().unwrap().parse::<Address>().unwrap(), router);
+        assert_eq!(swap["recipient"].as_str().unwrap().parse::<Address>().unwrap(), trader);
+        assert_eq!(
+            [&swap["amount0_in"], &swap["amount1_in"], &swap["amount0_out"], &swap["amount1_out"]],
+            [&json!("0"), &json!("1000000000000000000"), &json!("3000000000"), &json!("0")]
+        );
+
+        // The same trade on V3: the pool paid out token0, took in token1
+        let v3 = uniswap_v3::Swap {
+            sender: router,
+            recipient: trader,
+            amount0: I256::try_from(-3_000_000_000i64).unwrap(),
+            amount1: I256::try_from(10i128.pow(18)).unwrap(),
+            sqrtPriceX96: Default::default(),
+            liquidity: 0,
+            tick: Default::default(),
+        }.encode_log_data();
+        let swap = decode_swap(&v3).unwrap();
+        assert_eq!(swap["version"], "v3");
+        assert_eq!((&swap["amount0_out"], &swap["amount1_in"]), (&json!("3000000000"), &json!("1000000000000000000")));
+        assert_eq!((&swap["amount0_in"], &swap["amount1_out"]), (&json!("0"), &json!("0")));
+
+        assert!(decode_swap(&Transfer { from: router, to: trader, value: U256::from(1) }.encode_log_data()).is_none());
+    }
+
+    #[test]
+    fn amounts_format_in_token_units() {
+        let ether = U256::from(10u64).pow(U256::from(18));
+        assert_eq!(format_units(ether * U256::from(5), 18), "5.0");
+        assert_eq!(format_units(U256::from(1_500_000_000_000_000u64), 18), "0.0015");
+        assert_eq!(format_units(U256::from(1), 18), "0.000000000000000001");
+        // Beyond u128
+        assert_eq!(format_units(U256::MAX, 18), "115792089237316195423570985008687907853269984665640564039457.584007913129639935");
+
+        // USDC
+        assert_eq!(format_units(U256::from(1_234_500u64), 6), "1.2345");
+        assert_eq!(format_units(U256::from(250_000_000_000u64), 6), "250000.0");
+        assert_eq!(format_units(U256::ZERO, 6), "0.0");
+        assert_eq!(parse_units("1.2345", 6), Some(U256::from(1_234_500u64)));
+
+        let mut event = NormalizedEvent::sample(EventType::Transfer, serde_json::json!({}));
+        assert_eq!(event.format_amount(U256::from(5_000_000u64)), "5.0");
+        event.decimals = None;
+        assert_eq!(event.format_amount(U256::from(5_000_000u64)), "5000000");
+    }
 }
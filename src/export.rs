@@ -0,0 +1,83 @@
+use eyre::{Result, WrapErr};
+use std::io::Write;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::state::AlertRecord;
+
+/// Column order of `--export-csv` files.
+pub const CSV_HEADER: [&str; 5] = ["chain", "severity", "message", "timestamp", "count"];
+
+/// Writes `records` as CSV, one row per record under a `CSV_HEADER` row.
+/// Messages are quoted as needed, so commas, quotes and newlines survive.
+pub fn write_csv<W: Write>(writer: W, records: &[AlertRecord]) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(CSV_HEADER)?;
+    for record in records {
+        csv.write_record([
+            record.chain.as_str(),
+            &format!("{:?}", record.severity),
+            &record.message,
+            &iso8601(record.timestamp),
+            &record.count.to_string(),
+        ])?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+/// Writes `records` to a new CSV file at `path`, replacing any existing one.
+pub fn export_csv(path: &Path, records: &[AlertRecord]) -> Result<()> {
+    let file = std::fs::File::create(path).wrap_err_with(|| format!("cannot create {}", path.display()))?;
+    write_csv(file, records)
+}
+
+/// Unix seconds as an ISO-8601 UTC timestamp, e.g. "2024-05-01T12:00:00Z".
+pub fn iso8601(timestamp: u64) -> String {
+    i64::try_from(timestamp).ok()
+        .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Severity;
+
+    fn record(severity: Severity, chain: &str, message: &str, timestamp: u64, count: u64) -> AlertRecord {
+        AlertRecord { severity, chain: chain.to_string(), message: message.to_string(), timestamp, count }
+    }
+
+    #[test]
+    fn csv_round_trips_messages_in_column_order() {
+        let records = [
+            record(Severity::Critical, "ethereum", "Large Transfer Detected: 1,000,000 > 1,000", 1_714_564_800, 3),
+            record(Severity::Low, "polygon", "Contract \"USDC\" paused\nby 0xdead", 0, 1),
+        ];
+        let mut out = Vec::new();
+        write_csv(&mut out, &records).unwrap();
+
+        let mut reader = csv::Reader::from_reader(out.as_slice());
+        assert_eq!(reader.headers().unwrap(), &csv::StringRecord::from(CSV_HEADER.to_vec()));
+        let rows: Vec<Vec<String>> = reader.records()
+            .map(|row| row.unwrap().iter().map(String::from).collect())
+            .collect();
+        assert_eq!(rows, [
+            ["ethereum", "Critical", "Large Transfer Detected: 1,000,000 > 1,000", "2024-05-01T12:00:00Z", "3"],
+            ["polygon", "Low", "Contract \"USDC\" paused\nby 0xdead", "1970-01-01T00:00:00Z", "1"],
+        ]);
+    }
+
+    #[test]
+    fn export_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.csv");
+        std::fs::write(&path, "stale").unwrap();
+        export_csv(&path, &[]).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "chain,severity,message,timestamp,count\n");
+
+        assert!(export_csv(&dir.path().join("missing/alerts.csv"), &[]).is_err());
+    }
+}
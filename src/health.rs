@@ -0,0 +1,208 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use eyre::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+use crate::events::Severity;
+use crate::state::{AppState, ChainStatus};
+
+/// Serves Kubernetes probes on `0.0.0.0:port`: `GET /healthz` is 200 while
+/// the process runs, `GET /readyz` is 200 only while some chain is fresh
+/// (see `is_ready`), else 503. Same one-request-per-connection shape as the
+/// metrics endpoint.
+pub async fn serve(state: Arc<AppState>, port: u16, max_block_age: Option<Duration>) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Health endpoints listening on :{}/healthz and /readyz", port);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &state, max_block_age).await {
+                warn!("Health request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: &AppState, max_block_age: Option<Duration>) -> Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, body) = match (request.starts_with("GET "), path) {
+        (true, "/healthz") => ("200 OK", "ok\n"),
+        (true, "/readyz") if is_ready(state, Instant::now(), max_block_age) => ("200 OK", "ready\n"),
+        (true, "/readyz") => ("503 Service Unavailable", "no chain has a recent block\n"),
+        _ => ("404 Not Found", "not found\n"),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// True when at least one chain's last block is no older than
+/// `max_block_age`, or, when unset, not stale by the health panel's measure
+/// (a few missed block intervals). No block yet means not ready.
+pub fn is_ready(state: &AppState, now: Instant, max_block_age: Option<Duration>) -> bool {
+    state.chain_health(now).iter().any(|(_, age, stale)| match max_block_age {
+        Some(max) => *age <= max.as_secs(),
+        None => !stale,
+    })
+}
+
+/// Identifies a chain's staleness in `AppState::open_conditions`.
+fn stale_condition_key(chain: &str) -> String {
+    format!("stale:{}", chain)
+}
+
+/// Raises a condition for each chain whose blocks went stale (the health
+/// panel's measure, which includes block-timestamp lag) and resolves it
+/// once the chain catches up. Returns (ChainName, Message) for every
+/// transition since the last check, RESOLVED notices included.
+pub fn check_stale_chains(state: &AppState, now: Instant, severity: &Severity) -> Vec<(String, String)> {
+    let mut transitions = Vec::new();
+    for (chain, age, stale) in state.chain_health(now) {
+        let key = stale_condition_key(&chain);
+        if stale && !state.condition_open(&key) {
+            let message = format!("Chain Stale: last {} block is {}s old", chain, age);
+            state.raise_condition(&key, severity.clone(), chain.clone(), message.clone());
+            transitions.push((chain, message));
+        } else if !stale && state.resolve_condition(&key) {
+            transitions.push((chain.clone(), format!("RESOLVED: {} caught up, last block {}s old", chain, age)));
+        }
+    }
+    transitions
+}
+
+/// Identifies a chain's open circuit breaker in `AppState::open_conditions`.
+fn down_condition_key(chain: &str) -> String {
+    format!("down:{}", chain)
+}
+
+/// Raises a condition for each chain whose breaker is open (the supervisor
+/// marked it `Down` and retries it with backoff) and resolves it once the
+/// chain is no longer down: reconnected, or dropped from the config. A
+/// retry in progress (`Connecting`) keeps it open. Same return shape as
+/// `check_stale_chains`.
+pub fn check_down_chains(state: &AppState, severity: &Severity) -> Vec<(String, String)> {
+    let mut transitions = Vec::new();
+    let unconnected = state.unconnected_chains();
+    for (chain, status) in &unconnected {
+        let key = down_condition_key(chain);
+        if *status == ChainStatus::Down && !state.condition_open(&key) {
+            let message = format!("Chain Down: {} RPC unreachable, retrying in the background", chain);
+            state.raise_condition(&key, severity.clone(), chain.clone(), message.clone());
+            transitions.push((chain.clone(), message));
+        }
+    }
+
+    let open: Vec<String> = match state.open_conditions.lock() {
+        Ok(open) => open.keys().filter_map(|key| key.strip_prefix("down:")).map(String::from).collect(),
+        Err(_) => Vec::new(),
+    };
+    for chain in open {
+        if !unconnected.iter().any(|(c, _)| *c == chain) && state.resolve_condition(&down_condition_key(&chain)) {
+            transitions.push((chain.clone(), format!("RESOLVED: {} RPC is reachable again", chain)));
+        }
+    }
+    transitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AlertStatus;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unix_now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    /// GETs `path` from `handle` over a real socket; returns (status, body).
+    async fn get(state: Arc<AppState>, path: &str, max_block_age: Option<Duration>) -> (u16, String) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle(stream, &state, max_block_age).await.unwrap();
+        });
+        let resp = reqwest::get(url).await.unwrap();
+        (resp.status().as_u16(), resp.text().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_the_freshest_chain() {
+        let state = Arc::new(AppState::new());
+        state.set_expected_block_time("ethereum", Duration::from_secs(12));
+        assert_eq!(get(state.clone(), "/readyz", None).await.0, 503, "no block yet");
+        assert_eq!(get(state.clone(), "/healthz", None).await, (200, "ok\n".to_string()));
+
+        state.update_block("ethereum", 100, Some(unix_now()));
+        assert_eq!(get(state.clone(), "/readyz", None).await, (200, "ready\n".to_string()));
+
+        // A two-minute-old head is stale by block time, but within an explicit limit
+        state.update_block("ethereum", 101, Some(unix_now() - 120));
+        assert_eq!(get(state.clone(), "/readyz", None).await.0, 503);
+        assert_eq!(get(state.clone(), "/readyz", Some(Duration::from_secs(300))).await.0, 200);
+        assert_eq!(get(state.clone(), "/readyz", Some(Duration::from_secs(60))).await.0, 503);
+
+        // One fresh chain is enough
+        state.update_block("polygon", 5, Some(unix_now()));
+        assert_eq!(get(state, "/readyz", None).await.0, 200);
+    }
+
+    #[test]
+    fn lagging_chain_alerts_once_then_resolves() {
+        let state = AppState::new();
+        state.set_expected_block_time("ethereum", Duration::from_secs(12));
+
+        // A node serving a two-minute-old head on time is still lagging
+        state.update_block("ethereum", 100, Some(unix_now() - 120));
+        let raised = check_stale_chains(&state, Instant::now(), &Severity::High);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].0, "ethereum");
+        assert!(raised[0].1.starts_with("Chain Stale: last ethereum block is 12"), "{}", raised[0].1);
+        assert!(check_stale_chains(&state, Instant::now(), &Severity::High).is_empty(), "still open, not re-raised");
+        assert!(!is_ready(&state, Instant::now(), None));
+
+        state.update_block("ethereum", 101, Some(unix_now()));
+        let resolved = check_stale_chains(&state, Instant::now(), &Severity::High);
+        assert_eq!(resolved.len(), 1);
+        assert!(resolved[0].1.starts_with("RESOLVED: ethereum caught up"), "{}", resolved[0].1);
+        assert!(check_stale_chains(&state, Instant::now(), &Severity::High).is_empty());
+
+        let history = state.alert_history.lock().unwrap();
+        let entry = history.iter().find(|e| e.message.starts_with("Chain Stale")).unwrap();
+        assert_eq!(entry.status, AlertStatus::Resolved);
+    }
+    #[test]
+    fn open_breaker_alerts_once_then_resolves() {
+        let state = AppState::new();
+        state.set_chain_status("ethereum", ChainStatus::Down);
+        let raised = check_down_chains(&state, &Severity::High);
+        assert_eq!(raised, [("ethereum".to_string(), "Chain Down: ethereum RPC unreachable, retrying in the background".to_string())]);
+        assert!(check_down_chains(&state, &Severity::High).is_empty(), "still open, not re-raised");
+
+        // A retry attempt in flight isn't a recovery
+        state.set_chain_status("ethereum", ChainStatus::Connecting);
+        assert!(check_down_chains(&state, &Severity::High).is_empty());
+        state.set_chain_status("ethereum", ChainStatus::Down);
+        assert!(check_down_chains(&state, &Severity::High).is_empty());
+
+        state.set_chain_status("ethereum", ChainStatus::Connected);
+        let resolved = check_down_chains(&state, &Severity::High);
+        assert_eq!(resolved, [("ethereum".to_string(), "RESOLVED: ethereum RPC is reachable again".to_string())]);
+        assert!(check_down_chains(&state, &Severity::High).is_empty());
+
+        let history = state.alert_history.lock().unwrap();
+        let entry = history.iter().find(|e| e.message.starts_with("Chain Down")).unwrap();
+        assert_eq!(entry.status, AlertStatus::Resolved);
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use alloy::primitives::Address;
+use crate::events::{NormalizedEvent, Severity};
+
+/// An event that is expected to show up at least once per `interval`.
+/// Its absence (e.g. a stalled oracle or a skipped rebase) is the alarm.
+#[derive(Debug)]
+pub struct HeartbeatWatch {
+    pub label: String,
+    pub chain_name: String,
+    pub contract_address: Address,
+    pub event_name: String,
+    pub interval: Duration,
+    pub severity: Severity,
+    // (last matching event, last alert fired, currently overdue)
+    timers: Mutex<(Option<Instant>, Instant, bool)>,
+}
+
+impl HeartbeatWatch {
+    pub fn new(label: String, chain_name: String, contract_address: Address, event_name: String, interval: Duration, severity: Severity) -> Self {
+        Self {
+            label,
+            chain_name,
+            contract_address,
+            event_name,
+            interval,
+            severity,
+            // The deadline starts counting from startup
+            timers: Mutex::new((None, Instant::now(), false)),
+        }
+    }
+
+    /// Identifies this watch's condition in `AppState::open_conditions`.
+    pub fn condition_key(&self) -> String {
+        format!("heartbeat:{}:{}:{}", self.chain_name, self.contract_address, self.event_name)
+    }
+
+    fn matches(&self, event: &NormalizedEvent) -> bool {
+        event.chain_name == self.chain_name
+            && event.contract_address == self.contract_address
+            && event.event_type.name() == self.event_name
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HeartbeatMonitor {
+    watches: Vec<HeartbeatWatch>,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(watches: Vec<HeartbeatWatch>) -> Self {
+        Self { watches }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Resets the timer of every watch the event satisfies. Returns a
+    /// "restored" notice (ConditionKey, ChainName, Message, Severity) for each
+    /// watch that was overdue until now.
+    pub fn observe(&self, event: &NormalizedEvent) -> Vec<(String, String, String, Severity)> {
+        let mut restored = Vec::new();
+        for watch in self.watches.iter().filter(|w| w.matches(event)) {
+            if let Ok(mut timers) = watch.timers.lock() {
+                let now = Instant::now();
+                if timers.2 {
+                    restored.push((
+                        watch.condition_key(),
+                        watch.chain_name.clone(),
+                        format!("RESOLVED: Heartbeat restored, {} from {} seen again", watch.event_name, watch.label),
+                        watch.severity.clone(),
+                    ));
+                }
+                *timers = (Some(now), now, false);
+            }
+        }
+        restored
+    }
+
+    /// Returns one alert (ConditionKey, ChainName, Message, Severity) per watch
+    /// whose deadline has passed. A watch that stays silent re-fires once per
+    /// interval rather than on every check.
+    pub fn overdue(&self, now: Instant) -> Vec<(String, String, String, Severity)> {
+        let mut alerts = Vec::new();
+        for watch in &self.watches {
+            if let Ok(mut timers) = watch.timers.lock() {
+                let (last_seen, last_deadline, _) = *timers;
+                if now.saturating_duration_since(last_deadline) < watch.interval {
+                    continue;
+                }
+                timers.1 = now;
+                timers.2 = true;
+
+                let seen = match last_seen {
+                    Some(t) => format!("last seen {}s ago", now.saturating_duration_since(t).as_secs()),
+                    None => "never seen since startup".to_string(),
+                };
+                alerts.push((
+                    watch.condition_key(),
+                    watch.chain_name.clone(),
+                    format!(
+                        "Missing Heartbeat: no {} from {} within {}s ({})",
+                        watch.event_name, watch.label, watch.interval.as_secs(), seen
+                    ),
+                    watch.severity.clone(),
+                ));
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+
+    fn monitor(interval: Duration) -> (HeartbeatMonitor, NormalizedEvent) {
+        let event = NormalizedEvent::sample(EventType::Transfer, serde_json::json!({}));
+        let watch = HeartbeatWatch::new(
+            "USDT".into(), event.chain_name.clone(), event.contract_address, "Transfer".into(), interval, Severity::High,
+        );
+        (HeartbeatMonitor::new(vec![watch]), event)
+    }
+
+    #[test]
+    fn missed_interval_alerts_once_per_interval_then_resolves() {
+        let interval = Duration::from_secs(60);
+        let (monitor, event) = monitor(interval);
+        let start = Instant::now();
+
+        assert!(monitor.overdue(start).is_empty(), "not due before the first interval");
+        let alerts = monitor.overdue(start + interval);
+        assert_eq!(alerts.len(), 1);
+        let (key, chain, message, severity) = &alerts[0];
+        assert!(key.starts_with("heartbeat:ethereum:"));
+        assert_eq!(chain, "ethereum");
+        assert!(message.contains("Missing Heartbeat: no Transfer from USDT within 60s (never seen since startup)"), "{}", message);
+        assert_eq!(*severity, Severity::High);
+
+        // Still silent: no repeat until another full interval has passed
+        assert!(monitor.overdue(start + interval + Duration::from_secs(30)).is_empty());
+        assert_eq!(monitor.overdue(start + interval * 2).len(), 1);
+
+        let restored = monitor.observe(&event);
+        assert_eq!(restored.len(), 1);
+        assert!(restored[0].2.starts_with("RESOLVED: Heartbeat restored"));
+        assert!(monitor.observe(&event).is_empty(), "only the first event after an outage resolves it");
+    }
+
+    #[test]
+    fn other_events_do_not_reset_the_timer() {
+        let (monitor, mut event) = monitor(Duration::from_secs(60));
+        event.event_type = EventType::Approval;
+        monitor.observe(&event);
+        assert_eq!(monitor.overdue(Instant::now() + Duration::from_secs(60)).len(), 1);
+    }
+}
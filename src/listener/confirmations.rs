@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use eyre::Result;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::{error, info, warn};
+
+use crate::events::NormalizedEvent;
+use crate::state::AppState;
+use super::rpc_limit::RpcLimiter;
+
+/// Holds events until `confirmations` blocks have been built on top of theirs.
+#[derive(Debug)]
+pub struct ConfirmationBuffer {
+    confirmations: u64,
+    pending: BTreeMap<u64, Vec<NormalizedEvent>>, // BlockNumber -> events
+}
+
+impl ConfirmationBuffer {
+    pub fn new(confirmations: u64) -> Self {
+        Self { confirmations, pending: BTreeMap::new() }
+    }
+
+    /// Buffers an event. A reorg retraction (`removed`) instead cancels the
+    /// buffered event it refers to; if that was already released, the
+    /// retraction is handed back to be forwarded downstream.
+    pub fn push(&mut self, event: NormalizedEvent) -> Option<NormalizedEvent> {
+        if !event.removed {
+            self.pending.entry(event.block_number).or_default().push(event);
+            return None;
+        }
+        let Some(block) = self.pending.get_mut(&event.block_number) else {
+            return Some(event);
+        };
+        let matching = block.iter().position(|e| {
+            e.tx_hash == event.tx_hash
+                && e.contract_address == event.contract_address
+                && e.event_type.name() == event.event_type.name()
+        });
+        match matching {
+            Some(i) => {
+                block.remove(i);
+                if block.is_empty() {
+                    self.pending.remove(&event.block_number);
+                }
+                None
+            }
+            None => Some(event),
+        }
+    }
+
+    /// Removes and returns every event whose block has at least
+    /// `confirmations` blocks on top of it at `head`, oldest block first.
+    pub fn release(&mut self, head: u64) -> Vec<NormalizedEvent> {
+        let Some(last_ready) = head.checked_sub(self.confirmations) else {
+            return Vec::new();
+        };
+        let still_pending = self.pending.split_off(&(last_ready + 1));
+        std::mem::replace(&mut self.pending, still_pending)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Sits between a chain's listeners (`rx`) and the orchestrator (`tx`),
+/// releasing events once the chain head in `AppState::chain_heights` is
+/// `confirmations` blocks past them. Before release, the event's transaction
+/// receipt is re-fetched; if it is gone or now sits in a different block the
+/// log was reorged out and the event is dropped. Synthetic events (zero tx
+/// hash) skip that check.
+pub async fn confirm_events<T, P>(
+    provider: Arc<P>,
+    state: Arc<AppState>,
+    chain_name: String,
+    confirmations: u64,
+    mut rx: Receiver<NormalizedEvent>,
+    tx: Sender<NormalizedEvent>,
+) -> Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let rpc = state.rpc_limiter(&chain_name);
+    let mut buffer = ConfirmationBuffer::new(confirmations);
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => {
+                    if let Some(retraction) = buffer.push(event) {
+                        if let Err(e) = tx.send(retraction).await {
+                            error!("Failed to send event: {}", e);
+                            return Ok(());
+                        }
+                    }
+                }
+                None => {
+                    if !buffer.is_empty() {
+                        info!("[{}] Dropping {} unconfirmed event(s) on shutdown", chain_name, buffer.len());
+                    }
+                    return Ok(());
+                }
+            },
+            _ = ticker.tick() => {
+                let head = state.chain_heights.lock().ok().and_then(|h| h.get(&chain_name).copied());
+                let Some(head) = head else { continue };
+
+                for event in buffer.release(head) {
+                    if !event.tx_hash.is_zero() && !is_canonical(provider.as_ref(), &rpc, &event).await {
+                        warn!("[{}] Dropping reorged event {:?} in tx {}", chain_name, event.event_type, event.tx_hash);
+                        continue;
+                    }
+                    if let Err(e) = tx.send(event).await {
+                        error!("Failed to send event: {}", e);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// True when the event's transaction is still mined in the same block. An
+/// RPC failure is treated as canonical so a flaky node can't swallow alerts.
+async fn is_canonical<T, P>(provider: &P, rpc: &RpcLimiter, event: &NormalizedEvent) -> bool
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    match rpc.run(provider.get_transaction_receipt(event.tx_hash)).await {
+        Ok(Some(receipt)) => receipt.block_number == Some(event.block_number),
+        Ok(None) => false,
+        Err(e) => {
+            warn!("Receipt lookup for {} failed, releasing unverified: {}", event.tx_hash, e);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventType, Severity};
+    use crate::state::{AlertOrigin, AlertStatus};
+
+    fn event_in(block: u64) -> NormalizedEvent {
+        NormalizedEvent { block_number: block, ..NormalizedEvent::sample(EventType::Transfer, serde_json::json!({})) }
+    }
+
+    fn blocks(events: &[NormalizedEvent]) -> Vec<u64> {
+        events.iter().map(|e| e.block_number).collect()
+    }
+
+    #[test]
+    fn events_wait_for_their_confirmations() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        for block in [100, 101, 101] {
+            assert!(buffer.push(event_in(block)).is_none());
+        }
+
+        assert!(buffer.release(100).is_empty());
+        assert!(buffer.release(101).is_empty());
+        assert_eq!(blocks(&buffer.release(102)), [100]);
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(blocks(&buffer.release(103)), [101, 101]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn removed_logs_cancel_or_retract_their_event() {
+        let removed = NormalizedEvent { removed: true, ..event_in(100) };
+
+        // Still buffered: the event is dropped and nothing goes downstream
+        let mut buffer = ConfirmationBuffer::new(2);
+        buffer.push(event_in(100));
+        assert!(buffer.push(removed.clone()).is_none());
+        assert!(buffer.release(110).is_empty());
+
+        // Already released and alerted on: the retraction is forwarded and
+        // marks the alert retracted
+        let state = AppState::new();
+        buffer.push(event_in(100));
+        for event in buffer.release(102) {
+            state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), Some(AlertOrigin::from(&event)));
+        }
+        let retraction = buffer.push(removed).expect("retraction forwarded");
+        assert_eq!(state.retract_tx(retraction.tx_hash), [(Severity::High, "Large Transfer".to_string())]);
+        let history = state.alert_history.lock().unwrap();
+        assert_eq!(history[0].status, AlertStatus::Retracted);
+    }
+}
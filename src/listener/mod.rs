@@ -1,114 +1,1462 @@
-use alloy::providers::{Provider, ProviderBuilder, RootProvider, WsConnect};
+use alloy::providers::{Provider, ProviderBuilder, ReqwestProvider, RootProvider, WsConnect};
 use alloy::pubsub::PubSubFrontend;
 use alloy::rpc::types::{BlockNumberOrTag, Filter, Log};
-use alloy::sol_types::{SolEvent, SolInterface};
-use alloy::primitives::Address;
+use alloy::sol_types::SolEvent;
+use alloy::primitives::{keccak256, Address, B256};
+use alloy::transports::{Authorization, Transport};
 use eyre::Result;
 use futures_util::StreamExt;
 use url::Url;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::events::{NormalizedEvent, EventType, OwnershipTransferred, Transfer, Approval};
+use crate::config::GasSpikeConfig;
+use crate::events::abi::AbiEvents;
+use crate::events::{builtin_signatures, decode_swap, decode_transfer, event_data, raw_log_data, NormalizedEvent, EventType, Severity, OwnershipTransferred, Transfer, Approval};
+
+pub mod confirmations;
+pub mod rpc_batch;
+pub mod rpc_limit;
 
 pub type WsProvider = RootProvider<PubSubFrontend>;
+pub type HttpProvider = ReqwestProvider;
 
-pub async fn connect(rpc_url: &str) -> Result<WsProvider> {
-    let url = Url::parse(rpc_url)?;
-    let ws = WsConnect::new(url);
+/// Transport picked from the RPC URL scheme.
+pub enum ChainProvider {
+    Ws(Arc<WsProvider>),
+    Http(Arc<HttpProvider>),
+}
+
+/// Where and how to reach a chain's RPC node.
+#[derive(Debug, Clone)]
+pub struct RpcEndpoint {
+    pub url: String,
+    /// Sent as `Authorization` on the WebSocket upgrade (see `ws_auth`).
+    pub auth_header: Option<String>,
+}
+
+/// Interprets a configured auth header: `Bearer <token>` and
+/// `Basic <base64>` are used verbatim, `user:pass` becomes Basic auth, and
+/// anything else is treated as a bearer token.
+pub fn ws_auth(header: &str) -> Authorization {
+    let header = header.trim();
+    if let Some(token) = header.strip_prefix("Bearer ") {
+        Authorization::bearer(token.trim())
+    } else if let Some(encoded) = header.strip_prefix("Basic ") {
+        Authorization::Basic(encoded.trim().to_string())
+    } else if let Some((user, pass)) = header.split_once(':') {
+        Authorization::basic(user, pass)
+    } else {
+        Authorization::bearer(header)
+    }
+}
+
+pub async fn connect(endpoint: &RpcEndpoint) -> Result<WsProvider> {
+    let url = Url::parse(&endpoint.url)?;
+    let ws = WsConnect::with_auth(url, endpoint.auth_header.as_deref().map(ws_auth));
     let provider = ProviderBuilder::new().on_ws(ws).await?;
     Ok(provider)
 }
 
+pub fn connect_http(rpc_url: &str) -> Result<HttpProvider> {
+    let url = Url::parse(rpc_url)?;
+    Ok(ProviderBuilder::new().on_http(url))
+}
+
+/// `ws://`/`wss://` subscribe; `http://`/`https://` fall back to polling.
+pub async fn connect_auto(endpoint: &RpcEndpoint) -> Result<ChainProvider> {
+    let url = Url::parse(&endpoint.url)?;
+    match url.scheme() {
+        "ws" | "wss" => Ok(ChainProvider::Ws(Arc::new(connect(endpoint).await?))),
+        "http" | "https" => {
+            if endpoint.auth_header.is_some() {
+                warn!("auth_header is only sent on WebSocket endpoints; ignoring it for {}", url.host_str().unwrap_or_default());
+            }
+            Ok(ChainProvider::Http(Arc::new(connect_http(&endpoint.url)?)))
+        }
+        other => eyre::bail!("Unsupported RPC scheme: {}", other),
+    }
+}
+
 use crate::state::AppState;
+use crate::tokens::fetch_total_supply;
+use rpc_batch::RpcBatcher;
+use rpc_limit::RpcLimiter;
+
+/// Longest wait between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Reconnect behaviour for the resilient listeners.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: Option<u32>, // None = retry forever
+    pub base_delay: Duration,
+    /// Reconnect when the chain goes this long without a block while the
+    /// subscription looks alive (providers that stop pushing silently).
+    pub stall_timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: None, base_delay: Duration::from_secs(1), stall_timeout: None }
+    }
+}
+
+/// `base * 2^attempt`, capped at `MAX_BACKOFF` (1s, 2s, 4s, ... 60s).
+pub fn backoff_delay(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// Resolves once `chain_name` has gone `timeout` without a new block,
+/// counting from `since` at the earliest so a fresh subscription gets a full
+/// window before the previous silence counts against it.
+pub async fn stalled(state: &AppState, chain_name: &str, since: Instant, timeout: Duration) {
+    loop {
+        let last = state.last_block_time(chain_name).map_or(since, |t| t.max(since));
+        let deadline = last + timeout;
+        if Instant::now() >= deadline {
+            return;
+        }
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
+
+/// Runs `watch` against `provider`, getting a new one from `connect` with
+/// exponential backoff whenever the subscription errors, its stream ends, or
+/// (with `policy.stall_timeout`) `chain_name` stops producing blocks. Only
+/// returns once `policy.max_retries` consecutive reconnects have failed.
+async fn run_with_reconnect<P, C, CFut, F, Fut>(
+    mut provider: P,
+    mut connect: C,
+    state: &AppState,
+    chain_name: &str,
+    label: &str,
+    policy: RetryPolicy,
+    mut watch: F,
+) -> Result<()>
+where
+    P: Clone,
+    C: FnMut() -> CFut,
+    CFut: Future<Output = Result<P>>,
+    F: FnMut(P) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let session = async {
+            match policy.stall_timeout {
+                Some(timeout) => tokio::select! {
+                    result = watch(provider.clone()) => result,
+                    _ = stalled(state, chain_name, started, timeout) => {
+                        Err(eyre::eyre!("no new block for {:?}, assuming the subscription stalled", timeout))
+                    }
+                },
+                None => watch(provider.clone()).await,
+            }
+        };
+        match session.await {
+            Ok(()) => warn!("[{}] Subscription stream ended", label),
+            Err(e) => warn!("[{}] Subscription failed: {}", label, e),
+        }
+        // A session that stayed up for a while isn't part of a flapping streak
+        if started.elapsed() > MAX_BACKOFF {
+            attempt = 0;
+        }
+
+        loop {
+            if policy.max_retries.is_some_and(|max| attempt >= max) {
+                eyre::bail!("[{}] Giving up after {} reconnect attempts", label, attempt);
+            }
+            let delay = backoff_delay(policy.base_delay, attempt);
+            attempt += 1;
+            warn!("[{}] Reconnecting in {:?} (attempt {})", label, delay, attempt);
+            tokio::time::sleep(delay).await;
+
+            match connect().await {
+                Ok(p) => {
+                    state.record_reconnect();
+                    provider = p;
+                    break;
+                }
+                Err(e) => warn!("[{}] Reconnect failed: {}", label, e),
+            }
+        }
+    }
+}
+
+async fn reconnect(endpoint: &RpcEndpoint) -> Result<Arc<WsProvider>> {
+    connect(endpoint).await.map(Arc::new)
+}
+
+/// `watch_blocks` that survives disconnects (see `run_with_reconnect`).
+pub async fn watch_blocks_resilient(
+    provider: Arc<WsProvider>,
+    endpoint: RpcEndpoint,
+    state: Arc<AppState>,
+    chain_name: String,
+    gas_spike: Option<Arc<GasSpikeWatch>>,
+    policy: RetryPolicy,
+) -> Result<()> {
+    let label = format!("{} blocks", chain_name);
+    run_with_reconnect(provider, || reconnect(&endpoint), &state, &chain_name, &label, policy, |p| {
+        watch_blocks(p, state.clone(), chain_name.clone(), gas_spike.clone())
+    }).await
+}
+
+/// `watch_logs` that survives disconnects (see `run_with_reconnect`).
+pub async fn watch_logs_resilient(
+    provider: Arc<WsProvider>,
+    endpoint: RpcEndpoint,
+    state: Arc<AppState>,
+    watch: Arc<AddressWatch>,
+    tx: Sender<NormalizedEvent>,
+    policy: RetryPolicy,
+) -> Result<()> {
+    let label = format!("{} logs {}", watch.chain_name, watch.address);
+    run_with_reconnect(provider, || reconnect(&endpoint), &state, &watch.chain_name, &label, policy, |p| {
+        watch_logs(p, watch.clone(), tx.clone())
+    }).await
+}
+
+/// Upper bound on `eth_getCode` probes; a full binary search over any real
+/// chain height needs far fewer (~log2(head)).
+const MAX_CODE_PROBES: u32 = 64;
 
-pub async fn watch_blocks(provider: Arc<WsProvider>, state: Arc<AppState>, chain_name: String) -> Result<()> {
+/// Finds the first block at which `address` has code by binary-searching
+/// `eth_getCode` over `[0, head]`. Returns `None` when there is no code at
+/// `head` (never deployed, or self-destructed). Historical `eth_getCode`
+/// requires an archive node. Results are cached per chain in `AppState`.
+pub async fn find_deployment_block<T, P>(
+    provider: &P,
+    state: &AppState,
+    chain_name: &str,
+    address: Address,
+    head: u64,
+) -> Result<Option<u64>>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    if let Some(block) = state.cached_deployment_block(chain_name, address) {
+        return Ok(Some(block));
+    }
+
+    let rpc = state.rpc_limiter(chain_name);
+    if rpc.run(provider.get_code_at(address).number(head)).await?.is_empty() {
+        return Ok(None);
+    }
+
+    // Invariant: code exists at `high`; the deployment block is in [low, high]
+    let (mut low, mut high) = (0u64, head);
+    let mut probes = 0;
+    while low < high {
+        if probes >= MAX_CODE_PROBES {
+            eyre::bail!("Deployment search for {} exceeded {} probes", address, MAX_CODE_PROBES);
+        }
+        probes += 1;
+
+        let mid = low + (high - low) / 2;
+        if rpc.run(provider.get_code_at(address).number(mid)).await?.is_empty() {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    info!("[{}] {} deployed at block {}", chain_name, address, high);
+    state.cache_deployment_block(chain_name, address, high);
+    Ok(Some(high))
+}
+
+pub async fn watch_blocks(
+    provider: Arc<WsProvider>,
+    state: Arc<AppState>,
+    chain_name: String,
+    gas_spike: Option<Arc<GasSpikeWatch>>,
+) -> Result<()> {
     let sub = provider.subscribe_blocks().await?;
     let mut stream = sub.into_stream();
 
     while let Some(block) = stream.next().await {
-        let number = block.header.number.unwrap_or_default();
-        state.update_block(&chain_name, number);
+        // Some nodes (notably L2s) push headers without a number; that is not block 0
+        let Some(number) = block.header.number else {
+            warn!("[{}] Block header without a number, height not updated", chain_name);
+            continue;
+        };
+        state.update_block(&chain_name, number, Some(block.header.timestamp));
         info!("[{}] New Block: {:?}", chain_name, number);
+        if let Some(gas_spike) = &gas_spike {
+            gas_spike.observe(&state, number, block.header.base_fee_per_gas).await;
+        }
     }
 
     Ok(())
 }
 
+/// Base-fee spike detection for a chain's block listener.
+#[derive(Debug, Clone)]
+pub struct GasSpikeWatch {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub config: GasSpikeConfig,
+    pub severity: Severity,
+    pub tx: Sender<NormalizedEvent>,
+}
+
+impl GasSpikeWatch {
+    /// Adds the block's base fee to the trailing average in `AppState` and
+    /// emits a synthetic `GasSpike` event when it exceeds `multiplier` times
+    /// that average. Pre-EIP-1559 blocks (no base fee) are skipped.
+    async fn observe(&self, state: &AppState, block: u64, base_fee: Option<u128>) {
+        let Some(base_fee) = base_fee else { return };
+        let Some(average) = state.record_base_fee(&self.chain_name, base_fee, self.config.window_blocks) else {
+            return;
+        };
+        if !is_spike(base_fee, average, self.config.multiplier) {
+            return;
+        }
+        warn!("[{}] Base fee spike at block {}: {} wei vs {} wei average", self.chain_name, block, base_fee, average);
+
+        let event = NormalizedEvent {
+            chain_id: self.chain_id,
+            chain_name: self.chain_name.clone(),
+            contract_address: Address::ZERO,
+            contract_name: String::new(),
+            tx_hash: Default::default(),
+            block_number: block,
+            event_type: EventType::GasSpike,
+            severity: self.severity.clone(),
+            data: serde_json::json!({
+                "base_fee": base_fee.to_string(),
+                "average_base_fee": average.to_string(),
+                "window_blocks": self.config.window_blocks,
+            }),
+            removed: false,
+            decimals: None,
+            historical: false,
+            span: NormalizedEvent::new_span(self.chain_id, B256::ZERO),
+        };
+        event.span.record("event_type", event.event_type.name());
+        if let Err(e) = self.tx.send(event).await {
+            error!("Failed to send event to channel: {}", e);
+        }
+    }
+}
+
+/// True when `base_fee` is more than `multiplier` times a non-zero `average`.
+pub fn is_spike(base_fee: u128, average: u128, multiplier: f64) -> bool {
+    average > 0 && base_fee as f64 > average as f64 * multiplier
+}
+
 pub async fn watch_logs(
     provider: Arc<WsProvider>,
-    address: Address,
-    chain_id: u64,
-    chain_name: String,
+    watch: Arc<AddressWatch>,
     tx: Sender<NormalizedEvent>,
 ) -> Result<()> {
-    let filter = Filter::new()
-        .address(address)
-        .from_block(BlockNumberOrTag::Latest);
+    let filter = watch.filter().from_block(BlockNumberOrTag::Latest);
 
     let sub = provider.subscribe_logs(&filter).await?;
     let mut stream = sub.into_stream();
 
-     while let Some(log) = stream.next().await {
-         let signature = log.topics().first().copied();
-
-         if let Some(sig) = signature {
-              if sig == OwnershipTransferred::SIGNATURE_HASH {
-                  if let Ok(decoded) = OwnershipTransferred::decode_log(&log.inner, true) {
-                      info!("Detected OwnershipTransferred: {:?}", decoded);
-                      let event = NormalizedEvent {
-                          chain_id,
-                          chain_name: chain_name.clone(),
-                          contract_address: log.address(),
-                          tx_hash: log.transaction_hash.unwrap_or_default(),
-                          block_number: log.block_number.unwrap_or_default(),
-                          event_type: EventType::OwnershipTransferred,
-                          severity: crate::events::Severity::Low, // Default, upgraded by rules
-                          data: serde_json::to_value(&decoded).unwrap_or_default(),
-                      };
-                      if let Err(e) = tx.send(event).await {
-                          error!("Failed to send event: {}", e);
-                      }
-                  }
-              } else if sig == Transfer::SIGNATURE_HASH {
-                  if let Ok(decoded) = Transfer::decode_log(&log.inner, true) {
-                      info!("Detected Transfer: {:?}", decoded);
-                      let event = NormalizedEvent {
-                          chain_id,
-                          chain_name: chain_name.clone(),
-                          contract_address: log.address(),
-                          tx_hash: log.transaction_hash.unwrap_or_default(),
-                          block_number: log.block_number.unwrap_or_default(),
-                          event_type: EventType::Transfer,
-                          severity: crate::events::Severity::Low,
-                          data: serde_json::to_value(&decoded).unwrap_or_default(),
-                      };
-                      if let Err(e) = tx.send(event).await {
-                          error!("Failed to send event: {}", e);
-                      }
-                  }
-              } else if sig == Approval::SIGNATURE_HASH {
-                  if let Ok(decoded) = Approval::decode_log(&log.inner, true) {
-                      let event = NormalizedEvent {
-                          chain_id,
-                          chain_name: chain_name.clone(),
-                          contract_address: log.address(),
-                          tx_hash: log.transaction_hash.unwrap_or_default(),
-                          block_number: log.block_number.unwrap_or_default(),
-                          event_type: EventType::Approval,
-                          severity: crate::events::Severity::Low,
-                          data: serde_json::to_value(&decoded).unwrap_or_default(),
-                      };
-                      if let Err(e) = tx.send(event).await {
-                          error!("Failed to send event: {}", e);
-                      }
-                  }
-              } else {
-                  tracing::debug!("Unknown event signature: {:?}", sig);
-              }
-         }
-     }
+    while let Some(log) = stream.next().await {
+        for event in watch.normalize(&log) {
+            if let Err(e) = tx.send(event).await {
+                error!("Failed to send event: {}", e);
+            }
+        }
+    }
 
     Ok(())
 }
+
+/// HTTP-only counterpart of `watch_blocks`: polls the latest block header,
+/// which carries the number, the timestamp and the base fee in one request.
+pub async fn watch_blocks_polling(
+    provider: Arc<HttpProvider>,
+    state: Arc<AppState>,
+    chain_name: String,
+    gas_spike: Option<Arc<GasSpikeWatch>>,
+    poll_interval: Duration,
+) -> Result<()> {
+    let rpc = state.rpc_limiter(&chain_name);
+    let mut ticker = tokio::time::interval(poll_interval);
+    let mut last = None;
+    loop {
+        ticker.tick().await;
+        let header = match rpc.run(provider.get_block_by_number(BlockNumberOrTag::Latest, false)).await {
+            Ok(Some(block)) => block.header,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("[{}] Block poll failed: {}", chain_name, e);
+                continue;
+            }
+        };
+        let Some(number) = header.number else {
+            warn!("[{}] Latest block has no number, height not updated", chain_name);
+            continue;
+        };
+        if last == Some(number) {
+            continue;
+        }
+        last = Some(number);
+        state.update_block(&chain_name, number, Some(header.timestamp));
+        info!("[{}] New Block: {:?}", chain_name, number);
+        // Only the newest block per poll is sampled
+        if let Some(gas_spike) = &gas_spike {
+            gas_spike.observe(&state, number, header.base_fee_per_gas).await;
+        }
+    }
+}
+
+/// Polls the contract's bytecode every `interval` and emits a synthetic
+/// `CodeChanged` event when its hash differs from the last poll (proxy
+/// upgrade, self-destruct). The first poll only records a baseline. Reads go
+/// through the chain's `batcher`; a failed poll is retried on the next tick.
+pub async fn watch_code(
+    batcher: RpcBatcher,
+    state: Arc<AppState>,
+    watch: Arc<AddressWatch>,
+    tx: Sender<NormalizedEvent>,
+    interval: Duration,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let head = match batcher.block_number().await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!("[{}] Code poll for {} failed: {}", watch.chain_name, watch.address, e);
+                continue;
+            }
+        };
+        let code = match batcher.get_code(watch.address, head).await {
+            Ok(code) => code,
+            Err(e) => {
+                warn!("[{}] Code poll for {} failed: {}", watch.chain_name, watch.address, e);
+                continue;
+            }
+        };
+
+        let hash = keccak256(&code);
+        let Some(previous) = state.update_code_hash(&watch.chain_name, watch.address, hash) else {
+            continue;
+        };
+        warn!("[{}] Bytecode of {} changed at block {}", watch.chain_name, watch.address, head);
+
+        let event_type = EventType::CodeChanged;
+        // A bytecode swap is never routine; default to High unless configured
+        let severity = watch.contracts.iter()
+            .filter_map(|c| c.base_severity.get(event_type.name()).cloned())
+            .max()
+            .unwrap_or(Severity::High);
+        let event = NormalizedEvent {
+            chain_id: watch.chain_id,
+            chain_name: watch.chain_name.clone(),
+            contract_address: watch.address,
+            contract_name: watch.contract_name(),
+            tx_hash: Default::default(),
+            block_number: head,
+            event_type,
+            severity,
+            data: serde_json::json!({
+                "previous_code_hash": previous.to_string(),
+                "code_hash": hash.to_string(),
+                "code_size": code.len(),
+                "destroyed": code.is_empty(),
+            }),
+            removed: false,
+            decimals: None,
+            historical: false,
+            span: NormalizedEvent::new_span(watch.chain_id, B256::ZERO),
+        };
+        event.span.record("event_type", event.event_type.name());
+        if let Err(e) = tx.send(event).await {
+            error!("Failed to send event to channel: {}", e);
+            return Ok(());
+        }
+    }
+}
+
+/// Polls the token's `totalSupply()` every `interval` and emits a synthetic
+/// `SupplyChanged` event whenever it differs from the last poll; the first
+/// reading only records a baseline, and `SupplyChangeRule` decides whether a
+/// change is worth an alert. Non-ERC-20 contracts never produce one. Reads
+/// go through the chain's `batcher`.
+pub async fn watch_supply(
+    batcher: RpcBatcher,
+    state: Arc<AppState>,
+    watch: Arc<AddressWatch>,
+    tx: Sender<NormalizedEvent>,
+    interval: Duration,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let head = match batcher.block_number().await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!("[{}] Supply poll for {} failed: {}", watch.chain_name, watch.address, e);
+                continue;
+            }
+        };
+        let Some(supply) = fetch_total_supply(&batcher, watch.address).await else {
+            tracing::debug!("[{}] No totalSupply() from {}", watch.chain_name, watch.address);
+            continue;
+        };
+        let Some(previous) = state.update_total_supply(&watch.chain_name, watch.address, supply) else {
+            continue;
+        };
+
+        let event_type = EventType::SupplyChanged;
+        let severity = watch.contracts.iter()
+            .filter_map(|c| c.base_severity.get(event_type.name()).cloned())
+            .max()
+            .unwrap_or(Severity::Low);
+        let event = NormalizedEvent {
+            chain_id: watch.chain_id,
+            chain_name: watch.chain_name.clone(),
+            contract_address: watch.address,
+            contract_name: watch.contract_name(),
+            tx_hash: Default::default(),
+            block_number: head,
+            event_type,
+            severity,
+            data: serde_json::json!({
+                "previous_supply": previous.to_string(),
+                "total_supply": supply.to_string(),
+            }),
+            removed: false,
+            decimals: watch.contracts.iter().find_map(|c| c.decimals),
+            historical: false,
+            span: NormalizedEvent::new_span(watch.chain_id, B256::ZERO),
+        };
+        event.span.record("event_type", event.event_type.name());
+        if let Err(e) = tx.send(event).await {
+            error!("Failed to send event to channel: {}", e);
+            return Ok(());
+        }
+    }
+}
+
+/// Replays the last `backfill_blocks` blocks of logs for `watch` (never
+/// earlier than the contract's deployment) through the normal decode path,
+/// marking each event `historical`. Requests are paged by `max_block_range`.
+/// Returns the head it backfilled up to, so live polling can resume right after.
+pub async fn backfill_logs<T, P>(
+    provider: &P,
+    state: &AppState,
+    watch: &AddressWatch,
+    tx: &Sender<NormalizedEvent>,
+    backfill_blocks: u64,
+    max_block_range: u64,
+) -> Result<u64>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let rpc = state.rpc_limiter(&watch.chain_name);
+    let head = rpc.run(provider.get_block_number()).await?;
+    let mut from = head.saturating_sub(backfill_blocks);
+
+    // Only search for the deployment when the window might predate it
+    let has_code_at_start = rpc.run(provider.get_code_at(watch.address).number(from)).await
+        .map(|code| !code.is_empty())
+        .unwrap_or(true); // Pruned node: can't tell, backfill the whole window
+    if !has_code_at_start {
+        match find_deployment_block(provider, state, &watch.chain_name, watch.address, head).await {
+            Ok(Some(deployed)) => from = from.max(deployed),
+            Ok(None) => {
+                info!("[{}] {} has no code, skipping backfill", watch.chain_name, watch.address);
+                return Ok(head);
+            }
+            Err(e) => warn!("[{}] Deployment search for {} failed: {}", watch.chain_name, watch.address, e),
+        }
+    }
+
+    info!("[{}] Backfilling {} from block {} to {}", watch.chain_name, watch.address, from, head);
+    let max_block_range = max_block_range.max(1);
+    let mut emitted = 0;
+    let mut next_block = from;
+    while next_block <= head {
+        let to_block = (next_block + max_block_range - 1).min(head);
+        let filter = watch.filter()
+            .from_block(next_block)
+            .to_block(to_block);
+        for log in rpc.run(provider.get_logs(&filter)).await? {
+            for mut event in watch.normalize(&log) {
+                event.historical = true;
+                emitted += 1;
+                tx.send(event).await?;
+            }
+        }
+        next_block = to_block + 1;
+    }
+    info!("[{}] Backfill of {} done: {} event(s)", watch.chain_name, watch.address, emitted);
+    Ok(head)
+}
+
+/// HTTP-only counterpart of `watch_logs`: polls `eth_getLogs` every
+/// `poll_interval`, starting at the current head. Tracks the last processed
+/// block so nothing is emitted twice, and splits catch-up into chunks of at
+/// most `max_block_range` blocks for rate-limited endpoints. A failed request
+/// is retried from the same block on the next tick. `after_block` resumes
+/// right after an earlier backfill instead of at the current head. Every
+/// request goes through the chain's `rpc` limiter.
+pub async fn watch_logs_polling(
+    provider: Arc<HttpProvider>,
+    rpc: RpcLimiter,
+    watch: Arc<AddressWatch>,
+    tx: Sender<NormalizedEvent>,
+    poll_interval: Duration,
+    max_block_range: u64,
+    after_block: Option<u64>,
+) -> Result<()> {
+    let mut next_block = match after_block {
+        Some(block) => block + 1,
+        None => rpc.run(provider.get_block_number()).await? + 1,
+    };
+    let max_block_range = max_block_range.max(1);
+    let mut ticker = tokio::time::interval(poll_interval);
+
+    loop {
+        ticker.tick().await;
+        let head = match rpc.run(provider.get_block_number()).await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!("[{}] Head poll failed: {}", watch.chain_name, e);
+                continue;
+            }
+        };
+
+        while next_block <= head {
+            let to_block = (next_block + max_block_range - 1).min(head);
+            let filter = watch.filter()
+                .from_block(next_block)
+                .to_block(to_block);
+
+            match rpc.run(provider.get_logs(&filter)).await {
+                Ok(logs) => {
+                    for log in &logs {
+                        for event in watch.normalize(log) {
+                            if let Err(e) = tx.send(event).await {
+                                error!("Failed to send event: {}", e);
+                            }
+                        }
+                    }
+                    next_block = to_block + 1;
+                }
+                Err(e) => {
+                    warn!("[{}] Log poll {}..={} failed: {}", watch.chain_name, next_block, to_block, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Per-contract decoding settings (one `[[contracts]]` entry).
+#[derive(Debug, Clone)]
+pub struct ContractWatch {
+    /// The `[[contracts]]` name.
+    pub name: String,
+    pub address: Address,
+    pub chain_id: u64,
+    pub chain_name: String,
+    /// Starting severity per event name; unlisted events start at Low.
+    pub base_severity: HashMap<String, Severity>,
+    /// When non-empty, Transfer/Approval logs are only forwarded if one of
+    /// their indexed parties (from/to, owner/spender) is in this list.
+    pub watch_addresses: Vec<Address>,
+    /// Decoders for events outside the built-in three.
+    pub abi: AbiEvents,
+    /// Emit undecodable logs as `EventType::Unknown(topic0)` instead of dropping them.
+    pub forward_unknown: bool,
+    /// Token decimals for human-readable amounts in alerts.
+    pub decimals: Option<u8>,
+    /// Event names from the config; the RPC only streams these.
+    pub events: Vec<String>,
+}
+
+impl ContractWatch {
+    /// topic0 values for `events`, or None to receive every log: when the
+    /// list is empty, raw logs are forwarded (`forward_unknown`), or a name
+    /// matches neither a built-in event nor the ABI.
+    pub fn topic0s(&self) -> Option<Vec<B256>> {
+        if self.events.is_empty() || self.forward_unknown {
+            return None;
+        }
+        let mut topics = Vec::new();
+        for name in &self.events {
+            let mut found = builtin_signatures(name);
+            found.extend(self.abi.selectors(name));
+            if found.is_empty() {
+                return None;
+            }
+            topics.extend(found);
+        }
+        Some(topics)
+    }
+
+    /// Configured event names with no known signature; any of them turns
+    /// the topic filter off for the address.
+    pub fn unknown_events(&self) -> Vec<&str> {
+        self.events.iter()
+            .filter(|name| builtin_signatures(name).is_empty() && self.abi.selectors(name).is_empty())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Checks the indexed parties of Transfer/Approval logs against
+    /// `watch_addresses`. Other events always pass.
+    pub fn accepts(&self, log: &Log) -> bool {
+        if self.watch_addresses.is_empty() {
+            return true;
+        }
+        let topics = log.topics();
+        match topics.first() {
+            Some(sig) if *sig == Transfer::SIGNATURE_HASH || *sig == Approval::SIGNATURE_HASH => {
+                // topics[1], topics[2] are the left-padded indexed addresses
+                topics.iter()
+                    .skip(1)
+                    .take(2)
+                    .any(|t| self.watch_addresses.contains(&Address::from_word(*t)))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Every contract config sharing one address on one chain (e.g. a proxy and
+/// its implementation ABI). Listeners subscribe once per `AddressWatch` and
+/// decode each log against every config in it.
+#[derive(Debug)]
+pub struct AddressWatch {
+    pub address: Address,
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub contracts: Vec<ContractWatch>,
+}
+
+impl AddressWatch {
+    /// Groups `contracts` by address, keeping first-seen order. All entries
+    /// must belong to the same chain.
+    pub fn group(contracts: Vec<ContractWatch>) -> Vec<AddressWatch> {
+        let mut groups: Vec<AddressWatch> = Vec::new();
+        for contract in contracts {
+            match groups.iter_mut().find(|g| g.address == contract.address) {
+                Some(group) => group.contracts.push(contract),
+                None => groups.push(AddressWatch {
+                    address: contract.address,
+                    chain_id: contract.chain_id,
+                    chain_name: contract.chain_name.clone(),
+                    contracts: vec![contract],
+                }),
+            }
+        }
+        groups
+    }
+
+    /// Log filter for the address, narrowed to the configured events' topic0
+    /// (the union across configs) unless one of them needs every log.
+    pub fn filter(&self) -> Filter {
+        let filter = Filter::new().address(self.address);
+        let mut topics: Vec<B256> = Vec::new();
+        for contract in &self.contracts {
+            match contract.topic0s() {
+                Some(t) => topics.extend(t),
+                None => return filter,
+            }
+        }
+        topics.sort();
+        topics.dedup();
+        filter.event_signature(topics)
+    }
+
+    /// The configured names sharing this address, e.g. "USDC/USDC-large".
+    pub fn contract_name(&self) -> String {
+        self.contracts.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join("/")
+    }
+
+    /// One event per contract config that decodes the log.
+    pub fn normalize(&self, log: &Log) -> Vec<NormalizedEvent> {
+        self.contracts.iter().filter_map(|c| normalize_log(log, c)).collect()
+    }
+}
+
+/// Decodes a raw log into a `NormalizedEvent`. The built-in events use their
+/// typed decoders; anything else is tried against the contract's ABI, with
+/// the event name carried in `EventType::Unknown`. A signature matching neither
+/// yields `None`, unless the contract sets `forward_unknown`, in which case it
+/// comes back as `Unknown(<topic0>)` with the raw topics and data. Logs
+/// rejected by the contract's watch list also yield `None`. The starting severity
+/// comes from the contract's `base_severity` table (keyed by event name),
+/// defaulting to Low; rules can escalate from there.
+pub fn normalize_log(log: &Log, watch: &ContractWatch) -> Option<NormalizedEvent> {
+    let sig = log.topics().first().copied()?;
+    if !watch.accepts(log) {
+        tracing::debug!("Log outside watch list skipped: {:?}", log.transaction_hash);
+        return None;
+    }
+
+    let tx_hash = log.transaction_hash.unwrap_or_default();
+    let span = NormalizedEvent::new_span(watch.chain_id, tx_hash);
+    let _decode = tracing::info_span!(parent: &span, "decode").entered();
+
+    let (event_type, data) = if sig == OwnershipTransferred::SIGNATURE_HASH {
+        let decoded = OwnershipTransferred::decode_log(&log.inner, true).ok()?;
+        info!("Detected OwnershipTransferred: {:?}", decoded);
+        (EventType::OwnershipTransferred, event_data(&decoded, log))
+    } else if sig == Transfer::SIGNATURE_HASH {
+        let (event_type, data) = decode_transfer(log)?;
+        info!("Detected {}: {}", event_type.name(), data);
+        (event_type, data)
+    } else if sig == Approval::SIGNATURE_HASH {
+        let decoded = Approval::decode_log(&log.inner, true).ok()?;
+        (EventType::Approval, event_data(&decoded, log))
+    } else if let Some(data) = decode_swap(log.data()) {
+        (EventType::Swap, data)
+    } else if let Some((name, data)) = watch.abi.decode(log.data()) {
+        info!("Detected {} (ABI): {}", name, data);
+        (EventType::Unknown(name), data)
+    } else if watch.forward_unknown {
+        // Raw log for generic rules; keyed (and base-severity'd) by topic0
+        (EventType::Unknown(sig.to_string()), raw_log_data(log))
+    } else {
+        tracing::debug!("Unknown event signature: {:?}", sig);
+        return None;
+    };
+
+    let severity = watch.base_severity.get(event_type.name()).cloned().unwrap_or(Severity::Low);
+    span.record("event_type", event_type.name());
+
+    Some(NormalizedEvent {
+        chain_id: watch.chain_id,
+        chain_name: watch.chain_name.clone(),
+        contract_address: log.address(),
+        contract_name: watch.name.clone(),
+        tx_hash,
+        block_number: log.block_number.unwrap_or_default(),
+        event_type,
+        severity,
+        data,
+        removed: log.removed,
+        decimals: watch.decimals,
+        historical: false,
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, LogData, U256};
+
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+    const OWNER: Address = address!("28C6c06298d514Db089934071355E5743bf21d60");
+    const SPENDER: Address = address!("000000000000000000000000000000000000dEaD");
+
+    /// The `[[contracts]]` entry for USDT on ethereum, no options set.
+    fn watch() -> ContractWatch {
+        ContractWatch {
+            name: "USDT".to_string(),
+            address: USDT,
+            chain_id: 1,
+            chain_name: "ethereum".to_string(),
+            base_severity: HashMap::new(),
+            watch_addresses: Vec::new(),
+            abi: AbiEvents::default(),
+            forward_unknown: false,
+            decimals: Some(6),
+            events: Vec::new(),
+        }
+    }
+
+    /// A mined log emitted by `address` in block 100.
+    fn rpc_log(address: Address, data: LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log { address, data },
+            transaction_hash: Some(B256::repeat_byte(7)),
+            block_number: Some(100),
+            ..Default::default()
+        }
+    }
+
+    fn approval(value: U256) -> Log {
+        rpc_log(USDT, Approval { owner: OWNER, spender: SPENDER, value }.encode_log_data())
+    }
+
+    fn transfer(from: Address, to: Address, value: U256) -> Log {
+        rpc_log(USDT, Transfer { from, to, value }.encode_log_data())
+    }
+
+    #[test]
+    fn auth_headers_accept_bearer_and_basic_forms() {
+        let header = |configured: &str| ws_auth(configured).to_string();
+        assert_eq!(header("Bearer abc123"), "Bearer abc123");
+        assert_eq!(header("abc123"), "Bearer abc123");
+        assert_eq!(header("alice:secret"), "Basic YWxpY2U6c2VjcmV0");
+        assert_eq!(header("Basic YWxpY2U6c2VjcmV0"), "Basic YWxpY2U6c2VjcmV0");
+    }
+
+    #[tokio::test]
+    async fn websocket_upgrade_carries_the_auth_header() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = RpcEndpoint {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            auth_header: Some("alice:secret".to_string()),
+        };
+        let connecting = tokio::spawn(async move { connect(&endpoint).await.map(|_| ()) });
+
+        // Read the upgrade request, then hang up without answering it
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed mid-request");
+            request.extend_from_slice(&buf[..n]);
+        }
+        drop(socket);
+        let request = String::from_utf8(request).unwrap();
+        let auth = request.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("authorization").then(|| value.trim().to_string())
+        });
+        assert_eq!(auth.as_deref(), Some("Basic YWxpY2U6c2VjcmV0"), "{}", request);
+        assert!(connecting.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn base_fee_spikes_fire_against_the_trailing_average() {
+        assert!(is_spike(21, 10, 2.0));
+        assert!(!is_spike(20, 10, 2.0), "exactly the multiple is not a spike");
+        assert!(!is_spike(1_000, 0, 2.0));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let config = GasSpikeConfig { multiplier: 2.0, window_blocks: 3, severity: "High".into() };
+        let watch = GasSpikeWatch { chain_id: 1, chain_name: "ethereum".into(), config, severity: Severity::High, tx };
+        let state = AppState::new();
+        // Window fills; 25 > 2 x 10; 12 vs 15; pre-London block; 40 > 2 x 15
+        let fees = [Some(10), Some(10), Some(10), Some(25), Some(12), None, Some(40)];
+        for (block, fee) in (100..).zip(fees) {
+            watch.observe(&state, block, fee).await;
+        }
+        drop(watch);
+
+        let mut spikes = Vec::new();
+        while let Some(event) = rx.recv().await {
+            assert!(matches!(event.event_type, EventType::GasSpike));
+            spikes.push((event.block_number, event.data["base_fee"].clone(), event.data["average_base_fee"].clone()));
+        }
+        assert_eq!(spikes, [
+            (103, serde_json::json!("25"), serde_json::json!("10")),
+            (106, serde_json::json!("40"), serde_json::json!("15")),
+        ]);
+    }
+
+    #[test]
+    fn base_severity_applies_per_event_name() {
+        let mut watch = watch();
+        watch.base_severity.insert("Approval".to_string(), Severity::High);
+
+        let event = normalize_log(&approval(U256::from(5)), &watch).unwrap();
+        assert!(matches!(event.event_type, EventType::Approval));
+        assert_eq!(event.severity, Severity::High);
+        let event = normalize_log(&transfer(OWNER, SPENDER, U256::from(5)), &watch).unwrap();
+        assert_eq!(event.severity, Severity::Low, "unlisted events start at Low");
+    }
+
+    fn quick_retries(max_retries: Option<u32>) -> RetryPolicy {
+        RetryPolicy { max_retries, base_delay: Duration::from_millis(1), stall_timeout: None }
+    }
+
+    #[tokio::test]
+    async fn dropped_streams_reconnect_until_one_holds() {
+        let state = AppState::new();
+        let sessions = std::sync::Mutex::new(Vec::new());
+        let mut next_provider = 0;
+        let connect = || {
+            next_provider += 1;
+            std::future::ready(Ok(next_provider))
+        };
+        // Providers 0 and 1 drop their stream; provider 2 stays subscribed
+        let watch = |provider: u32| {
+            sessions.lock().unwrap().push(provider);
+            async move {
+                if provider < 2 {
+                    eyre::bail!("stream {} dropped", provider);
+                }
+                std::future::pending().await
+            }
+        };
+
+        let run = run_with_reconnect(0, connect, &state, "ethereum", "test", quick_retries(Some(5)), watch);
+        assert!(tokio::time::timeout(Duration::from_millis(500), run).await.is_err(), "still subscribed");
+        assert_eq!(*sessions.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(state.reconnects.load(std::sync::atomic::Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_gives_up_after_max_retries() {
+        let state = AppState::new();
+        let mut attempts = 0;
+        let connect = || {
+            attempts += 1;
+            std::future::ready(Err::<u32, _>(eyre::eyre!("connection refused")))
+        };
+        let watch = |_| async { eyre::bail!("stream dropped") };
+
+        let error = run_with_reconnect(0, connect, &state, "ethereum", "test", quick_retries(Some(3)), watch).await.unwrap_err();
+        assert!(error.to_string().contains("Giving up after 3 reconnect attempts"), "{}", error);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn watch_list_matches_indexed_from_to_owner_and_spender() {
+        let stranger = address!("1111111111111111111111111111111111111111");
+        let watch = ContractWatch { watch_addresses: vec![SPENDER], ..watch() };
+
+        assert!(normalize_log(&transfer(OWNER, SPENDER, U256::from(5)), &watch).is_some(), "to matches");
+        assert!(normalize_log(&transfer(SPENDER, OWNER, U256::from(5)), &watch).is_some(), "from matches");
+        assert!(normalize_log(&approval(U256::from(5)), &watch).is_some(), "spender matches");
+        assert!(normalize_log(&transfer(OWNER, stranger, U256::from(5)), &watch).is_none());
+
+        let watch = ContractWatch { watch_addresses: vec![stranger], ..watch };
+        assert!(normalize_log(&approval(U256::from(5)), &watch).is_none());
+        let ownership = rpc_log(USDT, OwnershipTransferred { previousOwner: OWNER, newOwner: SPENDER }.encode_log_data());
+        assert!(normalize_log(&ownership, &watch).is_some(), "other events always pass");
+    }
+
+    #[test]
+    fn abi_events_decode_by_name() {
+        let abi = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(abi.path(), r#"[
+            {"type":"event","name":"Deposit","anonymous":false,"inputs":[
+                {"name":"dst","type":"address","indexed":true},
+                {"name":"wad","type":"uint256","indexed":false}]},
+            {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+                {"name":"src","type":"address","indexed":true},
+                {"name":"dst","type":"address","indexed":true},
+                {"name":"wad","type":"uint256","indexed":false}]}]"#).unwrap();
+        let mut watch = ContractWatch { abi: AbiEvents::load(abi.path()).unwrap(), ..watch() };
+        watch.base_severity.insert("Deposit".to_string(), Severity::Medium);
+
+        let deposit = LogData::new_unchecked(
+            vec![alloy::primitives::keccak256("Deposit(address,uint256)"), OWNER.into_word()],
+            U256::from(3_000_000).to_be_bytes_vec().into(),
+        );
+        let event = normalize_log(&rpc_log(USDT, deposit), &watch).unwrap();
+        assert_eq!(event.event_type.name(), "Deposit");
+        assert_eq!(event.data, serde_json::json!({ "dst": OWNER.to_string(), "wad": "3000000" }));
+        assert_eq!(event.severity, Severity::Medium);
+
+        // Built-in decoders come first, whatever the ABI calls the fields
+        let event = normalize_log(&transfer(OWNER, SPENDER, U256::from(5)), &watch).unwrap();
+        assert!(matches!(event.event_type, EventType::Transfer));
+        assert_eq!(event.data["from"].as_str().unwrap().parse::<Address>().unwrap(), OWNER);
+    }
+
+    #[test]
+    fn unrecognized_logs_are_forwarded_only_when_enabled() {
+        let topic0 = B256::repeat_byte(0xab);
+        let log = rpc_log(USDT, LogData::new_unchecked(vec![topic0, OWNER.into_word()], vec![0x2a].into()));
+        assert!(normalize_log(&log, &watch()).is_none());
+
+        let mut watch = ContractWatch { forward_unknown: true, ..watch() };
+        watch.base_severity.insert(topic0.to_string(), Severity::Medium);
+        let event = normalize_log(&log, &watch).unwrap();
+        assert!(matches!(&event.event_type, EventType::Unknown(sig) if *sig == topic0.to_string()));
+        assert_eq!(event.severity, Severity::Medium, "base severity keyed by topic0");
+        assert_eq!(event.data["topics"], serde_json::json!([topic0.to_string(), OWNER.into_word().to_string()]));
+        assert_eq!(event.data["data"], "0x2a");
+
+        // Nothing downstream trips over it
+        let state = AppState::new();
+        let mut engine = crate::rules::RuleEngine::new();
+        engine.add_rule(Box::new(crate::rules::ThresholdRule::new(crate::rules::Threshold::Raw(U256::from(1)), Severity::High)));
+        assert_eq!(engine.process(&event, &state).len(), 1, "only the raised base severity alerts");
+    }
+
+    #[test]
+    fn shared_addresses_are_subscribed_once() {
+        let proxy = ContractWatch { name: "USDT-proxy".to_string(), ..watch() };
+        let other = ContractWatch { name: "DEAD".to_string(), address: SPENDER, ..watch() };
+        let groups = AddressWatch::group(vec![watch(), other, proxy]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].contract_name(), "USDT/USDT-proxy");
+        let filter = serde_json::to_value(groups[0].filter()).unwrap();
+        assert_eq!(filter["address"].as_str().unwrap().parse::<Address>().unwrap(), USDT);
+
+        // One log from the shared subscription reaches both configs
+        let events = groups[0].normalize(&transfer(OWNER, SPENDER, U256::from(5)));
+        let names: Vec<_> = events.iter().map(|e| e.contract_name.as_str()).collect();
+        assert_eq!(names, ["USDT", "USDT-proxy"]);
+    }
+
+    #[test]
+    fn topic_filters_follow_the_events_list() {
+        use alloy::primitives::keccak256;
+        // The filter keeps topics as a set, so compare them sorted
+        let topics = |group: &AddressWatch| {
+            let filter = serde_json::to_value(group.filter()).unwrap();
+            let mut topics: Vec<B256> = serde_json::from_value(filter["topics"][0].clone()).ok()?;
+            topics.sort();
+            Some(topics)
+        };
+        let transfer = keccak256("Transfer(address,address,uint256)");
+        let approval = keccak256("Approval(address,address,uint256)");
+        let ownership = keccak256("OwnershipTransferred(address,address)");
+        let events = |names: &[&str]| ContractWatch { events: names.iter().map(|n| n.to_string()).collect(), ..watch() };
+
+        let contract = events(&["Transfer", "Approval"]);
+        assert_eq!(contract.topic0s(), Some(vec![transfer, approval]));
+        let mut expected = vec![transfer, approval];
+        expected.sort();
+        assert_eq!(topics(&AddressWatch::group(vec![contract]).remove(0)), Some(expected));
+
+        // A proxy sharing the address widens the filter to the union
+        let proxy = ContractWatch { name: "USDT-proxy".to_string(), ..events(&["OwnershipTransferred", "Transfer"]) };
+        let group = AddressWatch::group(vec![events(&["Transfer"]), proxy]).remove(0);
+        let mut expected = vec![transfer, ownership];
+        expected.sort();
+        assert_eq!(topics(&group), Some(expected));
+
+        // No list, an unknown name or forwarding raw logs means every log
+        assert_eq!(events(&[]).topic0s(), None);
+        let unknown = events(&["Transfer", "Rebase"]);
+        assert_eq!(unknown.topic0s(), None);
+        assert_eq!(unknown.unknown_events(), ["Rebase"]);
+        assert_eq!(ContractWatch { forward_unknown: true, ..events(&["Transfer"]) }.topic0s(), None);
+        assert_eq!(topics(&AddressWatch::group(vec![events(&[])]).remove(0)), None);
+    }
+
+    #[tokio::test]
+    async fn events_carry_their_chain_id() {
+        let polygon = ContractWatch { chain_id: 137, chain_name: "polygon".to_string(), ..watch() };
+        let mut receivers = Vec::new();
+        for contract in [watch(), polygon] {
+            let (tx, rx) = tokio::sync::mpsc::channel(10);
+            let watch = AddressWatch::group(vec![contract]).remove(0);
+            for event in watch.normalize(&transfer(OWNER, SPENDER, U256::from(5))) {
+                tx.send(event).await.unwrap();
+            }
+            receivers.push(rx);
+        }
+
+        let ethereum = receivers[0].recv().await.unwrap();
+        assert_eq!((ethereum.chain_id, ethereum.chain_name.as_str()), (1, "ethereum"));
+        let polygon = receivers[1].recv().await.unwrap();
+        assert_eq!((polygon.chain_id, polygon.chain_name.as_str()), (137, "polygon"));
+    }
+
+    /// An archive node whose `eth_getCode` returns code for USDT from block
+    /// `deployed` up to (not including) `destroyed`.
+    struct ArchiveNode {
+        deployed: u64,
+        destroyed: u64,
+    }
+
+    impl wiremock::Respond for ArchiveNode {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["method"], "eth_getCode");
+            let block = u64::from_str_radix(body["params"][1].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            let code = if (self.deployed..self.destroyed).contains(&block) { "0x6001" } else { "0x" };
+            wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": body["id"], "result": code }))
+        }
+    }
+
+    async fn archive_node(deployed: u64, destroyed: u64) -> (wiremock::MockServer, ReqwestProvider) {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(ArchiveNode { deployed, destroyed })
+            .mount(&server)
+            .await;
+        let provider = ProviderBuilder::new().on_http(server.uri().parse().unwrap());
+        (server, provider)
+    }
+
+    /// A node whose head advances by one per `eth_blockNumber` and whose
+    /// `eth_getCode` returns `codes` in turn, repeating the last.
+    struct UpgradingNode {
+        codes: Vec<&'static str>,
+        polls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl wiremock::Respond for UpgradingNode {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let poll = self.polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let result = match body["method"].as_str().unwrap() {
+                "eth_blockNumber" => format!("{:#x}", 100 + poll),
+                _ => self.codes[(poll / 2).min(self.codes.len() - 1)].to_string(),
+            };
+            wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }))
+        }
+    }
+
+    #[tokio::test]
+    async fn code_change_between_polls_emits_one_event() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(UpgradingNode { codes: vec!["0x6001", "0x6001", "0x600260"], polls: Default::default() })
+            .mount(&server)
+            .await;
+        let provider = Arc::new(ProviderBuilder::new().on_http(server.uri().parse().unwrap()));
+        let batcher = RpcBatcher::spawn(provider, RpcLimiter::default(), Duration::ZERO);
+        let state = Arc::new(AppState::new());
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let watch = Arc::new(AddressWatch::group(vec![watch()]).remove(0));
+        tokio::spawn(watch_code(batcher, state, watch, tx, Duration::from_millis(10)));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(event.event_type, EventType::CodeChanged));
+        assert_eq!(event.severity, Severity::High);
+        assert_eq!(event.block_number, 104, "third poll");
+        assert_eq!(event.data["previous_code_hash"], alloy::primitives::keccak256([0x60, 0x01]).to_string());
+        assert_eq!(event.data["code_size"], 3);
+        assert_eq!(event.data["destroyed"], false);
+        // Unchanged code afterwards raises nothing more
+        assert!(tokio::time::timeout(Duration::from_millis(100), rx.recv()).await.is_err());
+    }
+
+    /// A node whose `totalSupply()` returns `supplies` in turn, one per
+    /// poll, repeating the last.
+    struct MintingNode {
+        supplies: Vec<u64>,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl wiremock::Respond for MintingNode {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let result = match body["method"].as_str().unwrap() {
+                "eth_blockNumber" => "0x64".to_string(),
+                "eth_call" => {
+                    let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    B256::from(U256::from(self.supplies[call.min(self.supplies.len() - 1)])).to_string()
+                }
+                method => panic!("unexpected {}", method),
+            };
+            wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }))
+        }
+    }
+
+    #[tokio::test]
+    async fn supply_jumps_between_polls_reach_the_rule() {
+        use crate::rules::{Rule, SupplyChangeRule};
+
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(MintingNode { supplies: vec![1_000_000, 1_000_000, 1_200_000, 1_210_000], calls: Default::default() })
+            .mount(&server)
+            .await;
+        let provider = Arc::new(ProviderBuilder::new().on_http(server.uri().parse().unwrap()));
+        let batcher = RpcBatcher::spawn(provider, RpcLimiter::default(), Duration::ZERO);
+        let state = Arc::new(AppState::new());
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let watch = Arc::new(AddressWatch::group(vec![watch()]).remove(0));
+        tokio::spawn(watch_supply(batcher, state, watch, tx, Duration::from_millis(10)));
+        let rule = SupplyChangeRule::new(10.0, Severity::Critical);
+
+        // The first reading is only a baseline and the second is unchanged, so
+        // the third is the first event
+        let mint = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(mint.event_type, EventType::SupplyChanged));
+        assert_eq!(mint.data, serde_json::json!({ "previous_supply": "1000000", "total_supply": "1200000" }));
+        let (message, severity) = rule.check(&mint).unwrap();
+        assert_eq!(message, format!("Supply Inflation: USDT ({}) total supply rose 20.00% (1.0 -> 1.2)", USDT));
+        assert_eq!(severity, Severity::Critical);
+
+        // A move within the bar is still an event, but the rule lets it pass
+        let drift = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(drift.data["previous_supply"], "1200000");
+        assert!(rule.check(&drift).is_none());
+    }
+
+    /// A node at head 1000 that moves to 1001 after the first
+    /// `eth_blockNumber`, holding a USDT Transfer in each block of `logs`.
+    struct HistoryNode {
+        logs: Vec<u64>,
+        heads: std::sync::atomic::AtomicU64,
+    }
+
+    impl wiremock::Respond for HistoryNode {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let block = |key: &str| u64::from_str_radix(body["params"][0][key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            let result = match body["method"].as_str().unwrap() {
+                "eth_blockNumber" => serde_json::json!(format!("{:#x}", 1000 + self.heads.fetch_add(1, std::sync::atomic::Ordering::SeqCst).min(1))),
+                "eth_getCode" => serde_json::json!("0x6001"),
+                "eth_getLogs" => {
+                    let range = block("fromBlock")..=block("toBlock");
+                    let logs: Vec<Log> = self.logs.iter().filter(|b| range.contains(b))
+                        .map(|b| Log { block_number: Some(*b), ..transfer(OWNER, SPENDER, U256::from(*b)) })
+                        .collect();
+                    serde_json::to_value(logs).unwrap()
+                }
+                method => panic!("unexpected {}", method),
+            };
+            wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }))
+        }
+    }
+
+    #[tokio::test]
+    async fn backfilled_logs_come_before_live_ones() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(HistoryNode { logs: vec![985, 992, 999, 1001], heads: Default::default() })
+            .mount(&server)
+            .await;
+        let provider = Arc::new(ProviderBuilder::new().on_http(server.uri().parse().unwrap()));
+        let state = AppState::new();
+        let watch = Arc::new(AddressWatch::group(vec![watch()]).remove(0));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+
+        let head = backfill_logs(provider.as_ref(), &state, &watch, &tx, 10, 4).await.unwrap();
+        assert_eq!(head, 1000);
+        let requests = server.received_requests().await.unwrap();
+        let pages = requests.iter().filter(|r| r.body_json::<serde_json::Value>().unwrap()["method"] == "eth_getLogs").count();
+        assert_eq!(pages, 3, "990-993, 994-997, 998-1000");
+
+        tokio::spawn(watch_logs_polling(provider, RpcLimiter::default(), watch, tx, Duration::from_millis(10), 4, Some(head)));
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+            seen.push((event.block_number, event.historical));
+        }
+        // 985 is outside the window
+        assert_eq!(seen, [(992, true), (999, true), (1001, false)]);
+    }
+
+    #[tokio::test]
+    async fn deployment_block_is_found_and_cached() {
+        let (server, provider) = archive_node(4_634_748, u64::MAX).await;
+        let state = AppState::new();
+
+        let block = find_deployment_block(&provider, &state, "ethereum", USDT, 19_000_000).await.unwrap();
+        assert_eq!(block, Some(4_634_748));
+        let probes = server.received_requests().await.unwrap().len();
+        assert!(probes <= 1 + 25, "binary search, not a scan: {} probes", probes);
+
+        let block = find_deployment_block(&provider, &state, "ethereum", USDT, 19_000_000).await.unwrap();
+        assert_eq!(block, Some(4_634_748));
+        assert_eq!(server.received_requests().await.unwrap().len(), probes, "second lookup is cached");
+    }
+
+    #[tokio::test]
+    async fn self_destructed_contract_has_no_deployment_block() {
+        let (_server, provider) = archive_node(100, 5_000).await;
+        let state = AppState::new();
+        assert_eq!(find_deployment_block(&provider, &state, "ethereum", USDT, 10_000).await.unwrap(), None);
+        assert_eq!(state.cached_deployment_block("ethereum", USDT), None);
+    }
+
+    /// A node whose latest block walks through `heads`, staying on the last;
+    /// `None` serves a header without a number.
+    struct HeadNode {
+        heads: Vec<Option<u64>>,
+        polls: std::sync::atomic::AtomicUsize,
+        timestamp: u64,
+    }
+
+    impl wiremock::Respond for HeadNode {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["method"], "eth_getBlockByNumber");
+            let poll = self.polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let header = alloy::rpc::types::Header {
+                number: self.heads[poll.min(self.heads.len() - 1)],
+                timestamp: self.timestamp,
+                ..Default::default()
+            };
+            let block: alloy::rpc::types::Block = alloy::rpc::types::Block { header, ..Default::default() };
+            wiremock::ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": body["id"], "result": block }))
+                .set_delay(Duration::from_millis(50))
+        }
+    }
+
+    #[tokio::test]
+    async fn headers_without_a_number_leave_the_height_alone() {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(HeadNode { heads: vec![None, Some(0), Some(7), None], polls: Default::default(), timestamp: now - 120 })
+            .mount(&server)
+            .await;
+        let provider = Arc::new(ProviderBuilder::new().on_http(server.uri().parse().unwrap()));
+        let state = Arc::new(AppState::new());
+        let poller = tokio::spawn(watch_blocks_polling(provider, state.clone(), "arbitrum".to_string(), None, Duration::from_millis(10)));
+
+        // Polls run one after another, so a new request means the last header was
+        // handled, and the reply delay keeps the next one from landing meanwhile
+        let height_after = |polls: usize| {
+            let (server, state) = (&server, &state);
+            async move {
+                while server.received_requests().await.unwrap().len() <= polls {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+                state.chain_heights.lock().unwrap().get("arbitrum").copied()
+            }
+        };
+        assert_eq!(height_after(1).await, None, "not recorded as block 0");
+        assert_eq!(height_after(2).await, Some(0), "a real block 0 is");
+        assert_eq!(height_after(3).await, Some(7));
+        assert_eq!(height_after(5).await, Some(7));
+        poller.abort();
+
+        // The sequencer's timestamp, two minutes old, counts towards the age
+        let (_, age, stale) = state.chain_health(Instant::now()).remove(0);
+        assert!((120..130).contains(&age), "{}", age);
+        assert!(stale);
+    }
+
+    #[tokio::test]
+    async fn stalled_streams_are_reconnected() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Mutex;
+
+        let state = Arc::new(AppState::new());
+        let policy = RetryPolicy { max_retries: Some(3), base_delay: Duration::from_millis(10), stall_timeout: Some(Duration::from_millis(150)) };
+        // Providers are numbered by connection; each session records which one it got
+        let connections = Arc::new(AtomicU32::new(0));
+        let sessions = Arc::new(Mutex::new(Vec::new()));
+
+        let (state_run, connections_run, sessions_run) = (state.clone(), connections.clone(), sessions.clone());
+        let runner = tokio::spawn(async move {
+            let connect = || {
+                let connections = connections_run.clone();
+                async move { Ok(connections.fetch_add(1, Ordering::SeqCst) + 1) }
+            };
+            run_with_reconnect(0u32, connect, &state_run, "ethereum", "ethereum blocks", policy, |provider| {
+                sessions_run.lock().unwrap().push(provider);
+                let state = state_run.clone();
+                async move {
+                    if provider == 0 {
+                        // Connected, but the node never pushes a block
+                        std::future::pending::<()>().await;
+                    }
+                    // The new connection streams a block every 50ms
+                    loop {
+                        state.update_block("ethereum", 100, None);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    }
+                }
+            })
+            .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        runner.abort();
+        assert_eq!(*sessions.lock().unwrap(), [0, 1], "one reconnect after the stall, none while blocks flow");
+        assert_eq!(state.reconnects.load(Ordering::Relaxed), 1);
+    }
+}
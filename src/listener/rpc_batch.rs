@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::time::Duration;
+use alloy::primitives::{Address, Bytes, U64};
+use alloy::providers::Provider;
+use alloy::rpc::client::BatchRequest;
+use alloy::rpc::types::{BlockNumberOrTag, TransactionInput, TransactionRequest};
+use alloy::transports::{RpcError, Transport, TransportErrorKind, TransportResult};
+use eyre::Result;
+use futures_util::future::join_all;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info};
+
+use super::rpc_limit::RpcLimiter;
+
+/// Most requests sent in one batch; later ones wait for the next.
+const MAX_BATCH: usize = 100;
+
+/// A read waiting for the next batch.
+struct Pending {
+    method: &'static str,
+    params: Value,
+    reply: oneshot::Sender<TransportResult<Box<RawValue>>>,
+}
+
+/// Coalesces a chain's per-contract reads (code and supply polls, which tick
+/// for every contract at once) into JSON-RPC batch requests: reads arriving
+/// within `window` of the first go out as one request, under one permit of
+/// the chain's `RpcLimiter`. If the node rejects a batch outright, it and
+/// everything after it is sent as individual calls instead. A zero window
+/// turns batching off. Clones share the batch; the background task stops
+/// once every clone is dropped.
+#[derive(Debug, Clone)]
+pub struct RpcBatcher {
+    requests: mpsc::UnboundedSender<Pending>,
+}
+
+impl RpcBatcher {
+    pub fn spawn<T, P>(provider: Arc<P>, rpc: RpcLimiter, window: Duration) -> Self
+    where
+        T: Transport + Clone,
+        P: Provider<T> + 'static,
+    {
+        let (requests, pending) = mpsc::unbounded_channel();
+        tokio::spawn(run(provider, rpc, pending, window));
+        Self { requests }
+    }
+
+    /// Sends `method` with `params` in the next batch and decodes the result.
+    pub async fn request<R: DeserializeOwned>(&self, method: &'static str, params: Value) -> Result<R> {
+        let (reply, result) = oneshot::channel();
+        self.requests.send(Pending { method, params, reply })
+            .map_err(|_| eyre::eyre!("RPC batcher stopped"))?;
+        let raw = result.await.map_err(|_| eyre::eyre!("RPC batcher dropped {}", method))??;
+        Ok(serde_json::from_str(raw.get())?)
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        let number: U64 = self.request("eth_blockNumber", json!([])).await?;
+        Ok(number.to())
+    }
+
+    pub async fn get_code(&self, address: Address, block: u64) -> Result<Bytes> {
+        self.request("eth_getCode", json!([address, BlockNumberOrTag::Number(block)])).await
+    }
+
+    /// `eth_call` of `data` on `to` at the latest block.
+    pub async fn call(&self, to: Address, data: Bytes) -> Result<Bytes> {
+        let tx = TransactionRequest::default().to(to).input(TransactionInput::new(data));
+        self.request("eth_call", json!([tx, BlockNumberOrTag::Latest])).await
+    }
+}
+
+async fn run<T, P>(provider: Arc<P>, rpc: RpcLimiter, mut pending: mpsc::UnboundedReceiver<Pending>, window: Duration)
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let mut batching = !window.is_zero();
+    while let Some(first) = pending.recv().await {
+        let mut batch = vec![first];
+        if batching {
+            let deadline = tokio::time::sleep(window);
+            tokio::pin!(deadline);
+            while batch.len() < MAX_BATCH {
+                tokio::select! {
+                    _ = &mut deadline => break,
+                    next = pending.recv() => match next {
+                        Some(request) => batch.push(request),
+                        None => break,
+                    },
+                }
+            }
+        }
+
+        let batch = if batching && batch.len() > 1 {
+            match rpc.run(send_batch(provider.as_ref(), batch)).await {
+                Ok(()) => continue,
+                Err(unsent) => {
+                    info!("RPC node rejected a batch request, sending reads individually from now on");
+                    batching = false;
+                    unsent
+                }
+            }
+        } else {
+            batch
+        };
+        let (rpc, provider) = (&rpc, &provider);
+        join_all(batch.into_iter().map(|request| async move {
+            let result = rpc.run(provider.client().request(request.method, request.params)).await;
+            let _ = request.reply.send(result);
+        }))
+        .await;
+    }
+}
+
+/// Sends `batch` as one JSON-RPC batch and answers each request. Gives the
+/// requests back unanswered when the node refused the batch as a whole: a
+/// transport error, or no entry answered at all (nodes without batch support
+/// reply with a single error object). Per-entry errors, such as a reverted
+/// `eth_call`, are answered like any other result.
+async fn send_batch<T, P>(provider: &P, batch: Vec<Pending>) -> std::result::Result<(), Vec<Pending>>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let mut request = BatchRequest::new(provider.client());
+    let mut waiters = Vec::with_capacity(batch.len());
+    for pending in &batch {
+        match request.add_call::<_, Box<RawValue>>(pending.method, &pending.params) {
+            Ok(waiter) => waiters.push(waiter),
+            Err(_) => return Err(batch),
+        }
+    }
+    debug!("Sending {} RPC reads as one batch", batch.len());
+    if request.send().await.is_err() {
+        return Err(batch);
+    }
+    let results = join_all(waiters).await;
+    if results.iter().all(|r| matches!(r, Err(RpcError::Transport(TransportErrorKind::MissingBatchResponse(_))))) {
+        return Err(batch);
+    }
+    for (pending, result) in batch.into_iter().zip(results) {
+        let _ = pending.reply.send(result);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, bytes};
+    use alloy::providers::ProviderBuilder;
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    const TOKEN: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+
+    /// A JSON-RPC node answering `eth_blockNumber` and `eth_getCode`.
+    /// Without `batches` it refuses array bodies the way nodes without batch
+    /// support do: one error object with a null id.
+    struct Node {
+        batches: bool,
+    }
+
+    impl Node {
+        fn answer(request: &Value) -> Value {
+            let result = match request["method"].as_str() {
+                Some("eth_blockNumber") => json!("0x64"),
+                _ => json!("0x6001"),
+            };
+            json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+        }
+    }
+
+    impl Respond for Node {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let reply = match &body {
+                Value::Array(requests) if self.batches => Value::Array(requests.iter().map(Self::answer).collect()),
+                Value::Array(_) => json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32600, "message": "batch requests not supported" } }),
+                single => Self::answer(single),
+            };
+            ResponseTemplate::new(200).set_body_json(reply)
+        }
+    }
+
+    /// Issues `n` concurrent code reads plus a block number read; returns how
+    /// many HTTP requests reached the node.
+    async fn poll(server: &MockServer, batcher: &RpcBatcher, n: usize) -> usize {
+        let before = server.received_requests().await.unwrap().len();
+        let (head, codes) = tokio::join!(
+            batcher.block_number(),
+            join_all((0..n).map(|_| batcher.get_code(TOKEN, 100))),
+        );
+        assert_eq!(head.unwrap(), 100);
+        for code in codes {
+            assert_eq!(code.unwrap(), bytes!("6001"));
+        }
+        server.received_requests().await.unwrap().len() - before
+    }
+
+    async fn node(batches: bool) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(Node { batches })
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn batcher(server: &MockServer, window: Duration) -> RpcBatcher {
+        let provider = Arc::new(ProviderBuilder::new().on_http(server.uri().parse().unwrap()));
+        // One request at a time, as on a rate-limited node
+        RpcBatcher::spawn(provider, RpcLimiter::new(1), window)
+    }
+
+    #[tokio::test]
+    async fn reads_within_the_window_share_one_request() {
+        let server = node(true).await;
+        assert_eq!(poll(&server, &batcher(&server, Duration::from_millis(20)), 5).await, 1);
+        let batch: Value = serde_json::from_slice(&server.received_requests().await.unwrap()[0].body).unwrap();
+        assert_eq!(batch.as_array().map(Vec::len), Some(6), "every read in the one body");
+
+        // A zero window sends each read on its own
+        assert_eq!(poll(&server, &batcher(&server, Duration::ZERO), 5).await, 6);
+    }
+
+    #[tokio::test]
+    async fn rejected_batches_fall_back_to_individual_calls() {
+        let server = node(false).await;
+        let batcher = batcher(&server, Duration::from_millis(20));
+        // The refused batch, then each read on its own
+        assert_eq!(poll(&server, &batcher, 3).await, 5);
+        // Batching stays off afterwards
+        assert_eq!(poll(&server, &batcher, 3).await, 4);
+    }
+}
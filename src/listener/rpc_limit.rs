@@ -0,0 +1,104 @@
+use std::future::IntoFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Caps how many RPC requests a chain has in flight at once, shared by its
+/// listeners, pollers, backfill and metadata fetches. Subscriptions only
+/// hold a permit while being set up, not while they stream.
+#[derive(Debug, Clone, Default)]
+pub struct RpcLimiter {
+    // None = unlimited (still counted for metrics)
+    permits: Option<Arc<Semaphore>>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Counts a request as in flight until dropped.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl RpcLimiter {
+    /// At most `max` concurrent requests; 0 means no limit.
+    pub fn new(max: usize) -> Self {
+        Self {
+            permits: (max > 0).then(|| Arc::new(Semaphore::new(max))),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Waits for a permit, then runs `request` while holding it.
+    pub async fn run<F: IntoFuture>(&self, request: F) -> F::Output {
+        let _permit = match &self.permits {
+            // The semaphore is never closed, so acquiring only fails on shutdown paths
+            Some(permits) => permits.acquire().await.ok(),
+            None => None,
+        };
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let _counted = InFlight(&self.in_flight);
+        request.await
+    }
+
+    /// Requests currently executing (not those waiting for a permit).
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+    use futures_util::future::join_all;
+    use std::time::Duration;
+
+    /// Stands in for an RPC node, recording the most calls it served at once.
+    #[derive(Default)]
+    struct Node {
+        current: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Node {
+        async fn call(&self) {
+            let now = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    async fn peak(limiter: &RpcLimiter, calls: usize) -> usize {
+        let node = Node::default();
+        join_all((0..calls).map(|_| limiter.run(node.call()))).await;
+        node.peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn permits_cap_concurrent_calls() {
+        assert_eq!(peak(&RpcLimiter::new(2), 6).await, 2);
+        assert_eq!(peak(&RpcLimiter::new(0), 6).await, 6, "0 is unlimited");
+    }
+
+    #[tokio::test]
+    async fn in_flight_calls_show_per_chain() {
+        let state = AppState::new();
+        state.set_rpc_limiter("ethereum", RpcLimiter::new(2));
+        let limiter = state.rpc_limiter("ethereum");
+        let node = Node::default();
+
+        let calls = join_all((0..5).map(|_| limiter.run(node.call())));
+        let observe = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            state.rpc_in_flight()
+        };
+        let (_, during) = tokio::join!(calls, observe);
+        // Waiting calls are not counted, only those holding a permit
+        assert_eq!(during, [("ethereum".to_string(), 2)]);
+        assert_eq!(state.rpc_in_flight(), [("ethereum".to_string(), 0)]);
+    }
+}
@@ -0,0 +1,189 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::EnvFilter;
+use crate::config::{LogFormat, LoggingConfig, TelemetryConfig};
+
+/// `[logging]` level and module directives in `RUST_LOG` syntax, e.g.
+/// "info,evm_event_watchdog::listener=warn".
+pub fn filter_directives(config: &LoggingConfig) -> String {
+    let mut directives = vec![config.level.clone()];
+    let mut modules: Vec<_> = config.directives.iter().collect();
+    modules.sort();
+    directives.extend(modules.into_iter().map(|(target, level)| format!("{}={}", target, level)));
+    directives.join(",")
+}
+
+/// The log filter: `rust_log` (the `RUST_LOG` variable) when set, otherwise
+/// `[logging]`.
+pub fn env_filter(config: &LoggingConfig, rust_log: Option<&str>) -> Result<EnvFilter, ParseError> {
+    match rust_log.filter(|directives| !directives.trim().is_empty()) {
+        Some(directives) => EnvFilter::try_new(directives),
+        None => EnvFilter::try_new(filter_directives(config)),
+    }
+}
+
+/// Builds the OTLP span exporter for `[telemetry]`, or None when export is
+/// off. Spans are batched and sent from a background thread.
+pub fn tracer_provider(config: &TelemetryConfig) -> eyre::Result<Option<SdkTracerProvider>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&config.otlp_endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+        .build();
+    Ok(Some(provider))
+}
+
+/// Builds the file subscriber for `format` behind `filter`, plus span export through
+/// `tracer` when given. JSON output flattens event fields (chain_name,
+/// event_type, severity, ...) into top-level keys.
+pub fn subscriber<W>(format: LogFormat, filter: EnvFilter, writer: W, tracer: Option<&SdkTracerProvider>) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let otel = tracer.map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("evm_event_watchdog")));
+    let file = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false); // Clean text for file
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(otel);
+
+    match format {
+        LogFormat::Text => Box::new(registry.with(file)),
+        LogFormat::Json => Box::new(registry.with(file.json().flatten_event(true).with_current_span(false))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything the subscriber writes.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The output of one alert-shaped event logged in `format`, inside an
+    /// event span exported through `tracer` when given.
+    fn log_alert_with(format: LogFormat, tracer: Option<&SdkTracerProvider>) -> String {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let filter = env_filter(&LoggingConfig::default(), None).unwrap();
+        let subscriber = subscriber(format, filter, move || writer.clone(), tracer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _event = crate::events::NormalizedEvent::new_span(1, alloy::primitives::B256::repeat_byte(0xab)).entered();
+            tracing::info!(chain_name = "ethereum", severity = "High", "Alert raised");
+            tracing::debug!("below the default level");
+        });
+        let output = buffer.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn log_alert(format: LogFormat) -> String {
+        log_alert_with(format, None)
+    }
+
+    #[test]
+    fn text_and_json_formats_both_build() {
+        let text = log_alert(LogFormat::Text);
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.contains("Alert raised chain_name=\"ethereum\" severity=\"High\""), "{}", text);
+
+        let json = log_alert(LogFormat::Json);
+        let line: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+        assert_eq!(line["message"], "Alert raised");
+        assert_eq!(line["chain_name"], "ethereum");
+        assert_eq!(line["severity"], "High");
+        assert_eq!(line["level"], "INFO");
+    }
+
+    #[test]
+    fn level_and_directives_build_the_filter_unless_rust_log_is_set() {
+        let config: LoggingConfig = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+level = "warn"
+directives = { "evm_event_watchdog::listener" = "off", "evm_event_watchdog::alerts" = "info" }
+"#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .unwrap();
+        assert_eq!(filter_directives(&config), "warn,evm_event_watchdog::alerts=info,evm_event_watchdog::listener=off");
+
+        let logged = |rust_log: Option<&str>| {
+            let buffer = Buffer::default();
+            let writer = buffer.clone();
+            let filter = env_filter(&config, rust_log).unwrap();
+            tracing::subscriber::with_default(subscriber(LogFormat::Text, filter, move || writer.clone(), None), || {
+                tracing::info!(target: "evm_event_watchdog::listener", "New Block");
+                tracing::info!(target: "evm_event_watchdog::alerts", "Alert raised");
+                tracing::info!(target: "evm_event_watchdog::rules", "Rule evaluated");
+                tracing::warn!(target: "evm_event_watchdog::rules", "Rule slow");
+            });
+            let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            output.lines().map(|line| line.rsplit(": ").next().unwrap().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(logged(None), ["Alert raised", "Rule slow"]);
+        // RUST_LOG replaces the config; a blank one does not
+        assert_eq!(logged(Some("evm_event_watchdog::listener=info")), ["New Block"]);
+        assert_eq!(logged(Some(" ")), ["Alert raised", "Rule slow"]);
+        // A malformed RUST_LOG is an error for main to report
+        assert!(env_filter(&config, Some("evm_event_watchdog=loud")).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn spans_export_over_otlp_only_when_enabled() {
+        assert!(tracer_provider(&TelemetryConfig::default()).unwrap().is_none());
+
+        let collector = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/v1/traces"))
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+        let config = TelemetryConfig {
+            enabled: true,
+            otlp_endpoint: format!("{}/v1/traces", collector.uri()),
+            ..TelemetryConfig::default()
+        };
+        let provider = tracer_provider(&config).unwrap().expect("export enabled");
+
+        // File logging is unaffected by the extra layer
+        let text = log_alert_with(LogFormat::Text, Some(&provider));
+        assert!(text.contains("Alert raised"), "{}", text);
+        let provider = tokio::task::spawn_blocking(move || { provider.force_flush().unwrap(); provider }).await.unwrap();
+
+        let requests = collector.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        let body = String::from_utf8_lossy(&requests[0].body);
+        assert!(body.contains("evm_event_watchdog"), "service name resource");
+        assert!(body.contains(&alloy::primitives::B256::repeat_byte(0xab).to_string()), "tx_hash span attribute");
+        assert!(body.contains("chain_id"));
+        tokio::task::spawn_blocking(move || provider.shutdown()).await.unwrap().unwrap();
+    }
+}
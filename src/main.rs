@@ -5,21 +5,44 @@ mod alerts;
 mod config;
 mod tui;
 mod state;
+mod heartbeat;
+mod metrics;
+mod health;
+mod logging;
+mod supervisor;
+mod replay;
+mod once;
+mod queue;
+mod tokens;
+mod export;
 
 use dotenv::dotenv;
 use eyre::Result;
-use tracing::{info, error, warn};
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use tracing::{info, info_span, error, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::IsTerminal;
+use tokio::sync::{mpsc, watch};
 use alloy::primitives::{Address, U256};
-use crate::config::AppConfig;
-use crate::events::Severity;
-
-use crate::listener::{connect, watch_blocks, watch_logs};
-use crate::state::AppState;
+use crate::config::{AppConfig, AutoMuteConfig, QueuePolicy, ThresholdUnit};
+use crate::events::{NormalizedEvent, Severity};
+use crate::state::{AlertOrigin, AppState, MuteDecision};
+use crate::supervisor::Supervisor;
 use std::time::Duration;
-use crate::rules::{RuleEngine, ThresholdRule, OwnershipRule, HighApprovalRule};
-use crate::alerts::AlertManager;
+use crate::rules::{
+    load_address_list, BlacklistRule, DrainPatternRule, DrainerApprovalRule, FanOutRule, GasSpikeRule, HighApprovalRule, LargeSwapRule, MintBurnDirection,
+    MintBurnRule, NftTransferRule, OwnershipPingPongRule, OwnershipRule, RuleEngine, SignedThresholdRule, SupplyChangeRule, Threshold, ThresholdRule, VelocityRule, WatchlistTransferRule,
+};
+use crate::alerts::digest::Activity;
+use crate::alerts::template::MessageTemplates;
+use crate::alerts::telegram::poll_acks;
+use crate::alerts::{AlertContext, AlertManager};
+use crate::heartbeat::{HeartbeatMonitor, HeartbeatWatch};
+
+/// How long shutdown waits for queued events and in-flight alerts.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -28,75 +51,183 @@ async fn main() -> Result<()> {
     // Parse args immediately
     let args: Vec<String> = std::env::args().collect();
     
+    // 1. Load Config (optionally layered with --profile <name>); first, since it picks the log format
+    let profile = arg_value(&args, "--profile");
+    let loaded = load_config(profile.as_deref());
+
     // File Logging Setup (Critical for TUI)
     let file_appender = tracing_appender::rolling::daily("logs", "watchdog.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-    
-    tracing_subscriber::fmt()
-        .with_writer(non_blocking)
-        .with_ansi(false) // Clean text for file
-        .init();
+    let logging = loaded.as_ref().map(|c| c.logging.clone()).unwrap_or_default();
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let filter = crate::logging::env_filter(&logging, rust_log.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Invalid RUST_LOG ({}), using [logging] levels", e);
+        crate::logging::env_filter(&logging, None).unwrap_or_else(|_| EnvFilter::new("info"))
+    });
+    let tracer_provider = match loaded.as_ref().map(|c| crate::logging::tracer_provider(&c.telemetry)) {
+        Ok(Err(e)) => {
+            eprintln!("OTLP trace export disabled: {}", e);
+            None
+        }
+        Ok(Ok(provider)) => provider,
+        Err(_) => None,
+    };
+    tracing::subscriber::set_global_default(crate::logging::subscriber(logging.format, filter, non_blocking, tracer_provider.as_ref()))?;
 
     info!("Starting EVM Event Watchdog - TUI Mode");
-    
-    
-    // 1. Load Config
-    let mut config = AppConfig::new().expect("Failed to load config");
-    
-    // Allow .env override for RPC_URL (Legacy support / easy setup)
-    if let Ok(env_rpc) = std::env::var("RPC_URL") {
-        if let Some(chain_cfg) = config.chains.get_mut("ethereum") {
-            chain_cfg.rpc_url = env_rpc;
-        }
+    if let Some(p) = &profile {
+        info!("Using config profile: {}", p);
     }
-    
 
+    let mut config = match loaded {
+        Ok(config) => config,
+        Err(problems) => {
+            eprintln!("Invalid configuration:");
+            for problem in &problems {
+                eprintln!("  - {}", problem);
+                error!("Config: {}", problem);
+            }
+            eyre::bail!("{} configuration problem(s), see above", problems.len());
+        }
+    };
 
     info!("Configuration Loaded.");
-    info!("  Discord Webhook: {}", if config.alerts.webhook_url.is_empty() { "Disabled" } else { "Enabled" });
+    let discord_routes = config.alerts.webhook_url.routes().len();
+    info!("  Discord Webhook: {}", if discord_routes == 0 { "Disabled".to_string() } else { format!("Enabled ({})", discord_routes) });
     info!("  Telegram Bot: {}", if config.alerts.telegram_bot_token.is_some() { "Enabled" } else { "Disabled" });
+    info!("  Email (SMTP): {}", if config.alerts.smtp_host.is_some() { "Enabled" } else { "Disabled" });
+    if tracer_provider.is_some() {
+        info!("  OTLP traces: {} (as {})", config.telemetry.otlp_endpoint, config.telemetry.service_name);
+    }
 
     // 2. Setup Components
-    let alert_manager =  Arc::new(AlertManager::new(config.alerts));
-    let state = Arc::new(AppState::new());
-    
-    let mut engine = RuleEngine::new();
+    let explorers: HashMap<String, String> = config.chains.iter()
+        .filter_map(|(name, chain)| chain.explorer_url.clone().map(|url| (name.clone(), url)))
+        .collect();
+    if args.contains(&"--dry-run".to_string()) {
+        config.alerts.dry_run = true;
+    }
+    if config.alerts.dry_run {
+        info!("  Dry run: alerts are logged, not sent");
+    }
+    // --export-csv <file> on its own dumps the persisted alert history and
+    // exits; with --once it dumps the pass's alerts after the run instead
+    let export_path = arg_value(&args, "--export-csv");
+    if let (Some(path), false) = (&export_path, args.contains(&"--once".to_string())) {
+        let Some(persistence) = &config.persistence else {
+            eyre::bail!("--export-csv needs a [persistence] history_file (or --once to export a single pass)");
+        };
+        let records = crate::state::read_history_file(std::path::Path::new(&persistence.history_file))
+            .map_err(|e| eyre::eyre!("cannot read {}: {}", persistence.history_file, e))?;
+        crate::export::export_csv(std::path::Path::new(path), &records)?;
+        println!("Exported {} alert(s) to {}", records.len(), path);
+        return Ok(());
+    }
 
-    // Configure Rules from Config
-    let transfer_severity = match config.rules.transfer_threshold.severity.as_str() {
-        "High" => Severity::High,
-        "Critical" => Severity::Critical,
-        "Medium" => Severity::Medium,
-        _ => Severity::Low,
-    };
-    
-    let ownership_severity = match config.rules.ownership_change.severity.as_str() {
-        "High" => Severity::High,
-        "Critical" => Severity::Critical,
-        "Medium" => Severity::Medium,
-        _ => Severity::Low,
-    };
-    let min_value: U256 = config.rules.transfer_threshold.min_value.parse().unwrap_or(U256::from(1000));
+    let alert_manager =  Arc::new(AlertManager::new(config.alerts.clone(), explorers));
 
-    engine.add_rule(Box::new(ThresholdRule::new(min_value, transfer_severity)));
+    // --test-alerts checks every channel and exits; alerts.self_test does the
+    // same check but only stops startup when a channel fails
+    let test_alerts = args.contains(&"--test-alerts".to_string());
+    if test_alerts || config.alerts.self_test {
+        let report = alert_manager.self_test().await;
+        println!("{}", report);
+        info!("{}", report);
+        if !report.passed() {
+            drop(_guard);
+            std::process::exit(1);
+        }
+        if test_alerts {
+            return Ok(());
+        }
+    }
+    let state = AppState::new().with_history_capacity(config.history.capacity).with_history_limits(
+        Duration::from_secs(config.history.dedup_window_secs),
+        config.history.retention_secs.map(Duration::from_secs),
+    );
+    let state = Arc::new(match &config.persistence {
+        Some(p) => state.with_history_file(std::path::Path::new(&p.history_file), p.restore_last),
+        None => state,
+    });
     
-    if config.rules.ownership_change.enabled {
-        engine.add_rule(Box::new(OwnershipRule::new(ownership_severity)));
+    // Rules live behind a lock so a config reload can swap the whole set at once
+    let engine = build_engine(&config)?;
+    info!("Active rules: {}", engine.rule_names().join(", "));
+    for (name, description) in engine.describe() {
+        info!("  {}: {}", name, description);
+    }
+    let engine = Arc::new(RwLock::new(Arc::new(engine)));
+
+    // Expected-event (heartbeat) watches
+    let mut watches = Vec::new();
+    for expected in &config.expected_events {
+        match config.contracts.iter().find(|c| c.name == expected.contract) {
+            Some(contract) => watches.push(HeartbeatWatch::new(
+                contract.name.clone(),
+                contract.chain.clone(),
+                contract.address,
+                expected.event.clone(),
+                Duration::from_secs(expected.interval_secs),
+                parse_severity(&expected.severity),
+            )),
+            None => warn!("Expected event references unknown contract: {}", expected.contract),
+        }
     }
+    let heartbeats = Arc::new(HeartbeatMonitor::new(watches));
 
-    // High Approval Rule (Infinite Allowance Detection)
-    engine.add_rule(Box::new(HighApprovalRule::new(
-        U256::MAX >> 1, // > 50% of uint256 max
-        Severity::Critical, 
-    )));
-    let engine = Arc::new(engine);
+    // 3. Spawn Tasks with Backpressure (or drop-oldest, per [queue]). Replays
+    // and --once runs always wait for room: their events are the whole point
+    let batch = args.contains(&"--once".to_string()) || arg_value(&args, "--replay").is_some();
+    let policy = if batch { QueuePolicy::Block } else { config.queue.on_full };
+    if policy == QueuePolicy::DropOldest {
+        info!("  Event queue: {} events, dropping the oldest when full", config.queue.capacity);
+    }
+    let (tx, rx) = crate::queue::event_queue(config.queue.capacity, policy, state.clone());
+
+    // Shutdown: set by quitting the TUI or by SIGINT/SIGTERM. Producers stop,
+    // dropping their senders, so the orchestrator drains `rx` and then exits.
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let shutdown_tx = Arc::new(shutdown_tx);
+    let signal_tx = shutdown_tx.clone();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        info!("Shutdown signal received");
+        signal_tx.send_replace(true);
+    });
+
+    let render_once = args.contains(&"--render-once".to_string());
+    // --once: one backfill pass (or replay), no TUI, JSON summary on stdout
+    let once = args.contains(&"--once".to_string());
+
+    // Replay Mode: --replay <file.jsonl> feeds recorded events instead of RPC
+    let recorded = match arg_value(&args, "--replay") {
+        Some(path) => {
+            let events = crate::replay::load_events(std::path::Path::new(&path))?;
+            info!("Replay mode: {} event(s) from {}, RPC listeners disabled", events.len(), path);
+            Some(events)
+        }
+        None => None,
+    };
 
-    // 3. Spawn Tasks with Backpressure
-    let (tx, mut rx) = mpsc::channel(100);
+    // Chain Selection: --chain <name> (repeatable) / --all, else prompt on a TTY.
+    // Without a flag or a TTY (systemd, Docker) every configured chain is watched.
+    let selection = chain_selection(&args);
+    if let ChainSelection::Only(names) = &selection {
+        if let Some(unknown) = names.iter().find(|n| !config.chains.contains_key(*n)) {
+            eyre::bail!("Unknown chain '{}' (configured: {})", unknown, config.chains.keys().cloned().collect::<Vec<_>>().join(", "));
+        }
+        info!("Chains selected via --chain: {}", names.join(", "));
+        config.chains.retain(|k, _| names.contains(k));
+    }
+    // A reload keeps to the chains picked here
+    let mut chain_filter = match &selection {
+        ChainSelection::Only(names) => Some(names.clone()),
+        _ => None,
+    };
 
-    // Interactive Chain Selection
-    // Skip if --simulate is passed (automated demo)
-    if !args.contains(&"--simulate".to_string()) && !config.chains.is_empty() {
+    // Skip the prompt if --simulate, --render-once or --once is passed (automated demo / CI capture)
+    let interactive = matches!(selection, ChainSelection::Unspecified) && std::io::stdin().is_terminal();
+    if interactive && !args.contains(&"--simulate".to_string()) && !render_once && !once && recorded.is_none() && !config.chains.is_empty() {
         let mut chain_names: Vec<String> = config.chains.keys().cloned().collect();
         chain_names.sort();
 
@@ -120,6 +251,7 @@ async fn main() -> Result<()> {
                     
                     // Filter config to keep only selected
                     config.chains.retain(|k, _| k == selected);
+                    chain_filter = Some(vec![selected.clone()]);
                 } else if choice == chain_names.len() + 1 {
                     println!("🚀 Starting Watchdog for: ALL CHAINS\n");
                 } else {
@@ -133,53 +265,52 @@ async fn main() -> Result<()> {
         tokio::time::sleep(Duration::from_millis(1000)).await;
     }
 
-    // Multi-Chain Loop
-    for (chain_name, chain_cfg) in &config.chains {
-        info!("Initializing Chain: {}", chain_name);
-        
-        let provider = match connect(&chain_cfg.rpc_url).await {
-            Ok(p) => Arc::new(p),
-            Err(e) => {
-                error!("Failed to connect to {}: {}", chain_name, e);
-                continue;
-            }
-        };
-
-        // Task A: Block Listener (Per Chain)
-        let provider_blocks = provider.clone();
-        let state_clone = state.clone();
-        let c_name = chain_name.clone();
-        tokio::spawn(async move {
-            if let Err(e) = watch_blocks(provider_blocks, state_clone, c_name).await {
-                error!("Block listener failed: {}", e);
-            }
+    // Multi-Chain Loop: block, log and code listeners per chain, owned by
+    // the supervisor so a config reload can start and stop them
+    let mut supervisor = Supervisor::new(state.clone(), tx.clone(), shutdown_rx.clone());
+    if let Some(events) = recorded {
+        let speed: f64 = arg_value(&args, "--replay-speed").and_then(|s| s.parse().ok()).unwrap_or(2.0);
+        let interval = if speed > 0.0 && !once { Duration::from_secs_f64(1.0 / speed) } else { Duration::ZERO };
+        spawn_until_shutdown(shutdown_rx.clone(), crate::replay::replay_events(events, tx.clone(), interval));
+        // Its sender copy would keep the pipeline open after the last event
+        drop(supervisor);
+    } else if once {
+        info!("Single pass mode: backfilling each chain once, then exiting");
+        let (config_once, state_once, tx_once) = (config.clone(), state.clone(), tx.clone());
+        spawn_until_shutdown(shutdown_rx.clone(), async move {
+            crate::once::scan(&config_once, state_once, tx_once).await;
         });
+        drop(supervisor);
+    } else {
+        supervisor.start(&config).await?;
 
-        // Task B: Log Listener (Per Contract on this Chain)
-        for contract in &config.contracts {
-            if contract.chain == *chain_name {
-                info!("  Watching Contract: {} on {}", contract.name, chain_name);
-                let provider_logs = provider.clone();
-                let tx_clone = tx.clone();
-                let address = contract.address;
-                let c_id = chain_cfg.chain_id;
-                let c_name_log = chain_name.clone();
-                
-                tokio::spawn(async move {
-                    if let Err(e) = watch_logs(provider_logs, address, c_id, c_name_log, tx_clone).await {
-                        error!("Log listener failed: {}", e);
-                    }
-                });
-            }
+        // Task R: SIGHUP reloads chains, contracts and rules in place, and
+        // chains that were down at startup are reconnected in the background
+        #[cfg(unix)]
+        spawn_until_shutdown(
+            shutdown_rx.clone(),
+            reload_on_sighup(profile.clone(), chain_filter, config.clone(), supervisor, engine.clone()),
+        );
+        #[cfg(not(unix))]
+        {
+            drop(chain_filter);
+            spawn_until_shutdown(shutdown_rx.clone(), async move {
+                loop {
+                    supervisor.retry_due().await;
+                    supervisor.retry_down().await;
+                }
+            });
         }
     }
 
-    // 4. Simulation Mode (Chaos Monkey for Demo)
-    if args.contains(&"--simulate".to_string()) {
+    // 4. Simulation Mode (Chaos Monkey for Demo); it never ends, so not with --once
+    if args.contains(&"--simulate".to_string()) && once {
+        warn!("--simulate is ignored with --once");
+    } else if args.contains(&"--simulate".to_string()) {
         info!("🚀 SIMULATION MODE ACTIVE: Chaos Monkey Enabled 🚀");
         let tx_sim = tx.clone();
         
-        tokio::spawn(async move {
+        spawn_until_shutdown(shutdown_rx.clone(), async move {
             use rand::Rng; // trait for random_range
             
             loop {
@@ -207,6 +338,7 @@ async fn main() -> Result<()> {
                     chain_id: 1,
                     chain_name: "Simulation".to_string(),
                     contract_address: Address::ZERO,
+                    contract_name: "SIM".to_string(),
                     tx_hash: Default::default(),
                     block_number: 1000,
                     event_type,
@@ -216,7 +348,12 @@ async fn main() -> Result<()> {
                         "from": "0x000000000000000000000000000000000000dead",
                         "to": "0x000000000000000000000000000000000000beef",
                     }),
+                    removed: false,
+                    decimals: Some(6), // Demo values are USDT-sized
+                    historical: false,
+                    span: crate::events::NormalizedEvent::new_span(1, Default::default()),
                 };
+                mock_event.span.record("event_type", mock_event.event_type.name());
                 
                 if let Err(e) = tx_sim.send(mock_event).await {
                      error!("Simulation failed: {}", e);
@@ -225,30 +362,845 @@ async fn main() -> Result<()> {
         });
     }
 
+    // Only the producer tasks hold senders from here on
+    drop(tx);
+
     // Task C: Orchestrator (Consumer - now Background)
     info!("Watchdog Active. Waiting for events...");
-    let state_consumer = state.clone();
-    tokio::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            info!("Processing event: {:?}", event.event_type);
-            
-            let alerts = engine.process(&event);
-            for (msg, severity) in alerts {
-                info!("RISK LEVEL {:?}: {}", severity, msg);
-                
-                // Record state
-                state_consumer.add_alert(severity.clone(), event.chain_name.clone(), msg.clone());
-                
-                alert_manager.send_alert(severity, msg).await;
+    let orchestrator = Orchestrator {
+        state: state.clone(),
+        alerts: alert_manager.clone(),
+        heartbeats: heartbeats.clone(),
+        engine: engine.clone(),
+        auto_mute: config.rules.auto_mute.clone(),
+        templates: MessageTemplates::from_config(&config.alerts).map_err(|e| eyre::eyre!(e))?,
+        contract_names: contract_names(&config),
+    };
+    let mut consumer = Some(tokio::spawn(orchestrator.run(rx)));
+
+    // Task E: Heartbeat Checker (fires when expected events go missing)
+    if !heartbeats.is_empty() {
+        let state_heartbeat = state.clone();
+        let alerts_heartbeat = alert_manager.clone();
+        spawn_until_shutdown(shutdown_rx.clone(), async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                ticker.tick().await;
+                for (key, chain, msg, severity) in heartbeats.overdue(std::time::Instant::now()) {
+                    info!("RISK LEVEL {:?}: {}", severity, msg);
+                    let ctx = AlertContext::for_chain(&chain);
+                    state_heartbeat.raise_condition(&key, severity.clone(), chain, msg.clone());
+                    alerts_heartbeat.send_alert(severity, msg, &ctx).await;
+                }
+            }
+        });
+    }
+    
+    // Task E2: Stale chains (no fresh block for a few block times) and down
+    // chains (RPC unreachable, retrying), each resolved once it recovers
+    let (state_stale, alerts_stale) = (state.clone(), alert_manager.clone());
+    let stale_severity = parse_severity(&config.alerts.stale_chain_severity);
+    spawn_until_shutdown(shutdown_rx.clone(), async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            ticker.tick().await;
+            let mut transitions = health::check_stale_chains(&state_stale, std::time::Instant::now(), &stale_severity);
+            transitions.extend(health::check_down_chains(&state_stale, &stale_severity));
+            for (chain, msg) in transitions {
+                warn!("{}", msg);
+                alerts_stale.send_alert(stale_severity.clone(), msg, &AlertContext::for_chain(&chain)).await;
             }
         }
     });
-    
+
+    // Task H: Critical repeat summaries ("N more occurrences") per cooldown
+    // window, and the digest of alerts held during quiet hours
+    let alerts_summary = alert_manager.clone();
+    let state_summary = state.clone();
+    spawn_until_shutdown(shutdown_rx.clone(), async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            ticker.tick().await;
+            alerts_summary.flush_suppressed(|severity, chain, msg| state_summary.is_acknowledged(severity, chain, msg)).await;
+            alerts_summary.flush_quiet_digest().await;
+        }
+    });
+
+    // Task J: Periodic digest (alert counts by severity, busiest rules)
+    if let Some(digest) = config.alerts.digest.clone().filter(|d| d.enabled) {
+        let (state_digest, alerts_digest) = (state.clone(), alert_manager.clone());
+        let (period, severity) = (Duration::from_secs(digest.interval_secs), parse_severity(&digest.severity));
+        spawn_until_shutdown(shutdown_rx.clone(), async move {
+            let mut previous = Activity::default();
+            let mut ticker = tokio::time::interval(period);
+            ticker.tick().await; // The first tick is immediate
+            loop {
+                ticker.tick().await;
+                let now = Activity::snapshot(&state_digest);
+                let activity = if digest.reset { now.since(&previous) } else { now.clone() };
+                previous = now;
+                if activity.total() == 0 {
+                    continue;
+                }
+                let message = activity.message(digest.reset.then_some(period), digest.top_rules);
+                alerts_digest.send_digest(severity.clone(), &message).await;
+            }
+        });
+    }
+
+    // Task F: Alert Replay (re-dispatch recorded alerts on request from the TUI)
+    let (replay_tx, mut replay_rx) = mpsc::unbounded_channel::<usize>();
+    let state_replay = state.clone();
+    let alerts_replay = alert_manager.clone();
+    tokio::spawn(async move {
+        while let Some(n) = replay_rx.recv().await {
+            alerts_replay.replay_recent(&state_replay, n).await;
+        }
+    });
+
+    // Task G: Prometheus metrics endpoint
+    if config.metrics.enabled {
+        let state_metrics = state.clone();
+        let port = config.metrics.port;
+        tokio::spawn(async move {
+            if let Err(e) = crate::metrics::serve(state_metrics, port).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        });
+    }
+
+    // Task I: Liveness/readiness probes
+    if config.health.enabled {
+        let state_health = state.clone();
+        let (port, max_age) = (config.health.port, config.health.max_block_age_secs.map(Duration::from_secs));
+        tokio::spawn(async move {
+            if let Err(e) = crate::health::serve(state_health, port, max_age).await {
+                error!("Health endpoint failed: {}", e);
+            }
+        });
+    }
+
+    // Task K: Telegram Acknowledge buttons (bot updates -> TUI acknowledgements)
+    let telegram = (config.alerts.telegram_bot_token.clone(), config.alerts.telegram_chat_id.clone());
+    if let (Some(acks), (Some(token), Some(chat_id))) = (alert_manager.telegram_acks(), telegram) {
+        if !token.is_empty() && !chat_id.is_empty() && !config.alerts.dry_run && !once {
+            spawn_until_shutdown(shutdown_rx.clone(), poll_acks(reqwest::Client::new(), token, chat_id, acks, state.clone()));
+        }
+    }
+
     // Task D: TUI (Main Thread)
-    // Runs blocking on main thread
-    if let Err(e) = crate::tui::run_tui(state) {
+    // --once waits for its pass to drain through rules and channels, then
+    // prints a JSON summary; --render-once prints a single plain-text frame
+    // instead (no raw mode, no TTY needed)
+    let mut exit_code = 0;
+    let history = state.clone();
+    if once {
+        if let Some(consumer) = consumer.take() {
+            if let Err(e) = consumer.await {
+                error!("Event processing failed: {}", e);
+            }
+        }
+        let summary = crate::once::RunSummary::from_state(&state);
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        exit_code = summary.exit_code();
+        if let Some(path) = &export_path {
+            if let Err(e) = crate::export::export_csv(std::path::Path::new(path), &state.history_records()) {
+                error!("CSV export failed: {}", e);
+                exit_code = 1;
+            }
+        }
+    } else if render_once {
+        // Give listeners / the simulator a moment to populate state
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        match crate::tui::render_once(&state, 120, 40) {
+            Ok(frame) => println!("{}", frame),
+            Err(e) => eprintln!("Render Error: {}", e),
+        }
+    } else if let Err(e) = crate::tui::run_tui(state, replay_tx, shutdown_rx) {
+        // Runs blocking on main thread
         eprintln!("TUI Error: {}", e);
     }
 
+    // Graceful shutdown: stop producers, then let the orchestrator finish
+    // the queued events (and the alerts they trigger)
+    info!("Shutting down, draining pending events...");
+    shutdown_tx.send_replace(true);
+    if let Some(consumer) = consumer {
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, consumer).await {
+            Ok(_) => info!("All pending events processed"),
+            Err(_) => warn!("Shutdown timed out after {:?} with events still pending", SHUTDOWN_TIMEOUT),
+        }
+    }
+    // Batched channels would otherwise drop what is still in their window
+    alert_manager.flush().await;
+    // Write out alert history still queued for the file
+    let _ = tokio::task::spawn_blocking(move || history.close_history()).await;
+    // Flush spans still queued for the collector (blocks on the exporter)
+    if let Some(provider) = tracer_provider {
+        if let Ok(Err(e)) = tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            warn!("OTLP trace export shutdown failed: {}", e);
+        }
+    }
+
+    if exit_code != 0 {
+        // process::exit skips destructors; flush the log writer first
+        drop(_guard);
+        std::process::exit(exit_code);
+    }
     Ok(())
 }
+
+/// The consumer side of the event queue: runs each event through the rules
+/// and hands the resulting alerts to the channels. Returns once every
+/// producer has dropped its sender and the queue is drained.
+struct Orchestrator {
+    state: Arc<AppState>,
+    alerts: Arc<AlertManager>,
+    heartbeats: Arc<HeartbeatMonitor>,
+    engine: Arc<RwLock<Arc<RuleEngine>>>,
+    auto_mute: Option<AutoMuteConfig>,
+    templates: MessageTemplates,
+    contract_names: HashMap<(String, Address), String>,
+}
+
+impl Orchestrator {
+    async fn run(self, mut rx: mpsc::Receiver<NormalizedEvent>) {
+        while let Some(mut event) = rx.recv().await {
+            // Replayed recordings may predate contract names; look them up
+            if event.contract_name.is_empty() {
+                if let Some(name) = self.contract_names.get(&(event.chain_name.clone(), event.contract_address)) {
+                    event.contract_name = name.clone();
+                }
+            }
+            // Token metadata fills in decimals the config left out and names the contract
+            let token = self.state.token_metadata(&event.chain_name, event.contract_address);
+            if event.decimals.is_none() {
+                event.decimals = token.as_ref().and_then(|t| t.decimals);
+            }
+            let ctx = AlertContext::from_event(&event).with_token(token);
+
+            // Reorg retraction: annotate what the removed log raised, no rules
+            if event.removed {
+                info!("Log removed by reorg: {:?} in tx {}", event.event_type, event.tx_hash);
+                for (severity, msg) in self.state.retract_tx(event.tx_hash) {
+                    let notice = format!("RETRACTED (reorg): {}", msg);
+                    self.alerts.send_alert(severity, notice, &ctx).await;
+                }
+                continue;
+            }
+
+            info!(chain_name = %event.chain_name, event_type = event.event_type.name(), "Processing event: {:?}", event.event_type);
+            self.state.record_event(&event.chain_name);
+            for (key, chain, msg, severity) in self.heartbeats.observe(&event) {
+                info!("{}", msg);
+                if self.state.resolve_condition(&key) {
+                    self.alerts.send_alert(severity, msg, &AlertContext::for_chain(&chain)).await;
+                }
+            }
+        
+            let rules = self.engine.read().unwrap_or_else(|e| e.into_inner()).clone();
+            let alerts = info_span!(parent: &event.span, "rules").in_scope(|| rules.process(&event, &self.state));
+            for (rule, msg, severity) in alerts {
+                // Backfilled alerts are labelled so they aren't mistaken for live ones
+                let msg = if event.historical { format!("[BACKFILL] {}", msg) } else { msg };
+                info!(
+                    chain_name = %event.chain_name,
+                    event_type = event.event_type.name(),
+                    severity = ?severity,
+                    rule,
+                    tx_hash = %event.tx_hash,
+                    "RISK LEVEL {:?}: {}", severity, msg
+                );
+            
+                // Record state
+                self.state.add_alert(severity.clone(), event.chain_name.clone(), msg.clone(), Some(AlertOrigin::from(&event)));
+            
+                // Below-threshold alerts stop here, before any channel sees them
+                if !self.alerts.sends(&severity) {
+                    continue;
+                }
+                // A rule firing too often is muted for a while (Critical always goes out)
+                if let Some(mute) = self.auto_mute.as_ref().filter(|_| severity < Severity::Critical) {
+                    let window = Duration::from_secs(mute.window_secs);
+                    let cooldown = Duration::from_secs(mute.cooldown_secs);
+                    let decision = self.state.gate_rule_alert(rule, mute.max_hits, window, cooldown);
+                    let notice = match decision {
+                        MuteDecision::Forward => None,
+                        MuteDecision::Suppressed => continue,
+                        MuteDecision::Muted => Some(format!(
+                            "Rule {} auto-muted: over {} alerts in {}s, suppressing it for {}s",
+                            rule, mute.max_hits, mute.window_secs, mute.cooldown_secs
+                        )),
+                        MuteDecision::Unmuted(suppressed) => Some(format!("Rule {} unmuted, {} alert(s) suppressed while muted", rule, suppressed)),
+                    };
+                    // Sent at High whatever the rule's severity, past every gate
+                    if let Some(notice) = notice {
+                        warn!("{}", notice);
+                        self.state.add_alert(Severity::High, event.chain_name.clone(), notice.clone(), None);
+                        self.alerts.send_notice(Severity::High, &notice, &AlertContext::for_chain(&event.chain_name)).await;
+                    }
+                    if decision == MuteDecision::Muted {
+                        continue;
+                    }
+                }
+                let ctx = ctx.clone().with_ack_message(msg.clone());
+                let msg = self.templates.apply(rule, msg, &severity, &event);
+                let dispatch = info_span!(parent: &event.span, "dispatch", rule, severity = ?severity);
+                self.alerts.send_alert(severity, msg, &ctx).instrument(dispatch).await;
+            }
+        }
+    }
+}
+
+/// (Chain, Address) -> `[[contracts]]` name, joined with "/" when several
+/// configs watch the same address.
+fn contract_names(config: &AppConfig) -> HashMap<(String, Address), String> {
+    let mut names: HashMap<(String, Address), String> = HashMap::new();
+    for contract in &config.contracts {
+        names.entry((contract.chain.clone(), contract.address))
+            .and_modify(|name| *name = format!("{}/{}", name, contract.name))
+            .or_insert_with(|| contract.name.clone());
+    }
+    names
+}
+
+/// Builds the rule set from `[rules]`. Stateful rules (e.g. velocity windows)
+/// start empty.
+fn build_engine(config: &AppConfig) -> Result<RuleEngine> {
+    let mut engine = RuleEngine::new()
+        .with_parallel(config.rules.parallel)
+        .with_stop_on_first(config.rules.stop_on_first);
+
+    // Configure Rules from Config
+    let transfer_severity = parse_severity(&config.rules.transfer_threshold.severity);
+    let ownership_severity = parse_severity(&config.rules.ownership_change.severity);
+    let threshold = match config.rules.transfer_threshold.threshold_unit {
+        ThresholdUnit::Raw => Threshold::Raw(config.rules.transfer_threshold.min_value.parse().unwrap_or(U256::from(1000))),
+        ThresholdUnit::Token => Threshold::Tokens(config.rules.transfer_threshold.min_value.clone()),
+    };
+
+    if config.rules.transfer_threshold.enabled {
+        engine.add_rule(Box::new(ThresholdRule::new(threshold, transfer_severity)));
+    }
+    
+    if config.rules.ownership_change.enabled {
+        engine.add_rule(Box::new(OwnershipRule::new(ownership_severity)));
+    }
+    if let Some(ping_pong) = &config.rules.ownership_ping_pong {
+        engine.add_rule(Box::new(OwnershipPingPongRule::new(
+            ping_pong.max_changes,
+            Duration::from_secs(ping_pong.window_secs),
+            parse_severity(&ping_pong.severity),
+        )));
+    }
+
+    // High Approval Rule (Infinite Allowance Detection, > 50% of uint256 max by default)
+    let approval = &config.rules.high_approval;
+    if approval.enabled {
+        engine.add_rule(Box::new(HighApprovalRule::new(
+            approval.threshold().unwrap_or(U256::MAX >> 1),
+            parse_severity(&approval.severity),
+        )));
+    }
+
+    // Formats base-fee spikes; only chains with `gas_spike` configured emit them
+    if config.chains.values().any(|chain| chain.gas_spike.is_some()) {
+        engine.add_rule(Box::new(GasSpikeRule));
+    }
+
+    if let Some(velocity) = &config.rules.velocity {
+        engine.add_rule(Box::new(VelocityRule::new(
+            velocity.max_transfers,
+            Duration::from_secs(velocity.window_secs),
+            parse_severity(&velocity.severity),
+        )));
+    }
+    if let Some(fan_out) = &config.rules.fan_out {
+        engine.add_rule(Box::new(FanOutRule::new(
+            fan_out.max_recipients,
+            Duration::from_secs(fan_out.window_secs),
+            parse_severity(&fan_out.severity),
+        )));
+    }
+    if let Some(watchlist) = &config.rules.watchlist {
+        engine.add_rule(Box::new(WatchlistTransferRule::new(
+            watchlist.addresses.iter().copied().collect(),
+            parse_severity(&watchlist.severity),
+        )));
+    }
+    if let Some(nft) = &config.rules.nft_transfer {
+        engine.add_rule(Box::new(NftTransferRule::new(
+            nft.collections.iter().copied().collect(),
+            nft.token_ids.iter().filter_map(|id| id.parse().ok()).collect(),
+            parse_severity(&nft.severity),
+        )));
+    }
+
+    if let Some(mint_burn) = &config.rules.mint_burn {
+        let direction = match mint_burn.direction.as_str() {
+            "mint" => MintBurnDirection::Mint,
+            "burn" => MintBurnDirection::Burn,
+            _ => MintBurnDirection::Both,
+        };
+        engine.add_rule(Box::new(MintBurnRule::new(
+            direction,
+            mint_burn.min_value.parse().unwrap_or(U256::ZERO),
+            parse_severity(&mint_burn.severity),
+        )));
+    }
+
+    if let Some(supply) = &config.rules.supply_change {
+        engine.add_rule(Box::new(SupplyChangeRule::new(supply.max_change_pct, parse_severity(&supply.severity))));
+    }
+
+    if let Some(large_swap) = &config.rules.large_swap {
+        let bound = |b: &Option<String>| b.as_deref().and_then(|s| s.parse::<U256>().ok());
+        engine.add_rule(Box::new(LargeSwapRule::new(
+            bound(&large_swap.min_amount0),
+            bound(&large_swap.min_amount1),
+            parse_severity(&large_swap.severity),
+        )));
+    }
+
+    if let Some(signed) = &config.rules.signed_threshold {
+        engine.add_rule(Box::new(SignedThresholdRule::new(
+            signed.event.clone(),
+            signed.field.clone(),
+            signed.min_magnitude.parse().unwrap_or(U256::ZERO),
+            parse_severity(&signed.severity),
+        )));
+    }
+
+    if let Some(blacklist) = &config.rules.blacklist {
+        let rule = BlacklistRule::from_file(std::path::Path::new(&blacklist.file))?;
+        info!("Loaded {} blacklisted address(es) from {}", rule.addresses.len(), blacklist.file);
+        engine.add_rule(Box::new(rule));
+    }
+    if let Some(drainers) = &config.rules.drainer_spenders {
+        let spenders = load_address_list(std::path::Path::new(&drainers.file))?;
+        info!("Loaded {} known drainer spender(s) from {}", spenders.len(), drainers.file);
+        let min_value = drainers.min_value.parse().unwrap_or(U256::from(1));
+        engine.add_rule(Box::new(DrainerApprovalRule::new(spenders, min_value)));
+    }
+    if let Some(drain) = &config.rules.drain_pattern {
+        engine.add_rule(Box::new(DrainPatternRule::new(Duration::from_secs(drain.window_secs))));
+    }
+
+    for (name, contracts) in &config.rules.scope {
+        if !engine.scope_rule(name, contracts.iter().copied().collect()) {
+            eyre::bail!("rules.scope.{}: no such active rule (active: {})", name, engine.rule_names().join(", "));
+        }
+    }
+    for (name, priority) in &config.rules.priority {
+        if !engine.prioritize_rule(name, *priority) {
+            eyre::bail!("rules.priority.{}: no such active rule (active: {})", name, engine.rule_names().join(", "));
+        }
+    }
+    Ok(engine)
+}
+
+/// Loads `config[.profile].toml` with env overrides applied and validates
+/// it, returning every problem found.
+fn load_config(profile: Option<&str>) -> std::result::Result<AppConfig, Vec<String>> {
+    let mut config = AppConfig::new(profile).map_err(|e| vec![e.to_string()])?;
+
+    // Allow .env override for RPC_URL (Legacy support / easy setup)
+    if let Ok(env_rpc) = std::env::var("RPC_URL") {
+        if let Some(chain_cfg) = config.chains.get_mut("ethereum") {
+            chain_cfg.rpc_url = env_rpc;
+        }
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+/// Reloads the config on each SIGHUP: rules are swapped atomically, and
+/// added/removed/changed chains and contracts are started or stopped. A
+/// config that fails to load or validate is rejected and the old one kept.
+/// Alert channels, heartbeats, persistence and metrics need a restart.
+/// Between signals, chains that failed to connect are retried.
+#[cfg(unix)]
+async fn reload_on_sighup(
+    profile: Option<String>,
+    chain_filter: Option<Vec<String>>,
+    mut current: AppConfig,
+    mut supervisor: Supervisor,
+    engine: Arc<RwLock<Arc<RuleEngine>>>,
+) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Cannot listen for SIGHUP, config reload disabled: {}", e);
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            signal = sighup.recv() => if signal.is_none() { break },
+            _ = supervisor.retry_due() => {
+                supervisor.retry_down().await;
+                continue;
+            }
+        }
+        info!("SIGHUP received, reloading configuration");
+        let mut new = match load_config(profile.as_deref()) {
+            Ok(config) => config,
+            Err(problems) => {
+                for problem in &problems {
+                    error!("Config: {}", problem);
+                }
+                warn!("Reload rejected ({} problem(s)), keeping the running config", problems.len());
+                continue;
+            }
+        };
+        if let Some(names) = &chain_filter {
+            new.chains.retain(|k, _| names.contains(k));
+        }
+        let new_engine = match build_engine(&new) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Reload rejected, keeping the running config: {}", e);
+                continue;
+            }
+        };
+        match supervisor.apply(&current, &new).await {
+            Ok(diff) if diff.is_empty() => info!("No chain or contract changes"),
+            Ok(diff) => info!(
+                "Reload applied: chains +{:?} -{:?}, contracts +{:?} -{:?}",
+                diff.added_chains, diff.removed_chains, diff.added_contracts, diff.removed_contracts
+            ),
+            Err(e) => {
+                warn!("Reload rejected, keeping the running config: {}", e);
+                continue;
+            }
+        }
+        info!("Active rules: {}", new_engine.rule_names().join(", "));
+        *engine.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(new_engine);
+        current = new;
+    }
+}
+
+/// Spawns `task`, dropping it (and any channel sender it owns) once
+/// shutdown is signalled.
+fn spawn_until_shutdown<F>(mut shutdown: watch::Receiver<bool>, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = task => {}
+            _ = shutdown.wait_for(|stop| *stop) => {}
+        }
+    });
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix (e.g. `docker stop`, systemd).
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[derive(Debug)]
+enum ChainSelection {
+    All,
+    Only(Vec<String>),
+    Unspecified,
+}
+
+/// Maps `--chain <name>` (repeatable) and `--all` to the chains to monitor.
+/// `--all` wins over any `--chain`.
+fn chain_selection(args: &[String]) -> ChainSelection {
+    if args.iter().any(|a| a == "--all") {
+        return ChainSelection::All;
+    }
+    let names: Vec<String> = args.windows(2)
+        .filter(|pair| pair[0] == "--chain")
+        .map(|pair| pair[1].clone())
+        .collect();
+    if names.is_empty() {
+        ChainSelection::Unspecified
+    } else {
+        ChainSelection::Only(names)
+    }
+}
+
+/// Returns the value following `flag` (e.g. `--profile prod`), if present.
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// Maps a config severity string to `Severity`, defaulting to Low.
+fn parse_severity(s: &str) -> Severity {
+    Severity::from_name(s).unwrap_or(Severity::Low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sinks::RecordingSink;
+    use crate::config::AlertsConfig;
+    use crate::events::EventType;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn chain_flags_select_the_retained_chains() {
+        let only = |line: &str| match chain_selection(&args(line)) {
+            ChainSelection::Only(names) => names,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(only("watchdog --chain ethereum"), ["ethereum"]);
+        assert_eq!(only("watchdog --chain ethereum --dry-run --chain polygon"), ["ethereum", "polygon"]);
+        assert!(matches!(chain_selection(&args("watchdog --chain ethereum --all")), ChainSelection::All));
+        assert!(matches!(chain_selection(&args("watchdog --once")), ChainSelection::Unspecified));
+        // A trailing flag without a name selects nothing
+        assert!(matches!(chain_selection(&args("watchdog --chain")), ChainSelection::Unspecified));
+    }
+
+    /// A config with one USDT contract and `rules` appended to its
+    /// `[rules.*]` tables.
+    fn config(rules: &str) -> AppConfig {
+        AppConfig::from_toml(&format!(
+            r#"
+[chains]
+ethereum = {{ rpc_url = "wss://eth.example", chain_id = 1 }}
+
+[[contracts]]
+name = "USDT"
+address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+chain = "ethereum"
+events = ["Transfer", "Approval"]
+
+[alerts]
+webhook_url = ""
+{}"#,
+            rules
+        ))
+    }
+
+    fn engine(rules: &str) -> RuleEngine {
+        build_engine(&config(rules)).unwrap()
+    }
+
+    #[test]
+    fn configured_contracts_name_their_events() {
+        let contracts = r#"
+[chains]
+ethereum = { rpc_url = "wss://eth.example", chain_id = 1 }
+polygon = { rpc_url = "wss://polygon.example", chain_id = 137 }
+
+[[contracts]]
+name = "USDT"
+address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+chain = "ethereum"
+events = ["Transfer"]
+
+[[contracts]]
+name = "USDT-proxy"
+address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+chain = "ethereum"
+events = ["Approval"]
+
+[[contracts]]
+name = "USDC"
+address = "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359"
+chain = "polygon"
+events = ["Transfer"]
+
+[alerts]
+webhook_url = ""
+"#;
+        let config = AppConfig::from_toml(&format!("{}{}", contracts, BUILT_IN));
+        let names = contract_names(&config);
+        let usdt = "0xdAC17F958D2ee523a2206206994597C13D831ec7".parse().unwrap();
+        assert_eq!(names[&("ethereum".to_string(), usdt)], "USDT/USDT-proxy");
+        // The same address on another chain is a different contract
+        assert!(!names.contains_key(&("polygon".to_string(), usdt)));
+
+        let event = NormalizedEvent { contract_name: names[&("ethereum".to_string(), usdt)].clone(), ..NormalizedEvent::sample(EventType::Transfer, serde_json::json!({})) };
+        assert_eq!(event.contract_label(), "USDT/USDT-proxy (0xdAC17F958D2ee523a2206206994597C13D831ec7)");
+        let unnamed = NormalizedEvent { contract_name: String::new(), ..event };
+        assert_eq!(unnamed.contract_label(), "0xdAC17F958D2ee523a2206206994597C13D831ec7");
+    }
+
+    const BUILT_IN: &str = r#"
+[rules.transfer_threshold]
+min_value = "1000"
+severity = "Medium"
+[rules.ownership_change]
+severity = "High"
+"#;
+
+    #[test]
+    fn disabled_built_in_rules_are_not_registered() {
+        let names = engine(BUILT_IN).rule_names();
+        assert_eq!(names, ["ThresholdRule", "OwnershipRule", "HighApprovalRule"]);
+
+        // GasSpikeRule comes with a chain's `gas_spike` table
+        let mut spiking = config(BUILT_IN);
+        spiking.chains.get_mut("ethereum").unwrap().gas_spike = Some(crate::config::GasSpikeConfig {
+            multiplier: 3.0,
+            window_blocks: 20,
+            severity: "High".to_string(),
+        });
+        assert_eq!(build_engine(&spiking).unwrap().rule_names(), ["ThresholdRule", "OwnershipRule", "HighApprovalRule", "GasSpikeRule"]);
+
+        let disabled = engine(
+            r#"
+[rules.transfer_threshold]
+enabled = false
+min_value = "1000"
+severity = "Medium"
+[rules.ownership_change]
+severity = "High"
+[rules.high_approval]
+enabled = false
+"#,
+        );
+        assert_eq!(disabled.rule_names(), ["OwnershipRule"]);
+    }
+
+    #[test]
+    fn threshold_unit_picks_raw_or_token_amounts() {
+        let threshold = |engine: RuleEngine| engine.describe()[0].1.clone();
+        assert_eq!(threshold(engine(BUILT_IN)), "Transfer value >= 1000 (Medium)", "raw by default");
+        let tokens = BUILT_IN.replace("min_value = \"1000\"", "min_value = \"1000\"\nthreshold_unit = \"token\"");
+        assert_eq!(threshold(engine(&tokens)), "Transfer value >= 1000 tokens (Medium)");
+    }
+
+    /// An orchestrator running `engine` over a fresh state, alerting through
+    /// the `[alerts]` settings in `alerts` to the returned sink.
+    fn orchestrator(engine: RuleEngine, alerts: &str, auto_mute: Option<AutoMuteConfig>) -> (Orchestrator, RecordingSink) {
+        let sink = RecordingSink::default();
+        let config = AlertsConfig::from_toml(&format!("webhook_url = \"\"\n{}", alerts));
+        let orchestrator = Orchestrator {
+            state: Arc::new(AppState::new()),
+            alerts: Arc::new(AlertManager::with_sinks(config.clone(), vec![Box::new(sink.clone())])),
+            heartbeats: Arc::new(HeartbeatMonitor::new(Vec::new())),
+            engine: Arc::new(RwLock::new(Arc::new(engine))),
+            auto_mute,
+            templates: MessageTemplates::from_config(&config).unwrap(),
+            contract_names: HashMap::new(),
+        };
+        (orchestrator, sink)
+    }
+
+    /// A USDT Transfer of `1000 + n` base units in its own transaction.
+    fn transfer(n: u8) -> NormalizedEvent {
+        NormalizedEvent {
+            tx_hash: alloy::primitives::B256::repeat_byte(n),
+            ..NormalizedEvent::sample(EventType::Transfer, serde_json::json!({ "value": (1000 + u64::from(n)).to_string() }))
+        }
+    }
+
+    fn threshold_engine(severity: Severity) -> RuleEngine {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), severity)));
+        engine
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_queued_events() {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (tx, rx) = mpsc::channel::<NormalizedEvent>(10);
+        let (orchestrator, sink) = orchestrator(threshold_engine(Severity::Medium), "", None);
+        let state = orchestrator.state.clone();
+
+        // A listener that queued three events and would otherwise run forever
+        spawn_until_shutdown(shutdown_rx.clone(), async move {
+            for n in 1..=3 {
+                tx.send(transfer(n)).await.unwrap();
+            }
+            std::future::pending::<()>().await
+        });
+        let consumer = tokio::spawn(orchestrator.run(rx));
+
+        tokio::task::yield_now().await;
+        shutdown_tx.send_replace(true);
+        tokio::time::timeout(SHUTDOWN_TIMEOUT, consumer).await.expect("consumer exits once producers stop").unwrap();
+        assert_eq!(state.events_processed.lock().unwrap().get("ethereum"), Some(&3));
+        // Each queued event went all the way through rules and channels
+        assert_eq!(state.alert_history.lock().unwrap().len(), 3);
+        assert_eq!(sink.messages().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn auto_mute_notices_get_past_min_severity_and_quiet_hours() {
+        // Quiet hours covering the current minute, holding everything below Critical
+        let minute = (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() % 86_400) / 60;
+        let at = |m: u64| format!("{:02}:{:02}", (m % 1440) / 60, m % 60);
+        let alerts = format!(
+            "min_severity = \"High\"\nquiet_hours = {{ start = \"{}\", end = \"{}\", bypass_severity = \"Critical\" }}",
+            at(minute + 1440 - 10),
+            at(minute + 10)
+        );
+        let mute = AutoMuteConfig { max_hits: 1, window_secs: 60, cooldown_secs: 60 };
+        let (orchestrator, sink) = orchestrator(threshold_engine(Severity::High), &alerts, Some(mute));
+        let state = orchestrator.state.clone();
+
+        let (tx, rx) = mpsc::channel(2);
+        tx.send(transfer(1)).await.unwrap();
+        tx.send(transfer(2)).await.unwrap();
+        drop(tx);
+        orchestrator.run(rx).await;
+
+        // The first alert is held for the quiet-hours digest; the notice isn't
+        let delivered = sink.delivered();
+        assert_eq!(delivered.len(), 1, "{:?}", sink.messages());
+        assert_eq!(delivered[0].0, Severity::High);
+        assert!(delivered[0].1.contains("auto-muted"), "{}", delivered[0].1);
+        let history = state.alert_history.lock().unwrap();
+        assert!(history.iter().any(|e| e.message.contains("auto-muted") && e.severity == Severity::High));
+    }
+
+    #[tokio::test]
+    async fn dry_run_records_alerts_without_sending() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(wiremock::ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let config = AlertsConfig::from_toml(&format!(
+            "webhook_url = \"{0}/discord\"\ngeneric_webhook_url = \"{0}/hook\"\nslack_webhook_url = \"{0}/slack\"\ndry_run = true",
+            server.uri()
+        ));
+        let (orchestrator, _) = orchestrator(threshold_engine(Severity::High), "", None);
+        let orchestrator = Orchestrator { alerts: Arc::new(AlertManager::new(config, HashMap::new())), ..orchestrator };
+        let state = orchestrator.state.clone();
+
+        let (tx, rx) = mpsc::channel(1);
+        tx.send(transfer(1)).await.unwrap();
+        drop(tx);
+        orchestrator.run(rx).await;
+
+        server.verify().await;
+        let history = state.alert_history.lock().unwrap();
+        assert_eq!(history.len(), 1, "the dashboard still shows it");
+        assert_eq!(history[0].severity, Severity::High);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Write as _;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use eyre::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+use crate::events::Severity;
+use crate::state::AppState;
+
+const SEVERITIES: [Severity; 4] = [Severity::Low, Severity::Medium, Severity::High, Severity::Critical];
+
+/// Serves `GET /metrics` in Prometheus text format on `0.0.0.0:port`.
+/// Deliberately minimal: one request per connection, anything else is a 404.
+pub async fn serve(state: Arc<AppState>, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Metrics endpoint listening on :{}/metrics", port);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &state).await {
+                warn!("Metrics request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: &AppState) -> Result<()> {
+    // The request line is all we route on; headers and body are ignored
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, body) = if request.starts_with("GET ") && path == "/metrics" {
+        ("200 OK", render(state))
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// Current metrics snapshot in Prometheus exposition format.
+pub fn render(state: &AppState) -> String {
+    let mut out = String::new();
+
+    out.push_str("# HELP watchdog_events_processed_total Events handed to the rule engine.\n");
+    out.push_str("# TYPE watchdog_events_processed_total counter\n");
+    if let Ok(processed) = state.events_processed.lock() {
+        let mut chains: Vec<_> = processed.iter().collect();
+        chains.sort();
+        for (chain, count) in chains {
+            let _ = writeln!(out, "watchdog_events_processed_total{{chain=\"{}\"}} {}", chain, count);
+        }
+    }
+
+    out.push_str("# HELP watchdog_alerts_total Alerts raised, by severity.\n");
+    out.push_str("# TYPE watchdog_alerts_total counter\n");
+    if let Ok(counts) = state.severity_counts.lock() {
+        for severity in &SEVERITIES {
+            let count = counts.get(severity).copied().unwrap_or(0);
+            let _ = writeln!(out, "watchdog_alerts_total{{severity=\"{:?}\"}} {}", severity, count);
+        }
+    }
+
+    out.push_str("# HELP watchdog_rpc_in_flight RPC requests currently executing, per chain.\n");
+    out.push_str("# TYPE watchdog_rpc_in_flight gauge\n");
+    for (chain, in_flight) in state.rpc_in_flight() {
+        let _ = writeln!(out, "watchdog_rpc_in_flight{{chain=\"{}\"}} {}", chain, in_flight);
+    }
+
+    out.push_str("# HELP watchdog_block_height Latest block seen, per chain.\n");
+    out.push_str("# TYPE watchdog_block_height gauge\n");
+    if let Ok(heights) = state.chain_heights.lock() {
+        let mut chains: Vec<_> = heights.iter().collect();
+        chains.sort();
+        for (chain, height) in chains {
+            let _ = writeln!(out, "watchdog_block_height{{chain=\"{}\"}} {}", chain, height);
+        }
+    }
+
+    out.push_str("# HELP watchdog_reconnects_total Successful WebSocket reconnects.\n");
+    out.push_str("# TYPE watchdog_reconnects_total counter\n");
+    let _ = writeln!(out, "watchdog_reconnects_total {}", state.reconnects.load(Ordering::Relaxed));
+
+    out.push_str("# HELP watchdog_event_queue_depth Events waiting for the rule engine.\n");
+    out.push_str("# TYPE watchdog_event_queue_depth gauge\n");
+    let _ = writeln!(out, "watchdog_event_queue_depth {}", state.queue_depth.load(Ordering::Relaxed));
+
+    out.push_str("# HELP watchdog_rule_panics_total Rule checks that panicked and were skipped.\n");
+    out.push_str("# TYPE watchdog_rule_panics_total counter\n");
+    let _ = writeln!(out, "watchdog_rule_panics_total {}", state.rule_panics.load(Ordering::Relaxed));
+    out.push_str("# HELP watchdog_events_dropped_total Events dropped because the queue was full (drop_oldest policy).\n");
+    out.push_str("# TYPE watchdog_events_dropped_total counter\n");
+    let _ = writeln!(out, "watchdog_events_dropped_total {}", state.events_dropped.load(Ordering::Relaxed));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// GETs `path` from `handle` over a real socket; returns (status, body).
+    async fn get(state: Arc<AppState>, path: &str) -> (u16, String) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle(stream, &state).await.unwrap();
+        });
+        let resp = reqwest::get(url).await.unwrap();
+        (resp.status().as_u16(), resp.text().await.unwrap())
+    }
+
+    /// The value of the sample line `name{labels}`, if present.
+    fn sample(body: &str, series: &str) -> Option<f64> {
+        body.lines()
+            .filter(|line| !line.starts_with('#'))
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' ')?.parse().ok())
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_serves_counters() {
+        let state = Arc::new(AppState::new());
+        state.record_event("ethereum");
+        state.record_event("ethereum");
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), None);
+        state.update_block("ethereum", 19_000_000, None);
+
+        let (status, body) = get(state, "/metrics").await;
+        assert_eq!(status, 200);
+        assert_eq!(sample(&body, "watchdog_events_processed_total{chain=\"ethereum\"}"), Some(2.0));
+        assert_eq!(sample(&body, "watchdog_alerts_total{severity=\"High\"}"), Some(1.0));
+        assert_eq!(sample(&body, "watchdog_alerts_total{severity=\"Low\"}"), Some(0.0));
+        assert!(body.contains("# TYPE watchdog_alerts_total counter"));
+    }
+
+    #[tokio::test]
+    async fn other_paths_are_not_found() {
+        assert_eq!(get(Arc::new(AppState::new()), "/").await.0, 404);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use serde::Serialize;
+use tokio::sync::mpsc::Sender;
+use tracing::{error, info, warn};
+
+use crate::config::AppConfig;
+use crate::events::{NormalizedEvent, Severity};
+use crate::listener::rpc_limit::RpcLimiter;
+use crate::listener::{backfill_logs, connect_auto, AddressWatch, ChainProvider, RpcEndpoint};
+use crate::state::AppState;
+use crate::supervisor::contract_watches;
+use crate::tokens::snapshot_token;
+
+/// Exit status of a `--once` run that raised at least one Critical alert
+/// (plain errors exit with 1).
+pub const CRITICAL_EXIT_CODE: i32 = 2;
+
+/// A single pass over recent history for `--once`: each chain's contracts
+/// are backfilled over `backfill_blocks` (one `max_block_range` page when
+/// unset) and the events sent down `tx`, then the function returns and the
+/// sender is dropped so the pipeline drains. Chains that fail to connect are
+/// skipped with an error; they don't stop the others.
+pub async fn scan(config: &AppConfig, state: Arc<AppState>, tx: Sender<NormalizedEvent>) {
+    for (chain_name, chain_cfg) in &config.chains {
+        state.set_rpc_limiter(chain_name, RpcLimiter::new(chain_cfg.max_concurrent_requests));
+        let endpoint = RpcEndpoint { url: chain_cfg.rpc_url.clone(), auth_header: chain_cfg.auth_header.clone() };
+        let provider = match connect_auto(&endpoint).await {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Failed to connect to {}: {}", chain_name, e);
+                continue;
+            }
+        };
+        let contracts: Vec<_> = config.contracts.iter().filter(|c| &c.chain == chain_name).collect();
+        let watches = match contract_watches(chain_name, chain_cfg, &contracts) {
+            Ok(w) => w,
+            Err(e) => {
+                error!("Skipping {}: {}", chain_name, e);
+                continue;
+            }
+        };
+        let blocks = if chain_cfg.backfill_blocks > 0 { chain_cfg.backfill_blocks } else { chain_cfg.max_block_range };
+        for watch in AddressWatch::group(watches) {
+            // Metadata first, so alerts carry symbols and decimals
+            let result = match &provider {
+                ChainProvider::Ws(ws) => {
+                    snapshot_token(ws.clone(), state.clone(), chain_name.clone(), watch.address).await;
+                    backfill_logs(ws.as_ref(), &state, &watch, &tx, blocks, chain_cfg.max_block_range).await
+                }
+                ChainProvider::Http(http) => {
+                    snapshot_token(http.clone(), state.clone(), chain_name.clone(), watch.address).await;
+                    backfill_logs(http.as_ref(), &state, &watch, &tx, blocks, chain_cfg.max_block_range).await
+                }
+            };
+            match result {
+                Ok(head) => state.update_block(chain_name, head, None),
+                Err(e) => warn!("[{}] Scan of {} failed: {}", chain_name, watch.address, e),
+            }
+        }
+    }
+    info!("Single pass finished");
+}
+
+/// What a `--once` run saw, printed to stdout as JSON.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub events: u64,
+    pub events_by_chain: BTreeMap<String, u64>,
+    pub alerts: u64,
+    pub alerts_by_severity: BTreeMap<String, u64>,
+    pub rule_hits: BTreeMap<String, u64>,
+    pub critical: bool,
+}
+
+impl RunSummary {
+    pub fn from_state(state: &AppState) -> Self {
+        let events_by_chain: BTreeMap<String, u64> = state.events_processed.lock()
+            .map(|m| m.iter().map(|(k, v)| (k.clone(), *v)).collect())
+            .unwrap_or_default();
+        let alerts_by_severity: BTreeMap<String, u64> = state.severity_counts.lock()
+            .map(|m| m.iter().map(|(k, v)| (format!("{:?}", k), *v)).collect())
+            .unwrap_or_default();
+        let rule_hits = state.rule_hits.lock()
+            .map(|m| m.iter().map(|(k, v)| (k.clone(), *v)).collect())
+            .unwrap_or_default();
+        let critical = alerts_by_severity.get(&format!("{:?}", Severity::Critical)).is_some_and(|n| *n > 0);
+        Self {
+            events: events_by_chain.values().sum(),
+            events_by_chain,
+            alerts: alerts_by_severity.values().sum(),
+            alerts_by_severity,
+            rule_hits,
+            critical,
+        }
+    }
+
+    /// 0, or `CRITICAL_EXIT_CODE` when a Critical alert fired.
+    pub fn exit_code(&self) -> i32 {
+        if self.critical { CRITICAL_EXIT_CODE } else { 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critical_alerts_set_the_exit_code() {
+        let state = AppState::new();
+        state.record_event("ethereum");
+        state.record_event("polygon");
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), None);
+        state.record_rule_hit("ThresholdRule".into());
+
+        let summary = RunSummary::from_state(&state);
+        assert_eq!((summary.events, summary.alerts, summary.critical), (2, 1, false));
+        assert_eq!(summary.exit_code(), 0);
+
+        state.add_alert(Severity::Critical, "ethereum".into(), "Ownership transferred".into(), None);
+        let summary = RunSummary::from_state(&state);
+        assert_eq!(summary.exit_code(), CRITICAL_EXIT_CODE);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["events_by_chain"], serde_json::json!({ "ethereum": 1, "polygon": 1 }));
+        assert_eq!(json["alerts_by_severity"], serde_json::json!({ "Critical": 1, "High": 1 }));
+        assert_eq!(json["rule_hits"], serde_json::json!({ "ThresholdRule": 1 }));
+        assert_eq!(json["critical"], true);
+    }
+}
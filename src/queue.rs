@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tracing::{info, warn};
+
+use crate::config::QueuePolicy;
+use crate::events::NormalizedEvent;
+use crate::state::AppState;
+
+/// Room in the listener-facing channel; the relay empties it as fast as
+/// events arrive, so this only smooths bursts.
+const INTAKE_CAPACITY: usize = 16;
+
+/// The event queue between listeners and the rule engine: listeners send on
+/// the returned `Sender`, the consumer reads the `Receiver`. A relay task
+/// holds up to `capacity` events in between and applies `policy` when that
+/// fills up, publishing the depth to `AppState::queue_depth`. It exits, and
+/// closes the consumer's end, once every sender is gone and the queue is
+/// drained.
+pub fn event_queue(capacity: usize, policy: QueuePolicy, state: Arc<AppState>) -> (Sender<NormalizedEvent>, Receiver<NormalizedEvent>) {
+    let (intake_tx, intake_rx) = mpsc::channel(INTAKE_CAPACITY);
+    let (out_tx, out_rx) = mpsc::channel(1);
+    tokio::spawn(relay(intake_rx, out_tx, capacity.max(1), policy, state));
+    (intake_tx, out_rx)
+}
+
+async fn relay(
+    mut intake: Receiver<NormalizedEvent>,
+    out: Sender<NormalizedEvent>,
+    capacity: usize,
+    policy: QueuePolicy,
+    state: Arc<AppState>,
+) {
+    let mut buffer: VecDeque<NormalizedEvent> = VecDeque::with_capacity(capacity);
+    // Drops since the queue last had room, for one warning per episode
+    let mut dropped = 0u64;
+    let mut open = true;
+    while open || !buffer.is_empty() {
+        // Block stops taking events while full, so listeners wait on the intake
+        let accepting = open && (policy == QueuePolicy::DropOldest || buffer.len() < capacity);
+        tokio::select! {
+            incoming = intake.recv(), if accepting => match incoming {
+                Some(event) => {
+                    if buffer.len() >= capacity {
+                        buffer.pop_front();
+                        state.events_dropped.fetch_add(1, Ordering::Relaxed);
+                        if dropped == 0 {
+                            warn!("Event queue full ({} events), dropping the oldest to keep listening", capacity);
+                        }
+                        dropped += 1;
+                    }
+                    buffer.push_back(event);
+                }
+                None => open = false,
+            },
+            permit = out.reserve(), if !buffer.is_empty() => match permit {
+                Ok(permit) => {
+                    if let Some(event) = buffer.pop_front() {
+                        permit.send(event);
+                    }
+                    if dropped > 0 && buffer.len() <= capacity / 2 {
+                        info!("Event queue recovered, {} event(s) dropped while saturated", dropped);
+                        dropped = 0;
+                    }
+                }
+                // The consumer is gone; nothing left to deliver to
+                Err(_) => break,
+            },
+        }
+        state.queue_depth.store(buffer.len(), Ordering::Relaxed);
+    }
+    state.queue_depth.store(0, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use std::time::Duration;
+
+    fn event(block: u64) -> NormalizedEvent {
+        NormalizedEvent { block_number: block, ..NormalizedEvent::sample(EventType::Transfer, serde_json::json!({})) }
+    }
+
+    /// Sends events for blocks 1..=n, stopping at the first send that waits
+    /// for room; returns how many went through.
+    async fn flood(tx: &Sender<NormalizedEvent>, n: u64) -> u64 {
+        for block in 1..=n {
+            if tokio::time::timeout(Duration::from_millis(50), tx.send(event(block))).await.is_err() {
+                return block - 1;
+            }
+        }
+        n
+    }
+
+    async fn drain(mut rx: Receiver<NormalizedEvent>) -> Vec<u64> {
+        let mut blocks = Vec::new();
+        while let Some(event) = rx.recv().await {
+            blocks.push(event.block_number);
+        }
+        blocks
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_listeners_moving() {
+        let state = Arc::new(AppState::new());
+        let (tx, rx) = event_queue(3, QueuePolicy::DropOldest, state.clone());
+        assert_eq!(flood(&tx, 30).await, 30, "no send ever waited");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(state.queue_depth.load(Ordering::Relaxed), 3);
+        drop(tx);
+
+        let blocks = drain(rx).await;
+        let dropped = state.events_dropped.load(Ordering::Relaxed);
+        assert_eq!(blocks.len() as u64 + dropped, 30);
+        // The newest three survive, plus at most the one already handed on
+        assert!(blocks.len() <= 4, "{:?}", blocks);
+        assert_eq!(blocks[blocks.len() - 3..], [28, 29, 30]);
+        assert_eq!(state.queue_depth.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn block_holds_listeners_back_and_loses_nothing() {
+        let state = Arc::new(AppState::new());
+        let (tx, rx) = event_queue(3, QueuePolicy::Block, state.clone());
+        // Intake, queue and hand-off slot fill up, then the listener waits
+        let sent = flood(&tx, 30).await;
+        assert!(sent < 30, "sent {}", sent);
+        assert_eq!(state.queue_depth.load(Ordering::Relaxed), 3);
+
+        let consumer = tokio::spawn(drain(rx));
+        for block in sent + 1..=30 {
+            tx.send(event(block)).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(consumer.await.unwrap(), (1..=30).collect::<Vec<_>>());
+        assert_eq!(state.events_dropped.load(Ordering::Relaxed), 0);
+    }
+}
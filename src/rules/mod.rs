@@ -1,31 +1,124 @@
-use crate::events::{NormalizedEvent, EventType, Severity};
-use alloy::primitives::U256;
+use crate::events::{parse_units, NormalizedEvent, EventType, Severity};
+use crate::state::AppState;
+use alloy::primitives::{Address, Sign, B256, I256, U256};
+use eyre::{Result, WrapErr};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::any::Any;
 use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use tracing::{debug, enabled, error, Level, Span};
+
+/// The text a panic was raised with, when it was a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// Parses an address stored as a JSON string in the event `data` (e.g.
+/// `from`, `spender`). Case-insensitive, so checksummed and lowercase match.
+fn data_address(event: &NormalizedEvent, field: &str) -> Option<Address> {
+    event.data.get(field)?.as_str()?.parse::<Address>().ok()
+}
+
+/// Parses a signed integer from the event `data`: a decimal or `0x` hex
+/// string with an optional leading `-`, or a plain JSON integer.
+fn data_signed(event: &NormalizedEvent, field: &str) -> Option<I256> {
+    parse_signed(event.data.get(field)?)
+}
+
+fn parse_signed(value: &serde_json::Value) -> Option<I256> {
+    if let Some(n) = value.as_i64() {
+        return I256::try_from(n).ok();
+    }
+    if let Some(n) = value.as_u64() {
+        return I256::try_from(n).ok();
+    }
+    // U256 parsing takes both decimal and 0x-prefixed hex
+    let text = value.as_str()?.trim();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => (Sign::Negative, rest),
+        None => (Sign::Positive, text),
+    };
+    I256::checked_from_sign_and_abs(sign, digits.parse::<U256>().ok()?)
+}
 
 pub trait Rule: Send + Sync + Debug {
+    fn name(&self) -> &'static str;
+    /// One-line summary of what the rule flags, with its configured parameters.
+    fn description(&self) -> String;
     fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)>;
+    /// Rank among rules matching the same event; with `stop_on_first` only
+    /// the highest one alerts.
+    fn priority(&self) -> i32 {
+        0
+    }
+}
+
+/// How large a Transfer must be for `ThresholdRule`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Threshold {
+    /// Base units, the same for every token.
+    Raw(U256),
+    /// Whole tokens (decimal text, e.g. "1000.5"), scaled by each event's
+    /// `decimals`.
+    Tokens(String),
+}
+
+impl Threshold {
+    /// The bar in `event`'s base units; None for a token threshold on a
+    /// token without known decimals (or with too few for the amount).
+    fn base_units(&self, event: &NormalizedEvent) -> Option<U256> {
+        match self {
+            Threshold::Raw(min) => Some(*min),
+            Threshold::Tokens(amount) => parse_units(amount, event.decimals?),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ThresholdRule {
-    pub min_value: U256,
+    pub threshold: Threshold,
     pub severity: Severity,
 }
 
 impl ThresholdRule {
-    pub fn new(min_value: U256, severity: Severity) -> Self {
-        Self { min_value, severity }
+    pub fn new(threshold: Threshold, severity: Severity) -> Self {
+        Self { threshold, severity }
     }
 }
 
 impl Rule for ThresholdRule {
+    fn name(&self) -> &'static str {
+        "ThresholdRule"
+    }
+
+    fn description(&self) -> String {
+        match &self.threshold {
+            Threshold::Raw(min) => format!("Transfer value >= {} ({:?})", min, self.severity),
+            Threshold::Tokens(amount) => format!("Transfer value >= {} tokens ({:?})", amount, self.severity),
+        }
+    }
+
     fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
         if let EventType::Transfer = event.event_type {
             if let Some(value) = event.data.get("value") {
                  if let Some(val_str) = value.as_str() {
                      if let Ok(val) = val_str.parse::<U256>() {
-                         if val >= self.min_value {
-                             return Some((format!("Large Transfer Detected: {} > {}", val, self.min_value), self.severity.clone()));
+                         let Some(min_value) = self.threshold.base_units(event) else {
+                             debug!("No decimals for {}, token threshold skipped", event.contract_address);
+                             return None;
+                         };
+                         if val >= min_value {
+                             return Some((
+                                 format!("Large Transfer Detected: {} > {}", event.format_amount(val), event.format_amount(min_value)),
+                                 self.severity.clone(),
+                             ));
                          }
                      }
                  }
@@ -47,6 +140,14 @@ impl OwnershipRule {
 }
 
 impl Rule for OwnershipRule {
+    fn name(&self) -> &'static str {
+        "OwnershipRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Any ownership transfer ({:?})", self.severity)
+    }
+
     fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
         if let EventType::OwnershipTransferred = event.event_type {
             return Some(("Ownership Transferred!".to_string(), self.severity.clone()));
@@ -55,6 +156,81 @@ impl Rule for OwnershipRule {
     }
 }
 
+/// (When, previousOwner, newOwner) of one ownership transfer.
+type OwnershipChange = (Instant, Address, Address);
+
+/// Flags ownership bouncing around a contract: more than `max_changes`
+/// transfers within `window`, or ownership handed back to an address that
+/// held it earlier in the window (A -> B -> A).
+#[derive(Debug)]
+pub struct OwnershipPingPongRule {
+    pub max_changes: usize,
+    pub window: Duration,
+    pub severity: Severity,
+    // (ChainId, Contract) -> its recent changes, oldest first
+    history: Mutex<HashMap<(u64, Address), VecDeque<OwnershipChange>>>,
+}
+
+impl OwnershipPingPongRule {
+    pub fn new(max_changes: usize, window: Duration, severity: Severity) -> Self {
+        Self { max_changes, window, severity, history: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a transfer on `key` and returns how many fall inside the
+    /// window, plus whether `new_owner` already gave up ownership in it.
+    fn record(&self, key: (u64, Address), previous: Address, new_owner: Address, now: Instant) -> (usize, bool) {
+        let Ok(mut history) = self.history.lock() else { return (0, false) };
+        history.retain(|_, changes| {
+            while changes.front().is_some_and(|(t, _, _)| now.saturating_duration_since(*t) > self.window) {
+                changes.pop_front();
+            }
+            !changes.is_empty()
+        });
+        let changes = history.entry(key).or_default();
+        // Renouncing to the zero address is never a handback
+        let returned = !new_owner.is_zero() && changes.iter().any(|(_, from, _)| *from == new_owner);
+        changes.push_back((now, previous, new_owner));
+        (changes.len(), returned)
+    }
+}
+
+impl Rule for OwnershipPingPongRule {
+    fn name(&self) -> &'static str {
+        "OwnershipPingPongRule"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "More than {} ownership changes on one contract within {}s, or ownership returned to a previous owner ({:?})",
+            self.max_changes,
+            self.window.as_secs(),
+            self.severity
+        )
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if !matches!(event.event_type, EventType::OwnershipTransferred) {
+            return None;
+        }
+        let previous = data_address(event, "previousOwner")?;
+        let new_owner = data_address(event, "newOwner")?;
+        let (count, returned) = self.record((event.chain_id, event.contract_address), previous, new_owner, Instant::now());
+        if returned {
+            Some((
+                format!("Ownership Ping-Pong: {} handed {} back to {} ({} changes within {}s)", previous, event.contract_label(), new_owner, count, self.window.as_secs()),
+                self.severity.clone(),
+            ))
+        } else if count > self.max_changes {
+            Some((
+                format!("Ownership Churn: {} changed owner {} times within {}s", event.contract_label(), count, self.window.as_secs()),
+                self.severity.clone(),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct HighApprovalRule {
     pub threshold: U256,
@@ -68,13 +244,24 @@ impl HighApprovalRule {
 }
 
 impl Rule for HighApprovalRule {
+    fn name(&self) -> &'static str {
+        "HighApprovalRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Approval value >= {} ({:?})", self.threshold, self.severity)
+    }
+
     fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
         if let EventType::Approval = event.event_type {
             if let Some(value) = event.data.get("value") {
                 if let Some(val_str) = value.as_str() {
                     if let Ok(val) = val_str.parse::<U256>() {
                         if val >= self.threshold {
-                            return Some((format!("High Approval Detected: {} >= {}", val, self.threshold), self.severity.clone()));
+                            return Some((
+                                format!("High Approval Detected: {} >= {}", event.format_amount(val), event.format_amount(self.threshold)),
+                                self.severity.clone(),
+                            ));
                         }
                     }
                 }
@@ -84,26 +271,1366 @@ impl Rule for HighApprovalRule {
     }
 }
 
+/// Flags a burst of transfers from a single sender (drainers, flash mints):
+/// more than `max_transfers` within `window`.
+#[derive(Debug)]
+pub struct VelocityRule {
+    pub max_transfers: usize,
+    pub window: Duration,
+    pub severity: Severity,
+    // Transfer timestamps per sender, oldest first
+    seen: Mutex<HashMap<Address, VecDeque<Instant>>>,
+}
+
+impl VelocityRule {
+    pub fn new(max_transfers: usize, window: Duration, severity: Severity) -> Self {
+        Self { max_transfers, window, severity, seen: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a transfer from `from` at `now` and returns how many fall
+    /// inside the window.
+    fn record(&self, from: Address, now: Instant) -> usize {
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(_) => return 0,
+        };
+        // Drop senders that have gone quiet so the map doesn't grow forever
+        seen.retain(|_, times| {
+            while times.front().is_some_and(|t| now.saturating_duration_since(*t) > self.window) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = seen.entry(from).or_default();
+        times.push_back(now);
+        times.len()
+    }
+}
+
+impl Rule for VelocityRule {
+    fn name(&self) -> &'static str {
+        "VelocityRule"
+    }
+
+    fn description(&self) -> String {
+        format!("More than {} transfers from one sender within {}s ({:?})", self.max_transfers, self.window.as_secs(), self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Transfer = event.event_type {
+            let from = data_address(event, "from")?;
+            let count = self.record(from, Instant::now());
+            if count > self.max_transfers {
+                return Some((
+                    format!("Transfer Burst: {} transfers from {} within {}s", count, from, self.window.as_secs()),
+                    self.severity.clone(),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Flags a sender paying more than `max_recipients` distinct addresses
+/// within the window (drainer sweeps, airdrop spam).
+#[derive(Debug)]
+pub struct FanOutRule {
+    pub max_recipients: usize,
+    pub window: Duration,
+    pub severity: Severity,
+    // Sender -> recipient -> last transfer to it
+    seen: Mutex<HashMap<Address, HashMap<Address, Instant>>>,
+}
+
+impl FanOutRule {
+    pub fn new(max_recipients: usize, window: Duration, severity: Severity) -> Self {
+        Self { max_recipients, window, severity, seen: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a transfer `from` -> `to` at `now` and returns how many
+    /// distinct recipients `from` paid inside the window.
+    fn record(&self, from: Address, to: Address, now: Instant) -> usize {
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(_) => return 0,
+        };
+        seen.retain(|_, recipients| {
+            recipients.retain(|_, last| now.saturating_duration_since(*last) <= self.window);
+            !recipients.is_empty()
+        });
+        let recipients = seen.entry(from).or_default();
+        recipients.insert(to, now);
+        recipients.len()
+    }
+}
+
+impl Rule for FanOutRule {
+    fn name(&self) -> &'static str {
+        "FanOutRule"
+    }
+
+    fn description(&self) -> String {
+        format!("One sender paying more than {} distinct recipients within {}s ({:?})", self.max_recipients, self.window.as_secs(), self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Transfer = event.event_type {
+            let from = data_address(event, "from")?;
+            let to = data_address(event, "to")?;
+            let count = self.record(from, to, Instant::now());
+            if count > self.max_recipients {
+                return Some((
+                    format!("Transfer Fan-Out: {} sent to {} distinct recipients within {}s", from, count, self.window.as_secs()),
+                    self.severity.clone(),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Flags any Transfer/Approval touching a known-malicious address
+/// (e.g. sanctioned or exploit-tagged addresses).
+#[derive(Debug)]
+pub struct BlacklistRule {
+    pub addresses: HashSet<Address>,
+}
+
+impl BlacklistRule {
+    pub fn new(addresses: HashSet<Address>) -> Self {
+        Self { addresses }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(load_address_list(path)?))
+    }
+}
+
+/// Reads one address per line; blank lines and `#` comments are skipped.
+/// Checksummed and lowercase forms are both accepted.
+pub fn load_address_list(path: &Path) -> Result<HashSet<Address>> {
+    let raw = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Cannot read address list {}", path.display()))?;
+    let mut addresses = HashSet::new();
+    for (n, line) in raw.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let address = line.parse::<Address>()
+            .wrap_err_with(|| format!("{}:{}: invalid address '{}'", path.display(), n + 1, line))?;
+        addresses.insert(address);
+    }
+    Ok(addresses)
+}
+
+impl Rule for BlacklistRule {
+    fn name(&self) -> &'static str {
+        "BlacklistRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Transfer/Approval touching one of {} blacklisted addresses (Critical)", self.addresses.len())
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        let fields: &[&str] = match event.event_type {
+            EventType::Transfer | EventType::NftTransfer => &["from", "to"],
+            EventType::Approval => &["owner", "spender"],
+            _ => return None,
+        };
+        for field in fields {
+            if let Some(address) = data_address(event, field).filter(|a| self.addresses.contains(a)) {
+                return Some((
+                    format!("Blacklisted Address: {} is the {} of a {}", address, field, event.event_type.name()),
+                    Severity::Critical,
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Escalates an Approval to Critical when the `spender` is a known drainer
+/// contract, even for ordinary amounts. Approvals below `min_value` (and
+/// zero-value revocations) are ignored; unknown spenders are left to
+/// `HighApprovalRule`.
+#[derive(Debug)]
+pub struct DrainerApprovalRule {
+    pub spenders: HashSet<Address>,
+    pub min_value: U256,
+}
+
+impl DrainerApprovalRule {
+    pub fn new(spenders: HashSet<Address>, min_value: U256) -> Self {
+        Self { spenders, min_value }
+    }
+}
+
+impl Rule for DrainerApprovalRule {
+    fn name(&self) -> &'static str {
+        "DrainerApprovalRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Approval >= {} to one of {} known drainer spenders (Critical)", self.min_value, self.spenders.len())
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Approval = event.event_type {
+            let spender = data_address(event, "spender").filter(|a| self.spenders.contains(a))?;
+            let value = event.data.get("value")?.as_str()?.parse::<U256>().ok()?;
+            if !value.is_zero() && value >= self.min_value {
+                let owner = data_address(event, "owner").map(|o| o.to_string()).unwrap_or_else(|| "?".to_string());
+                return Some((
+                    format!("Drainer Approval: {} approved known drainer {} for {}", owner, spender, event.format_amount(value)),
+                    Severity::Critical,
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Flags the classic wallet drain: an Approval of `spender` by `owner`,
+/// then a Transfer of the same token from `owner` to `spender` within
+/// `window`. Zero-value approvals revoke what was remembered.
+#[derive(Debug)]
+pub struct DrainPatternRule {
+    pub window: Duration,
+    // (ChainId, Token, Owner, Spender) -> when the approval was seen
+    approvals: Mutex<HashMap<(u64, Address, Address, Address), Instant>>,
+}
+
+impl DrainPatternRule {
+    pub fn new(window: Duration) -> Self {
+        Self { window, approvals: Mutex::new(HashMap::new()) }
+    }
+
+    fn remember(&self, key: (u64, Address, Address, Address), revoked: bool, now: Instant) {
+        let Ok(mut approvals) = self.approvals.lock() else { return };
+        // Forget approvals that were never used so the map doesn't grow forever
+        approvals.retain(|_, seen| now.saturating_duration_since(*seen) <= self.window);
+        if revoked {
+            approvals.remove(&key);
+        } else {
+            approvals.insert(key, now);
+        }
+    }
+
+    /// Consumes a live approval matching `key`, returning how long ago it was seen.
+    fn take(&self, key: &(u64, Address, Address, Address), now: Instant) -> Option<Duration> {
+        let seen = self.approvals.lock().ok()?.remove(key)?;
+        Some(now.saturating_duration_since(seen)).filter(|age| *age <= self.window)
+    }
+}
+
+impl Rule for DrainPatternRule {
+    fn name(&self) -> &'static str {
+        "DrainPatternRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Approval followed by a transfer from the owner to the spender within {}s (Critical)", self.window.as_secs())
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        let now = Instant::now();
+        match event.event_type {
+            EventType::Approval => {
+                let key = (event.chain_id, event.contract_address, data_address(event, "owner")?, data_address(event, "spender")?);
+                let revoked = event.data.get("value")?.as_str()?.parse::<U256>().ok()?.is_zero();
+                self.remember(key, revoked, now);
+                None
+            }
+            EventType::Transfer => {
+                let (from, to) = (data_address(event, "from")?, data_address(event, "to")?);
+                let age = self.take(&(event.chain_id, event.contract_address, from, to), now)?;
+                let amount = event.data.get("value")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| s.parse::<U256>().ok())
+                    .map(|v| event.format_amount(v))
+                    .unwrap_or_else(|| "?".to_string());
+                Some((
+                    format!("Drain Pattern: {} approved {} then {} moved to it {}s later", from, to, amount, age.as_secs()),
+                    Severity::Critical,
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Which zero-address transfers `MintBurnRule` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintBurnDirection {
+    Mint,
+    Burn,
+    Both,
+}
+
+/// Flags supply changes: a Transfer from the zero address is a mint, one to
+/// it is a burn. Amounts below `min_value` are ignored.
+#[derive(Debug)]
+pub struct MintBurnRule {
+    pub direction: MintBurnDirection,
+    pub min_value: U256,
+    pub severity: Severity,
+}
+
+impl MintBurnRule {
+    pub fn new(direction: MintBurnDirection, min_value: U256, severity: Severity) -> Self {
+        Self { direction, min_value, severity }
+    }
+}
+
+impl Rule for MintBurnRule {
+    fn name(&self) -> &'static str {
+        "MintBurnRule"
+    }
+
+    fn description(&self) -> String {
+        format!("{:?} transfers (zero address) >= {} ({:?})", self.direction, self.min_value, self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Transfer = event.event_type {
+            let from = data_address(event, "from")?;
+            let to = data_address(event, "to")?;
+            let value = event.data.get("value")?.as_str()?.parse::<U256>().ok()?;
+            if value < self.min_value {
+                return None;
+            }
+            let mints = matches!(self.direction, MintBurnDirection::Mint | MintBurnDirection::Both);
+            let burns = matches!(self.direction, MintBurnDirection::Burn | MintBurnDirection::Both);
+
+            if from.is_zero() && !to.is_zero() && mints {
+                return Some((format!("Mint: {} minted to {}", event.format_amount(value), to), self.severity.clone()));
+            }
+            if to.is_zero() && !from.is_zero() && burns {
+                return Some((format!("Burn: {} burned from {}", event.format_amount(value), from), self.severity.clone()));
+            }
+        }
+        None
+    }
+}
+
+/// Flags a token's total supply moving by more than `max_change_pct` percent
+/// between two polls (`SupplyChanged` events). Any growth from a zero
+/// supply counts.
+#[derive(Debug)]
+pub struct SupplyChangeRule {
+    pub max_change_pct: f64,
+    pub severity: Severity,
+}
+
+impl SupplyChangeRule {
+    pub fn new(max_change_pct: f64, severity: Severity) -> Self {
+        Self { max_change_pct, severity }
+    }
+}
+
+impl Rule for SupplyChangeRule {
+    fn name(&self) -> &'static str {
+        "SupplyChangeRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Total supply moving more than {}% between polls ({:?})", self.max_change_pct, self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if !matches!(event.event_type, EventType::SupplyChanged) {
+            return None;
+        }
+        let previous = event.data.get("previous_supply")?.as_str()?.parse::<U256>().ok()?;
+        let current = event.data.get("total_supply")?.as_str()?.parse::<U256>().ok()?;
+        let change = percent_change(previous, current);
+        if change <= self.max_change_pct {
+            return None;
+        }
+        let (kind, verb) = if current > previous { ("Inflation", "rose") } else { ("Contraction", "fell") };
+        Some((
+            format!(
+                "Supply {}: {} total supply {} {:.2}% ({} -> {})",
+                kind, event.contract_label(), verb, change, event.format_amount(previous), event.format_amount(current)
+            ),
+            self.severity.clone(),
+        ))
+    }
+}
+
+/// Change from `previous` to `current` in percent of `previous`, either
+/// direction; infinite for any change from zero.
+pub fn percent_change(previous: U256, current: U256) -> f64 {
+    let delta = if current > previous { current - previous } else { previous - current };
+    if delta.is_zero() {
+        return 0.0;
+    }
+    if previous.is_zero() {
+        return f64::INFINITY;
+    }
+    // Parts per million in integer math (U256 has no float conversion), then
+    // percent; near the top of the range, scale the divisor down instead
+    let million = U256::from(1_000_000);
+    let ppm = match delta.checked_mul(million) {
+        Some(scaled) => scaled / previous,
+        None => delta / (previous / million).max(U256::from(1)),
+    };
+    u128::try_from(ppm).map_or(f64::INFINITY, |ppm| ppm as f64 / 10_000.0)
+}
+
+/// Flags Uniswap swaps moving at least `min_amount0` of token0 or
+/// `min_amount1` of token1 (base units, in either direction). An unset
+/// bound never matches, so each pool token gets a threshold in its own units.
+#[derive(Debug)]
+pub struct LargeSwapRule {
+    pub min_amount0: Option<U256>,
+    pub min_amount1: Option<U256>,
+    pub severity: Severity,
+}
+
+impl LargeSwapRule {
+    pub fn new(min_amount0: Option<U256>, min_amount1: Option<U256>, severity: Severity) -> Self {
+        Self { min_amount0, min_amount1, severity }
+    }
+}
+
+impl Rule for LargeSwapRule {
+    fn name(&self) -> &'static str {
+        "LargeSwapRule"
+    }
+
+    fn description(&self) -> String {
+        let bound = |b: Option<U256>| b.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+        format!("Swap moving >= {} token0 or >= {} token1 ({:?})", bound(self.min_amount0), bound(self.min_amount1), self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Swap = event.event_type {
+            let amount = |field: &str| {
+                event.data.get(field)
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| s.parse::<U256>().ok())
+                    .unwrap_or_default()
+            };
+            let (in0, out0, in1, out1) = (amount("amount0_in"), amount("amount0_out"), amount("amount1_in"), amount("amount1_out"));
+            let hit0 = self.min_amount0.is_some_and(|min| in0.max(out0) >= min);
+            let hit1 = self.min_amount1.is_some_and(|min| in1.max(out1) >= min);
+            if hit0 || hit1 {
+                // Describe the trade as what went in and what came out
+                let (sold, bought) = if !in0.is_zero() {
+                    (format!("{} token0", in0), format!("{} token1", out1))
+                } else {
+                    (format!("{} token1", in1), format!("{} token0", out0))
+                };
+                return Some((
+                    format!("Large Swap on {}: {} in, {} out", event.contract_label(), sold, bought),
+                    self.severity.clone(),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Flags a signed amount (rebase delta, custom ABI `int256`) whose magnitude
+/// reaches `min_magnitude`, reporting positive values as inflows and
+/// negative ones as outflows.
+#[derive(Debug)]
+pub struct SignedThresholdRule {
+    pub event: Option<String>,
+    pub field: String,
+    pub min_magnitude: U256,
+    pub severity: Severity,
+}
+
+impl SignedThresholdRule {
+    pub fn new(event: Option<String>, field: String, min_magnitude: U256, severity: Severity) -> Self {
+        Self { event, field, min_magnitude, severity }
+    }
+}
+
+impl Rule for SignedThresholdRule {
+    fn name(&self) -> &'static str {
+        "SignedThresholdRule"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{}.{} moving >= {} either way ({:?})",
+            self.event.as_deref().unwrap_or("*"), self.field, self.min_magnitude, self.severity
+        )
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if self.event.as_deref().is_some_and(|name| name != event.event_type.name()) {
+            return None;
+        }
+        let value = data_signed(event, &self.field)?;
+        let magnitude = value.unsigned_abs();
+        if magnitude < self.min_magnitude {
+            return None;
+        }
+        let (direction, sign) = if value.is_negative() { ("outflow", "-") } else { ("inflow", "+") };
+        Some((
+            format!(
+                "Large {} on {}: {} {} {}{}",
+                direction, event.contract_label(), event.event_type.name(), self.field, sign, event.format_amount(magnitude)
+            ),
+            self.severity.clone(),
+        ))
+    }
+}
+
+/// Flags any Transfer moving funds into or out of a watched address (whales,
+/// treasuries), regardless of amount.
+#[derive(Debug)]
+pub struct WatchlistTransferRule {
+    pub addresses: HashSet<Address>,
+    pub severity: Severity,
+}
+
+impl WatchlistTransferRule {
+    pub fn new(addresses: HashSet<Address>, severity: Severity) -> Self {
+        Self { addresses, severity }
+    }
+}
+
+impl Rule for WatchlistTransferRule {
+    fn name(&self) -> &'static str {
+        "WatchlistTransferRule"
+    }
+
+    fn description(&self) -> String {
+        format!("Any transfer from or to one of {} watched addresses ({:?})", self.addresses.len(), self.severity)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::Transfer = event.event_type {
+            let amount = event.data.get("value")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.parse::<U256>().ok())
+                .map(|v| event.format_amount(v))
+                .unwrap_or_else(|| "?".to_string());
+            let watched = |field| data_address(event, field).filter(|a| self.addresses.contains(a));
+
+            if let Some(from) = watched("from") {
+                return Some((format!("Watched Address Sending: {} sent {}", from, amount), self.severity.clone()));
+            }
+            if let Some(to) = watched("to") {
+                return Some((format!("Watched Address Receiving: {} received {}", to, amount), self.severity.clone()));
+            }
+        }
+        None
+    }
+}
+
+/// Flags ERC-721 transfers, limited to `collections` (contract addresses)
+/// and/or `token_ids` when those are non-empty.
+#[derive(Debug)]
+pub struct NftTransferRule {
+    pub collections: HashSet<Address>,
+    pub token_ids: HashSet<U256>,
+    pub severity: Severity,
+}
+
+impl NftTransferRule {
+    pub fn new(collections: HashSet<Address>, token_ids: HashSet<U256>, severity: Severity) -> Self {
+        Self { collections, token_ids, severity }
+    }
+}
+
+impl Rule for NftTransferRule {
+    fn name(&self) -> &'static str {
+        "NftTransferRule"
+    }
+
+    fn description(&self) -> String {
+        let count = |n: usize, what: &str| if n == 0 { format!("any {}", what) } else { format!("{} {}(s)", n, what) };
+        format!(
+            "ERC-721 transfers in {}, {} ({:?})",
+            count(self.collections.len(), "collection"),
+            count(self.token_ids.len(), "token id"),
+            self.severity
+        )
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if !matches!(event.event_type, EventType::NftTransfer) {
+            return None;
+        }
+        if !self.collections.is_empty() && !self.collections.contains(&event.contract_address) {
+            return None;
+        }
+        let token_id = event.data.get("tokenId")?.as_str()?.parse::<U256>().ok()?;
+        if !self.token_ids.is_empty() && !self.token_ids.contains(&token_id) {
+            return None;
+        }
+        let (from, to) = (data_address(event, "from")?, data_address(event, "to")?);
+        Some((
+            format!("NFT Transfer: token #{} of {} from {} to {}", token_id, event.contract_label(), from, to),
+            self.severity.clone(),
+        ))
+    }
+}
+
+/// Describes the synthetic `GasSpike` events a chain's block listener emits
+/// (see `chains.<name>.gas_spike`). Severity comes from the event itself.
+#[derive(Debug)]
+pub struct GasSpikeRule;
+
+impl Rule for GasSpikeRule {
+    fn name(&self) -> &'static str {
+        "GasSpikeRule"
+    }
+
+    fn description(&self) -> String {
+        "Base fee above a multiple of the chain's trailing average (chains with gas_spike)".to_string()
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if let EventType::GasSpike = event.event_type {
+            let fee = |field| event.data.get(field)?.as_str()?.parse::<u128>().ok();
+            let (base_fee, average) = (fee("base_fee")?, fee("average_base_fee")?);
+            let window = event.data.get("window_blocks").and_then(|w| w.as_u64()).unwrap_or_default();
+            let gwei = |wei: u128| wei as f64 / 1e9;
+            return Some((
+                format!(
+                    "Gas Spike: base fee {:.2} gwei is {:.1}x the {}-block average of {:.2} gwei",
+                    gwei(base_fee), base_fee as f64 / average.max(1) as f64, window, gwei(average),
+                ),
+                event.severity.clone(),
+            ));
+        }
+        None
+    }
+}
+
+/// Restricts a rule to events emitted by `contracts`; other events skip it.
+#[derive(Debug)]
+pub struct ScopedRule {
+    pub inner: Box<dyn Rule>,
+    pub contracts: HashSet<Address>,
+}
+
+impl Rule for ScopedRule {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> String {
+        format!("{} [only {} contract(s)]", self.inner.description(), self.contracts.len())
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        if !self.contracts.contains(&event.contract_address) {
+            return None;
+        }
+        self.inner.check(event)
+    }
+
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+}
+
+/// Gives a rule a configured `priority` in place of its own.
+#[derive(Debug)]
+pub struct PrioritizedRule {
+    pub inner: Box<dyn Rule>,
+    pub priority: i32,
+}
+
+impl Rule for PrioritizedRule {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> String {
+        format!("{} [priority {}]", self.inner.description(), self.priority)
+    }
+
+    fn check(&self, event: &NormalizedEvent) -> Option<(String, Severity)> {
+        self.inner.check(event)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+/// Recent (TxHash, RuleName, Message) hits remembered to collapse identical
+/// alerts from repeated logs in one transaction.
+const TX_DEDUP_CAPACITY: usize = 256;
+
 pub struct RuleEngine {
     rules: Vec<Box<dyn Rule>>,
+    tx_hits: Mutex<VecDeque<(B256, &'static str, String)>>,
+    parallel: bool,
+    stop_on_first: bool,
 }
 
 impl RuleEngine {
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self {
+            rules: Vec::new(),
+            tx_hits: Mutex::new(VecDeque::with_capacity(TX_DEDUP_CAPACITY)),
+            parallel: false,
+            stop_on_first: false,
+        }
+    }
+
+    /// Checks every rule of an event concurrently on rayon's pool. Alerts
+    /// keep rule order either way. Handing an event to the pool costs about
+    /// as much as a dozen built-in checks, so this only pays off with several
+    /// cores and dozens of rules (or a few expensive ABI-field ones); below
+    /// that, and always on a single core, sequential is faster.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Reports only the highest-priority match per event (the earliest rule
+    /// on a tie) instead of every match, for one consolidated alert.
+    pub fn with_stop_on_first(mut self, stop_on_first: bool) -> Self {
+        self.stop_on_first = stop_on_first;
+        self
     }
 
     pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
         self.rules.push(rule);
     }
 
-    pub fn process(&self, event: &NormalizedEvent) -> Vec<(String, Severity)> {
-        let mut alerts = Vec::new();
-        for rule in &self.rules {
-            if let Some(result) = rule.check(event) {
-                alerts.push(result);
+    /// Limits the rule called `name` to events from `contracts`. Returns
+    /// false if no such rule is active.
+    pub fn scope_rule(&mut self, name: &str, contracts: HashSet<Address>) -> bool {
+        let Some(i) = self.rules.iter().position(|r| r.name() == name) else {
+            return false;
+        };
+        let inner = self.rules.remove(i);
+        self.rules.insert(i, Box::new(ScopedRule { inner, contracts }));
+        true
+    }
+
+    /// Sets the priority of the rule called `name`. Returns false if no such
+    /// rule is active.
+    pub fn prioritize_rule(&mut self, name: &str, priority: i32) -> bool {
+        let Some(i) = self.rules.iter().position(|r| r.name() == name) else {
+            return false;
+        };
+        let inner = self.rules.remove(i);
+        self.rules.insert(i, Box::new(PrioritizedRule { inner, priority }));
+        true
+    }
+
+    /// Names of the active rules, in evaluation order.
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// (Name, Description) of each active rule, in evaluation order.
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        self.rules.iter().map(|r| (r.name(), r.description())).collect()
+    }
+
+    /// Runs every rule against the event. Returns (RuleName, Message, Severity)
+    /// per match, or just the top one with `stop_on_first`; a base-severity
+    /// alert is attributed to "BaseSeverity". Each returned match counts
+    /// towards `state.rule_hits`. A rule that panics is logged, counted in
+    /// `state.rule_panics` and treated as no match; the other rules still run.
+    pub fn process(&self, event: &NormalizedEvent, state: &AppState) -> Vec<(&'static str, String, Severity)> {
+        // Decision tracing is only assembled when debug logging is on
+        let trace = enabled!(Level::DEBUG);
+        let evaluate = |rule: &dyn Rule| {
+            let checked = match panic::catch_unwind(AssertUnwindSafe(|| rule.check(event))) {
+                Ok(checked) => checked,
+                Err(payload) => {
+                    error!(rule = rule.name(), tx_hash = %event.tx_hash, "Rule panicked, skipped for this event: {}", panic_message(payload.as_ref()));
+                    state.rule_panics.fetch_add(1, Ordering::Relaxed);
+                    None
+                }
+            };
+            // Rules can only escalate from the event's base severity, never lower it
+            let result = checked
+                .map(|(msg, severity)| (msg, severity.max(event.severity.clone())));
+            if trace {
+                debug!(
+                    rule = rule.name(),
+                    chain = %event.chain_name,
+                    tx_hash = %event.tx_hash,
+                    contract = %event.contract_address,
+                    event_type = ?event.event_type,
+                    value = %event.data.get("value").and_then(|v| v.as_str()).unwrap_or("-"),
+                    matched = result.is_some(),
+                    severity = ?result.as_ref().map(|(_, s)| s),
+                    "Rule evaluated"
+                );
             }
+            result.map(|(msg, severity)| (rule.priority(), (rule.name(), msg, severity)))
+        };
+        let hits: Vec<_> = if self.parallel {
+            // Pool threads don't inherit the caller's span; re-enter it for the trace
+            let span = Span::current();
+            let mut hits: Vec<_> = self.rules.par_iter()
+                .enumerate()
+                .filter_map(|(i, rule)| span.in_scope(|| evaluate(rule.as_ref())).map(|hit| (i, hit)))
+                .collect();
+            hits.sort_by_key(|(i, _)| *i);
+            hits.into_iter().map(|(_, hit)| hit).collect()
+        } else {
+            self.rules.iter().filter_map(|rule| evaluate(rule.as_ref())).collect()
+        };
+        let mut alerts: Vec<_> = if self.stop_on_first {
+            // max_by_key keeps the last of equals, so walk backwards to favour rule order
+            hits.into_iter().rev().max_by_key(|(priority, _)| *priority).map(|(_, hit)| hit).into_iter().collect()
+        } else {
+            hits.into_iter().map(|(_, hit)| hit).collect()
+        };
+
+        // A raised base severity alerts on its own even when no rule matched
+        if alerts.is_empty() && event.severity > Severity::Low {
+            alerts.push((
+                "BaseSeverity",
+                format!("{} on {} (base severity)", event.event_type.name(), event.contract_label()),
+                event.severity.clone(),
+            ));
+        }
+
+        // The same hit from another identical log in the same tx is one alert
+        alerts.retain(|(rule, msg, _)| {
+            let first = self.first_in_tx(event.tx_hash, rule, msg);
+            if !first {
+                debug!(rule, tx_hash = %event.tx_hash, "Duplicate hit in the same transaction, skipped");
+            }
+            first
+        });
+        for (rule, _, _) in &alerts {
+            state.record_rule_hit(rule.to_string());
         }
         alerts
     }
+
+    /// Records a hit and reports whether it is new for `tx_hash`. Synthetic
+    /// events (zero tx hash) are never deduplicated.
+    fn first_in_tx(&self, tx_hash: B256, rule: &'static str, msg: &str) -> bool {
+        if tx_hash.is_zero() {
+            return true;
+        }
+        let Ok(mut hits) = self.tx_hits.lock() else { return true };
+        if hits.iter().any(|(h, r, m)| *h == tx_hash && *r == rule && m == msg) {
+            return false;
+        }
+        if hits.len() >= TX_DEDUP_CAPACITY {
+            hits.pop_front();
+        }
+        hits.push_back((tx_hash, rule, msg.to_string()));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A sample `event_type` event carrying `data`, at `severity`.
+    fn event(event_type: EventType, data: serde_json::Value, severity: Severity) -> NormalizedEvent {
+        NormalizedEvent { severity, ..NormalizedEvent::sample(event_type, data) }
+    }
+
+    #[test]
+    fn rules_escalate_on_top_of_base_severity() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(HighApprovalRule::new(U256::from(1000), Severity::Critical)));
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+        let state = AppState::new();
+
+        // Base High, rule Critical: the rule wins
+        let approval = event(EventType::Approval, json!({ "value": "5000" }), Severity::High);
+        assert_eq!(engine.process(&approval, &state), vec![("HighApprovalRule", "High Approval Detected: 0.005 >= 0.001".to_string(), Severity::Critical)]);
+
+        // Base High, rule Medium: a rule never lowers the base
+        let transfer = event(EventType::Transfer, json!({ "value": "5000" }), Severity::High);
+        assert_eq!(engine.process(&transfer, &state)[0].2, Severity::High);
+
+        // No rule matches: the raised base alerts on its own
+        let mut small = event(EventType::Approval, json!({ "value": "1" }), Severity::High);
+        small.tx_hash = B256::repeat_byte(2);
+        let alerts = engine.process(&small, &state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].0, "BaseSeverity");
+        assert_eq!(alerts[0].2, Severity::High);
+
+        // ...but a Low base with no match stays silent
+        let quiet = event(EventType::Approval, json!({ "value": "1" }), Severity::Low);
+        assert!(engine.process(&quiet, &state).is_empty());
+    }
+
+    const SENDER: &str = "0x28C6c06298d514Db089934071355E5743bf21d60";
+
+    const DEAD: &str = "0x000000000000000000000000000000000000dEaD";
+
+    fn transfer(from: &str, to: &str, value: &str) -> NormalizedEvent {
+        event(EventType::Transfer, json!({ "from": from, "to": to, "value": value }), Severity::Low)
+    }
+
+    #[test]
+    fn signed_amounts_fire_on_magnitude_either_way() {
+        let rule = SignedThresholdRule::new(Some("Rebase".to_string()), "delta".to_string(), U256::from(1_000_000), Severity::High);
+        let rebase = |delta: serde_json::Value| event(EventType::Unknown("Rebase".to_string()), json!({ "delta": delta }), Severity::Low);
+
+        let (message, severity) = rule.check(&rebase(json!("2500000"))).unwrap();
+        assert!(message.starts_with("Large inflow on USDT"), "{}", message);
+        assert!(message.ends_with("Rebase delta +2.5"), "{}", message);
+        assert_eq!(severity, Severity::High);
+
+        // Negative hex: -0x1e8480 is -2000000
+        let (message, _) = rule.check(&rebase(json!("-0x1e8480"))).unwrap();
+        assert!(message.starts_with("Large outflow on USDT"), "{}", message);
+        assert!(message.ends_with("Rebase delta -2.0"), "{}", message);
+
+        // The threshold itself fires on both sides; one below it does not
+        assert!(rule.check(&rebase(json!(1_000_000))).is_some());
+        assert!(rule.check(&rebase(json!(-1_000_000))).is_some());
+        assert!(rule.check(&rebase(json!("999999"))).is_none());
+        assert!(rule.check(&rebase(json!("-999999"))).is_none());
+
+        // Unparseable values and other events are skipped
+        assert!(rule.check(&rebase(json!("lots"))).is_none());
+        let transfer = event(EventType::Transfer, json!({ "delta": "-5000000" }), Severity::Low);
+        assert!(rule.check(&transfer).is_none());
+    }
+
+    #[test]
+    fn engine_reports_added_rules_in_order() {
+        let mut engine = RuleEngine::new();
+        assert!(engine.rule_names().is_empty());
+        engine.add_rule(Box::new(HighApprovalRule::new(U256::from(1000), Severity::Critical)));
+        engine.add_rule(Box::new(VelocityRule::new(3, Duration::from_secs(60), Severity::High)));
+        assert_eq!(engine.rule_names(), ["HighApprovalRule", "VelocityRule"]);
+
+        let described = engine.describe();
+        assert_eq!(described[1], ("VelocityRule", "More than 3 transfers from one sender within 60s (High)".to_string()));
+    }
+
+    #[test]
+    fn processing_counts_hits_per_rule() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+        engine.add_rule(Box::new(BlacklistRule::new(HashSet::from([DEAD.parse().unwrap()]))));
+        let state = AppState::new();
+
+        let mut large_to_dead = transfer(SENDER, DEAD, "5000");
+        engine.process(&large_to_dead, &state);
+        large_to_dead.tx_hash = B256::repeat_byte(2);
+        engine.process(&large_to_dead, &state);
+        let mut small = transfer(SENDER, "0x1111111111111111111111111111111111111111", "5");
+        small.tx_hash = B256::repeat_byte(3);
+        engine.process(&small, &state);
+
+        let hits = state.rule_hits.lock().unwrap().clone();
+        assert_eq!(hits, HashMap::from([("ThresholdRule".to_string(), 2), ("BlacklistRule".to_string(), 2)]));
+    }
+
+    #[test]
+    fn blacklist_flags_a_listed_counterparty() {
+        let list = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(list.path(), "# exploit-tagged\n0x000000000000000000000000000000000000dead  # lowercase\n\n").unwrap();
+        let rule = BlacklistRule::from_file(list.path()).unwrap();
+
+        let (message, severity) = rule.check(&transfer(SENDER, DEAD, "1")).unwrap();
+        assert_eq!(message, format!("Blacklisted Address: {} is the to of a Transfer", DEAD));
+        assert_eq!(severity, Severity::Critical);
+        assert!(rule.check(&transfer(SENDER, "0x1111111111111111111111111111111111111111", "1")).is_none());
+    }
+
+    #[test]
+    fn drainer_spenders_escalate_ordinary_approvals() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(HighApprovalRule::new(U256::from(1_000_000_000u64), Severity::High)));
+        engine.add_rule(Box::new(DrainerApprovalRule::new(HashSet::from([DEAD.parse().unwrap()]), U256::from(1))));
+        let state = AppState::new();
+        let approval = |spender: &str, value: &str, tx: u8| NormalizedEvent {
+            tx_hash: B256::repeat_byte(tx),
+            ..event(EventType::Approval, json!({ "owner": SENDER, "spender": spender, "value": value }), Severity::Low)
+        };
+
+        // Blacklisted spender, 5 USDT
+        let alerts = engine.process(&approval(DEAD, "5000000", 1), &state);
+        assert_eq!(alerts, vec![(
+            "DrainerApprovalRule",
+            format!("Drainer Approval: {} approved known drainer {} for 5.0", SENDER, DEAD),
+            Severity::Critical,
+        )]);
+
+        // Unknown spender, 5000 USDT: only the usual high-approval alert
+        let alerts = engine.process(&approval("0x1111111111111111111111111111111111111111", "5000000000", 2), &state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].0, alerts[0].2.clone()), ("HighApprovalRule", Severity::High));
+
+        // Revoking a drainer's allowance is not an alert
+        assert!(engine.process(&approval(DEAD, "0", 3), &state).is_empty());
+    }
+
+    #[test]
+    fn approval_then_transfer_to_the_spender_is_a_drain() {
+        let rule = DrainPatternRule::new(Duration::from_secs(60));
+        let approve = |spender: &str, value: &str| {
+            event(EventType::Approval, json!({ "owner": SENDER, "spender": spender, "value": value }), Severity::Low)
+        };
+
+        assert!(rule.check(&approve(DEAD, "1000000")).is_none());
+        let (message, severity) = rule.check(&transfer(SENDER, DEAD, "1000000")).unwrap();
+        assert_eq!(message, format!("Drain Pattern: {} approved {} then 1.0 moved to it 0s later", SENDER, DEAD));
+        assert_eq!(severity, Severity::Critical);
+        assert!(rule.check(&transfer(SENDER, DEAD, "1000000")).is_none(), "each approval correlates once");
+
+        // Transfers elsewhere, revoked approvals and stale ones don't match
+        rule.check(&approve(DEAD, "1000000"));
+        assert!(rule.check(&transfer(SENDER, "0x1111111111111111111111111111111111111111", "1000000")).is_none());
+        rule.check(&approve(DEAD, "0"));
+        assert!(rule.check(&transfer(SENDER, DEAD, "1000000")).is_none());
+
+        let short = DrainPatternRule::new(Duration::from_millis(20));
+        short.check(&approve(DEAD, "1000000"));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(short.check(&transfer(SENDER, DEAD, "1000000")).is_none());
+    }
+
+    #[test]
+    fn identical_logs_in_one_tx_alert_once() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+        let state = AppState::new();
+        let log = transfer(SENDER, DEAD, "5000");
+
+        assert_eq!(engine.process(&log, &state).len(), 1);
+        assert!(engine.process(&log, &state).is_empty(), "second identical log in the same tx");
+        // A different amount in that tx is its own alert
+        assert_eq!(engine.process(&transfer(SENDER, DEAD, "6000"), &state).len(), 1);
+        // The same transfer in another tx alerts again
+        let next_tx = NormalizedEvent { tx_hash: B256::repeat_byte(2), ..transfer(SENDER, DEAD, "5000") };
+        assert_eq!(engine.process(&next_tx, &state).len(), 1);
+    }
+
+    #[test]
+    fn scoped_rules_fire_only_for_their_contracts() {
+        let mut engine = RuleEngine::new();
+        engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+        engine.add_rule(Box::new(BlacklistRule::new(HashSet::from([DEAD.parse().unwrap()]))));
+        let stablecoin = NormalizedEvent::sample(EventType::Transfer, json!({})).contract_address;
+        assert!(engine.scope_rule("ThresholdRule", HashSet::from([stablecoin])));
+        assert!(!engine.scope_rule("NoSuchRule", HashSet::new()));
+        let state = AppState::new();
+
+        let fired = |event: &NormalizedEvent| -> Vec<&'static str> {
+            engine.process(event, &state).into_iter().map(|(name, _, _)| name).collect()
+        };
+        assert_eq!(fired(&transfer(SENDER, DEAD, "5000")), ["ThresholdRule", "BlacklistRule"]);
+
+        // The same transfer from another contract skips the scoped rule only
+        let mut elsewhere = transfer(SENDER, DEAD, "5000");
+        elsewhere.contract_address = Address::repeat_byte(0xbc);
+        elsewhere.tx_hash = B256::repeat_byte(2);
+        assert_eq!(fired(&elsewhere), ["BlacklistRule"]);
+    }
+
+    #[test]
+    fn mints_and_burns_are_told_apart() {
+        const ZERO: &str = "0x0000000000000000000000000000000000000000";
+        let rule = MintBurnRule::new(MintBurnDirection::Both, U256::from(1_000_000u64), Severity::Medium);
+
+        let (message, severity) = rule.check(&transfer(ZERO, SENDER, "2000000")).unwrap();
+        assert_eq!(message, format!("Mint: 2.0 minted to {}", SENDER));
+        assert_eq!(severity, Severity::Medium);
+        let (message, _) = rule.check(&transfer(SENDER, ZERO, "3000000")).unwrap();
+        assert_eq!(message, format!("Burn: 3.0 burned from {}", SENDER));
+
+        assert!(rule.check(&transfer(SENDER, DEAD, "5000000")).is_none(), "an ordinary transfer");
+        assert!(rule.check(&transfer(ZERO, SENDER, "999999")).is_none(), "below min_value");
+        let burns_only = MintBurnRule::new(MintBurnDirection::Burn, U256::ZERO, Severity::Medium);
+        assert!(burns_only.check(&transfer(ZERO, SENDER, "2000000")).is_none());
+    }
+
+    #[test]
+    fn watchlist_tells_sending_from_receiving() {
+        let rule = WatchlistTransferRule::new(HashSet::from([DEAD.parse().unwrap()]), Severity::High);
+        let other = "0x1111111111111111111111111111111111111111";
+
+        let (message, severity) = rule.check(&transfer(DEAD, SENDER, "2500000")).unwrap();
+        assert_eq!(message, format!("Watched Address Sending: {} sent 2.5", DEAD));
+        assert_eq!(severity, Severity::High);
+        let (message, _) = rule.check(&transfer(SENDER, DEAD, "1")).unwrap();
+        assert_eq!(message, format!("Watched Address Receiving: {} received 0.000001", DEAD));
+
+        assert!(rule.check(&transfer(SENDER, other, "1000000000000")).is_none());
+    }
+
+    #[test]
+    fn velocity_counts_transfers_inside_the_window() {
+        let rule = VelocityRule::new(3, Duration::from_secs(60), Severity::High);
+        let (sender, other) = (SENDER.parse().unwrap(), Address::repeat_byte(9));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(rule.record(sender, at(0)), 1);
+        assert_eq!(rule.record(other, at(1)), 1, "senders are counted separately");
+        assert_eq!(rule.record(sender, at(10)), 2);
+        assert_eq!(rule.record(sender, at(20)), 3);
+        assert_eq!(rule.record(sender, at(30)), 4);
+        // The first two fell out of the window
+        assert_eq!(rule.record(sender, at(75)), 3);
+        assert_eq!(rule.record(sender, at(200)), 1);
+    }
+
+    #[test]
+    fn velocity_fires_past_the_threshold() {
+        let rule = VelocityRule::new(3, Duration::from_secs(60), Severity::High);
+        let transfer = transfer(SENDER, DEAD, "1");
+        for _ in 0..3 {
+            assert!(rule.check(&transfer).is_none());
+        }
+        let (message, severity) = rule.check(&transfer).unwrap();
+        assert!(message.starts_with("Transfer Burst: 4 transfers from 0x28C6"), "{}", message);
+        assert_eq!(severity, Severity::High);
+    }
+
+    #[test]
+    fn fan_out_fires_past_the_distinct_recipient_threshold() {
+        let rule = FanOutRule::new(3, Duration::from_secs(60), Severity::High);
+        let recipient = |n: u8| format!("{}", Address::repeat_byte(n));
+
+        for n in 1..=3 {
+            assert!(rule.check(&transfer(SENDER, &recipient(n), "1")).is_none());
+        }
+        // Paying a recipient again does not add to the count
+        assert!(rule.check(&transfer(SENDER, &recipient(1), "1")).is_none());
+        assert!(rule.check(&transfer(DEAD, &recipient(4), "1")).is_none(), "senders are counted separately");
+
+        let (message, severity) = rule.check(&transfer(SENDER, &recipient(4), "1")).unwrap();
+        assert!(message.starts_with("Transfer Fan-Out: 0x28C6"), "{}", message);
+        assert!(message.ends_with("sent to 4 distinct recipients within 60s"), "{}", message);
+        assert_eq!(severity, Severity::High);
+    }
+
+    #[test]
+    fn fan_out_forgets_recipients_outside_the_window() {
+        let rule = FanOutRule::new(3, Duration::from_secs(60), Severity::High);
+        let sender = SENDER.parse().unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(rule.record(sender, Address::repeat_byte(1), at(0)), 1);
+        assert_eq!(rule.record(sender, Address::repeat_byte(2), at(10)), 2);
+        assert_eq!(rule.record(sender, Address::repeat_byte(3), at(20)), 3);
+        // Recipient 1 fell out of the window; paying 2 again refreshes it
+        assert_eq!(rule.record(sender, Address::repeat_byte(2), at(65)), 2);
+        assert_eq!(rule.record(sender, Address::repeat_byte(4), at(100)), 2);
+    }
+
+    #[test]
+    fn ownership_handbacks_and_churn_escalate() {
+        let rule = OwnershipPingPongRule::new(3, Duration::from_secs(60), Severity::Critical);
+        let owners = |n: u8| format!("{}", Address::repeat_byte(n));
+        let change = |from: u8, to: u8| {
+            let data = json!({ "previousOwner": owners(from), "newOwner": owners(to) });
+            event(EventType::OwnershipTransferred, data, Severity::Low)
+        };
+
+        assert!(rule.check(&change(1, 2)).is_none());
+        let (message, severity) = rule.check(&change(2, 1)).unwrap();
+        assert!(message.starts_with(&format!("Ownership Ping-Pong: {} handed USDT", owners(2))), "{}", message);
+        assert!(message.ends_with(&format!("back to {} (2 changes within 60s)", owners(1))), "{}", message);
+        assert_eq!(severity, Severity::Critical);
+
+        // Fresh owners every time only trip the count
+        let rule = OwnershipPingPongRule::new(3, Duration::from_secs(60), Severity::Critical);
+        for n in 1..=3 {
+            assert!(rule.check(&change(n, n + 1)).is_none());
+        }
+        let (message, _) = rule.check(&change(4, 5)).unwrap();
+        assert!(message.ends_with("changed owner 4 times within 60s"), "{}", message);
+        assert!(message.starts_with("Ownership Churn: USDT"), "{}", message);
+
+        // Renouncing to the zero address is not a handback
+        let rule = OwnershipPingPongRule::new(3, Duration::from_secs(60), Severity::Critical);
+        let renounce = |from: u8| {
+            let data = json!({ "previousOwner": owners(from), "newOwner": format!("{}", Address::ZERO) });
+            event(EventType::OwnershipTransferred, data, Severity::Low)
+        };
+        assert!(rule.check(&renounce(1)).is_none());
+        assert!(rule.check(&renounce(0)).is_none());
+    }
+
+    #[test]
+    fn ownership_changes_age_out_of_the_window() {
+        let rule = OwnershipPingPongRule::new(3, Duration::from_secs(60), Severity::Critical);
+        let key = (1, Address::repeat_byte(0xee));
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(rule.record(key, a, b, at(0)), (1, false));
+        assert_eq!(rule.record(key, b, a, at(30)), (2, true));
+        assert_eq!(rule.record((137, key.1), a, b, at(31)), (1, false), "chains are tracked separately");
+        // Both earlier changes fell out of the window: no longer a handback
+        assert_eq!(rule.record(key, a, b, at(100)), (1, false));
+    }
+
+    #[test]
+    fn parallel_and_sequential_engines_agree() {
+        let build = |parallel: bool| {
+            let mut engine = RuleEngine::new().with_parallel(parallel);
+            // Enough thresholds that several match each event, in a known order
+            for tokens in ["0.001", "0.01", "0.1", "1", "10", "100"] {
+                engine.add_rule(Box::new(ThresholdRule::new(Threshold::Tokens(tokens.to_string()), Severity::Medium)));
+            }
+            engine.add_rule(Box::new(BlacklistRule::new(HashSet::from([DEAD.parse().unwrap()]))));
+            engine.add_rule(Box::new(WatchlistTransferRule::new(HashSet::from([SENDER.parse().unwrap()]), Severity::High)));
+            engine.add_rule(Box::new(VelocityRule::new(2, Duration::from_secs(60), Severity::High)));
+            engine.add_rule(Box::new(MintBurnRule::new(MintBurnDirection::Both, U256::ZERO, Severity::High)));
+            engine
+        };
+        let (sequential, parallel) = (build(false), build(true));
+        let (sequential_state, parallel_state) = (AppState::new(), AppState::new());
+
+        let zero = format!("{}", Address::ZERO);
+        let events: Vec<NormalizedEvent> = [
+            (SENDER, DEAD, "5000000"),
+            (SENDER, &zero, "20000"),
+            (DEAD, SENDER, "150000000"),
+            (SENDER, DEAD, "1"),
+            (&zero, SENDER, "7000000"),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (from, to, value))| NormalizedEvent { tx_hash: B256::repeat_byte(i as u8 + 1), ..transfer(from, to, value) })
+        .collect();
+
+        let mut matched = 0;
+        for event in &events {
+            let expected = sequential.process(event, &sequential_state);
+            assert_eq!(parallel.process(event, &parallel_state), expected);
+            matched += expected.len();
+        }
+        assert!(matched > events.len() * 3, "only {} matches", matched);
+        let hits = |state: &AppState| {
+            let mut hits: Vec<_> = state.rule_hits.lock().unwrap().clone().into_iter().collect();
+            hits.sort();
+            hits
+        };
+        assert_eq!(hits(&parallel_state), hits(&sequential_state));
+    }
+
+    #[test]
+    fn nft_rule_watches_collections_and_token_ids() {
+        let collection = NormalizedEvent::sample(EventType::Transfer, json!({})).contract_address;
+        let nft = |token_id: &str| event(EventType::NftTransfer, json!({ "from": SENDER, "to": DEAD, "tokenId": token_id }), Severity::Low);
+
+        let rule = NftTransferRule::new(HashSet::new(), HashSet::from([U256::from(42)]), Severity::High);
+        let (message, severity) = rule.check(&nft("0x2a")).unwrap();
+        assert!(message.starts_with("NFT Transfer: token #42 of USDT"), "{}", message);
+        assert_eq!(severity, Severity::High);
+        assert!(rule.check(&nft("0x2b")).is_none(), "another token id");
+        // An ERC-20 transfer of the same amount is not an NFT transfer
+        assert!(rule.check(&transfer(SENDER, DEAD, "42")).is_none());
+
+        let any_token = NftTransferRule::new(HashSet::from([collection]), HashSet::new(), Severity::High);
+        assert!(any_token.check(&nft("0x2b")).is_some());
+        let elsewhere = NftTransferRule::new(HashSet::from([Address::repeat_byte(1)]), HashSet::new(), Severity::High);
+        assert!(elsewhere.check(&nft("0x2a")).is_none(), "another collection");
+    }
+
+    #[test]
+    fn token_thresholds_scale_by_each_tokens_decimals() {
+        let tokens = ThresholdRule::new(Threshold::Tokens("1000".to_string()), Severity::High);
+        let raw = ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::High);
+        let with_decimals = |value: &str, decimals: Option<u8>| NormalizedEvent { decimals, ..transfer(SENDER, DEAD, value) };
+
+        // USDT (6 decimals): 1000 tokens is 1e9 base units
+        assert!(tokens.check(&with_decimals("999999999", Some(6))).is_none());
+        let (message, _) = tokens.check(&with_decimals("1000000000", Some(6))).unwrap();
+        assert_eq!(message, "Large Transfer Detected: 1000.0 > 1000.0");
+        // An 18-decimal token needs 1e21
+        assert!(tokens.check(&with_decimals("1000000000", Some(18))).is_none());
+        assert!(tokens.check(&with_decimals("1000000000000000000000", Some(18))).is_some());
+        // Unknown decimals can't be scaled, so the rule stays quiet
+        assert!(tokens.check(&with_decimals("1000000000000000000000", None)).is_none());
+
+        // Raw compares base units whatever the decimals
+        assert!(raw.check(&with_decimals("1000", Some(18))).is_some());
+        assert!(raw.check(&with_decimals("999", Some(0))).is_none());
+        let (message, _) = raw.check(&with_decimals("1500", None)).unwrap();
+        assert_eq!(message, "Large Transfer Detected: 1500 > 1000");
+
+        let fractional = ThresholdRule::new(Threshold::Tokens("0.5".to_string()), Severity::High);
+        assert!(fractional.check(&with_decimals("500000", Some(6))).is_some());
+        assert!(fractional.check(&with_decimals("499999", Some(6))).is_none());
+    }
+
+    /// A rule with a bug: it panics on every event.
+    #[derive(Debug)]
+    struct PanickingRule;
+
+    impl Rule for PanickingRule {
+        fn name(&self) -> &'static str {
+            "PanickingRule"
+        }
+
+        fn description(&self) -> String {
+            "Always panics".to_string()
+        }
+
+        fn check(&self, _event: &NormalizedEvent) -> Option<(String, Severity)> {
+            panic!("index out of bounds");
+        }
+    }
+
+    #[test]
+    fn a_panicking_rule_does_not_stop_the_others() {
+        for parallel in [false, true] {
+            let mut engine = RuleEngine::new().with_parallel(parallel);
+            engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+            engine.add_rule(Box::new(PanickingRule));
+            engine.add_rule(Box::new(BlacklistRule::new(HashSet::from([DEAD.parse().unwrap()]))));
+            let state = AppState::new();
+
+            let alerts = engine.process(&transfer(SENDER, DEAD, "5000"), &state);
+            let rules: Vec<_> = alerts.iter().map(|(rule, _, _)| *rule).collect();
+            assert_eq!(rules, ["ThresholdRule", "BlacklistRule"], "parallel: {}", parallel);
+            assert_eq!(state.rule_panics.load(Ordering::Relaxed), 1);
+
+            // It keeps being skipped, and counted, on later events
+            let mut next = transfer(SENDER, DEAD, "5000");
+            next.tx_hash = B256::repeat_byte(2);
+            assert_eq!(engine.process(&next, &state).len(), 2);
+            assert_eq!(state.rule_panics.load(Ordering::Relaxed), 2);
+        }
+    }
+
+    #[test]
+    fn stop_on_first_keeps_only_the_top_priority_match() {
+        let engine = |stop_on_first| {
+            let mut engine = RuleEngine::new().with_stop_on_first(stop_on_first);
+            engine.add_rule(Box::new(ThresholdRule::new(Threshold::Raw(U256::from(1000)), Severity::Medium)));
+            engine.add_rule(Box::new(BlacklistRule::new(HashSet::from([DEAD.parse().unwrap()]))));
+            engine
+        };
+        let rules = |engine: &RuleEngine| -> Vec<&'static str> {
+            engine.process(&transfer(SENDER, DEAD, "5000"), &AppState::new()).into_iter().map(|(rule, _, _)| rule).collect()
+        };
+
+        // By default every match alerts
+        assert_eq!(rules(&engine(false)), ["ThresholdRule", "BlacklistRule"]);
+
+        // Equal priorities: the earliest rule wins
+        assert_eq!(rules(&engine(true)), ["ThresholdRule"]);
+
+        // A raised priority wins regardless of order
+        let mut prioritized = engine(true);
+        assert!(prioritized.prioritize_rule("BlacklistRule", 10));
+        assert!(!prioritized.prioritize_rule("NoSuchRule", 10));
+        assert_eq!(rules(&prioritized), ["BlacklistRule"]);
+    }
 }
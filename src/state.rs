@@ -1,63 +1,868 @@
-use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::Ordering;
-use std::time::Instant;
-use std::sync::Mutex;
-use crate::events::Severity;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::events::{NormalizedEvent, Severity};
+use crate::listener::rpc_limit::RpcLimiter;
+use crate::tokens::TokenMetadata;
+
+/// Block time assumed for chains without `expected_block_time` (Ethereum's).
+const DEFAULT_BLOCK_TIME: Duration = Duration::from_secs(12);
+/// How many missed block intervals make a chain stale.
+const STALE_BLOCK_FACTOR: u32 = 3;
+/// Repeats of an alert within this window bump its count instead of adding a row.
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(60);
+/// Rows kept in the TUI history before the oldest is evicted.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 50;
+
+/// Lifecycle of a recorded alert, shown in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertStatus {
+    Open,
+    Resolved,  // Its condition cleared (e.g. heartbeat restored)
+    Retracted, // The triggering log was removed by a reorg
+}
+
+/// The log an alert was raised for. Synthetic events carry a zero tx hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertOrigin {
+    pub tx_hash: B256,
+    pub block_number: u64,
+    pub contract_address: Address,
+    pub contract_name: String, // Empty when not a configured contract
+}
+
+impl From<&NormalizedEvent> for AlertOrigin {
+    fn from(event: &NormalizedEvent) -> Self {
+        Self {
+            tx_hash: event.tx_hash,
+            block_number: event.block_number,
+            contract_address: event.contract_address,
+            contract_name: event.contract_name.clone(),
+        }
+    }
+}
+
+/// One row of the TUI alert history.
+#[derive(Debug, Clone)]
+pub struct AlertEntry {
+    pub severity: Severity,
+    pub chain: String,
+    pub message: String,
+    pub time: Instant, // Last occurrence
+    pub count: u64,
+    pub status: AlertStatus,
+    // None for conditions (e.g. heartbeats) and restored history
+    pub origin: Option<AlertOrigin>,
+}
+
+impl AlertEntry {
+    fn new(severity: Severity, chain: String, message: String, time: Instant, count: u64, origin: Option<AlertOrigin>) -> Self {
+        Self { severity, chain, message, time, count, status: AlertStatus::Open, origin }
+    }
+}
+
+/// One line of the JSON-lines alert history file. Repeats of a deduplicated
+/// alert are appended again with the updated `count`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRecord {
+    pub severity: Severity,
+    pub chain: String,
+    pub message: String,
+    pub timestamp: u64, // unix seconds
+    pub count: u64,
+}
+
+impl AlertRecord {
+    fn new(severity: Severity, chain: String, message: String, count: u64) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Self { severity, chain, message, timestamp, count }
+    }
+}
+
+/// Whether a chain's RPC endpoint is reachable. A chain that fails to
+/// connect at startup (or reload) stays `Down` between background retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainStatus {
+    Connecting,
+    Connected,
+    Down,
+}
+
+/// What `[rules.auto_mute]` makes of one more alert from a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MuteDecision {
+    Forward,
+    /// This hit pushed the rule over its limit; it is muted from here on
+    Muted,
+    Suppressed,
+    /// The mute expired; forward again, reporting how many were suppressed
+    Unmuted(u64),
+}
+
+/// Recent forwarded hits of one rule, and its mute if one is active.
+#[derive(Debug, Default)]
+struct RuleRate {
+    hits: VecDeque<Instant>,
+    muted_until: Option<Instant>,
+    suppressed: u64,
+}
 
 #[derive(Debug)]
 pub struct AppState {
     pub chain_heights: Mutex<HashMap<String, u64>>,
-    pub last_block_time: Mutex<Instant>,
-    // (Severity, ChainName, Message, Time, Count)
-    pub alert_history: Mutex<VecDeque<(Severity, String, String, Instant, u64)>>,
+    // ChainName -> when its latest block arrived
+    pub last_block_times: Mutex<HashMap<String, Instant>>,
+    // ChainName -> how far the latest block's timestamp trailed its arrival
+    block_lags: Mutex<HashMap<String, Duration>>,
+    // ChainName -> configured block time, for staleness checks
+    pub expected_block_times: Mutex<HashMap<String, Duration>>,
+    // ChainName -> whether its endpoint is up
+    chain_statuses: Mutex<HashMap<String, ChainStatus>>,
+    // ChainName -> limiter all of the chain's RPC requests go through
+    rpc_limiters: Mutex<HashMap<String, RpcLimiter>>,
+    // ChainName -> base fees of the latest blocks, oldest first (gas spike detection)
+    base_fees: Mutex<HashMap<String, VecDeque<u128>>>,
+    pub alert_history: Mutex<VecDeque<AlertEntry>>,
     pub severity_counts: Mutex<HashMap<Severity, u64>>,
+    // RuleName -> alerts produced
     pub rule_hits: Mutex<HashMap<String, u64>>,
+    // RuleName -> sliding window of forwarded hits (auto-mute)
+    rule_rates: Mutex<HashMap<String, RuleRate>>,
+    // (ChainName, Contract) -> first block with code
+    pub deployment_blocks: Mutex<HashMap<(String, Address), u64>>,
+    // (ChainName, Contract) -> ERC-20 metadata read at startup
+    token_metadata: Mutex<HashMap<(String, Address), TokenMetadata>>,
+    // Condition key -> messages alerted while the condition is open
+    pub open_conditions: Mutex<HashMap<String, Vec<String>>>,
+    // ChainName -> events handed to the rule engine
+    pub events_processed: Mutex<HashMap<String, u64>>,
+    pub reconnects: AtomicU64,
+    // Rule checks that panicked (and were skipped)
+    pub rule_panics: AtomicU64,
+    // Events waiting between the listeners and the rule engine
+    pub queue_depth: AtomicUsize,
+    // Events discarded by the drop_oldest queue policy
+    pub events_dropped: AtomicU64,
+    // (ChainName, Contract) -> keccak256 of the last polled bytecode
+    pub code_hashes: Mutex<HashMap<(String, Address), B256>>,
+    // (ChainName, Token) -> totalSupply at the last supply poll
+    pub total_supplies: Mutex<HashMap<(String, Address), U256>>,
+    // (Severity, ChainName, Message) acknowledged in the TUI or on Telegram; survives repeats
+    acknowledged: Mutex<HashSet<(Severity, String, String)>>,
+    // (TxHash, Message) of recent event alerts, for reorg retraction
+    tx_alerts: Mutex<VecDeque<(B256, String)>>,
+    history_sink: Mutex<Option<HistoryWriter>>,
+    history_capacity: usize,
+    dedup_window: Duration,
+    // Alerts older than this are dropped from the TUI history; None keeps them
+    retention: Option<Duration>,
+}
+
+/// Appends alert records to the history file on its own thread, so the
+/// event consumer never waits on disk.
+#[derive(Debug)]
+struct HistoryWriter {
+    records: mpsc::Sender<AlertRecord>,
+    thread: JoinHandle<()>,
+}
+
+impl HistoryWriter {
+    fn spawn(file: File) -> Self {
+        let (records, queued) = mpsc::channel();
+        let thread = std::thread::spawn(move || write_history(BufWriter::new(file), queued));
+        Self { records, thread }
+    }
+}
+
+/// Writes records as they arrive, flushing once per batch of whatever queued
+/// up meanwhile. Returns when every sender is gone or on the first failed
+/// write, which is logged.
+fn write_history(mut writer: BufWriter<File>, queued: mpsc::Receiver<AlertRecord>) {
+    while let Ok(first) = queued.recv() {
+        let written = std::iter::once(first)
+            .chain(queued.try_iter())
+            .try_for_each(|record| writeln!(writer, "{}", serde_json::to_string(&record).unwrap_or_default()))
+            .and_then(|_| writer.flush());
+        if let Err(e) = written {
+            warn!("Alert history file unwritable, persistence disabled: {}", e);
+            return;
+        }
+    }
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             chain_heights: Mutex::new(HashMap::new()),
-            last_block_time: Mutex::new(Instant::now()),
-            alert_history: Mutex::new(VecDeque::with_capacity(50)),
+            chain_statuses: Mutex::new(HashMap::new()),
+            last_block_times: Mutex::new(HashMap::new()),
+            block_lags: Mutex::new(HashMap::new()),
+            expected_block_times: Mutex::new(HashMap::new()),
+            rpc_limiters: Mutex::new(HashMap::new()),
+            base_fees: Mutex::new(HashMap::new()),
+            alert_history: Mutex::new(VecDeque::with_capacity(DEFAULT_HISTORY_CAPACITY)),
             severity_counts: Mutex::new(HashMap::new()),
             rule_hits: Mutex::new(HashMap::new()),
+            rule_rates: Mutex::new(HashMap::new()),
+            deployment_blocks: Mutex::new(HashMap::new()),
+            token_metadata: Mutex::new(HashMap::new()),
+            open_conditions: Mutex::new(HashMap::new()),
+            events_processed: Mutex::new(HashMap::new()),
+            reconnects: AtomicU64::new(0),
+            rule_panics: AtomicU64::new(0),
+            queue_depth: AtomicUsize::new(0),
+            events_dropped: AtomicU64::new(0),
+            code_hashes: Mutex::new(HashMap::new()),
+            total_supplies: Mutex::new(HashMap::new()),
+            acknowledged: Mutex::new(HashSet::new()),
+            tx_alerts: Mutex::new(VecDeque::with_capacity(DEFAULT_HISTORY_CAPACITY)),
+            history_sink: Mutex::new(None),
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            retention: None,
+        }
+    }
+
+    /// Sets how many rows the TUI history (and the tx links used to retract
+    /// them) keep before evicting the oldest. Must be non-zero.
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self.alert_history = Mutex::new(VecDeque::with_capacity(capacity));
+        self.tx_alerts = Mutex::new(VecDeque::with_capacity(capacity));
+        self
+    }
+
+    /// Sets how long repeats are folded into an existing row, and how long
+    /// rows are kept at all. Call before `with_history_file` so restored
+    /// records are merged with the same window.
+    pub fn with_history_limits(mut self, dedup_window: Duration, retention: Option<Duration>) -> Self {
+        self.dedup_window = dedup_window;
+        self.retention = retention;
+        self
+    }
+
+    /// Appends every alert to the JSON-lines file at `path` and restores up
+    /// to `restore_last` of its most recent records into the TUI history. A
+    /// missing file starts empty; an unopenable one is logged and the
+    /// watchdog runs without persistence.
+    pub fn with_history_file(self, path: &Path, restore_last: usize) -> Self {
+        let state = self;
+
+        if let Ok(records) = read_history_file(path) {
+            state.restore(&records[records.len().saturating_sub(restore_last)..]);
+        }
+
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                if let Ok(mut sink) = state.history_sink.lock() {
+                    *sink = Some(HistoryWriter::spawn(file));
+                }
+            }
+            Err(e) => warn!("Cannot open alert history file {}: {}", path.display(), e),
+        }
+        state
+    }
+
+    fn restore(&self, records: &[AlertRecord]) {
+        let now_epoch = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        if let Ok(mut history) = self.alert_history.lock() {
+            for r in records {
+                let age = Duration::from_secs(now_epoch.saturating_sub(r.timestamp));
+                let time = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+
+                // Later lines for the same alert carry its updated count
+                if let Some(mut entry) = take_recent(&mut history, self.dedup_window, &r.severity, &r.chain, &r.message, time) {
+                    entry.time = time;
+                    entry.count = r.count;
+                    history.push_back(entry);
+                    continue;
+                }
+                if history.len() >= self.history_capacity {
+                    history.pop_front();
+                }
+                history.push_back(AlertEntry::new(r.severity.clone(), r.chain.clone(), r.message.clone(), time, r.count, None));
+            }
         }
     }
 
-    pub fn update_block(&self, chain_name: &str, block: u64) {
+    /// Records a new head. `timestamp` is the block's own (unix seconds, the
+    /// sequencer's on L2s); how far it trails our clock counts towards the
+    /// chain's staleness, so a node serving old blocks on time still looks stale.
+    pub fn update_block(&self, chain_name: &str, block: u64, timestamp: Option<u64>) {
         if let Ok(mut heights) = self.chain_heights.lock() {
             heights.insert(chain_name.to_string(), block);
         }
-        if let Ok(mut time) = self.last_block_time.lock() {
-            *time = Instant::now();
+        if let Ok(mut times) = self.last_block_times.lock() {
+            times.insert(chain_name.to_string(), Instant::now());
+        }
+        if let Some(timestamp) = timestamp {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+            if let Ok(mut lags) = self.block_lags.lock() {
+                lags.insert(chain_name.to_string(), Duration::from_secs(now.saturating_sub(timestamp)));
+            }
+        }
+    }
+
+    /// When the chain's latest block arrived, if one has.
+    pub fn last_block_time(&self, chain_name: &str) -> Option<Instant> {
+        self.last_block_times.lock().ok()?.get(chain_name).copied()
+    }
+
+    /// Adds a block's base fee to the chain's trailing window of `window`
+    /// blocks. Returns the average of the window before this block, once it
+    /// is full.
+    pub fn record_base_fee(&self, chain_name: &str, base_fee: u128, window: usize) -> Option<u128> {
+        let mut fees = self.base_fees.lock().ok()?;
+        let fees = fees.entry(chain_name.to_string()).or_default();
+        let average = (fees.len() >= window && window > 0).then(|| fees.iter().sum::<u128>() / fees.len() as u128);
+        while fees.len() >= window.max(1) {
+            fees.pop_front();
+        }
+        fees.push_back(base_fee);
+        average
+    }
+
+    /// Drops a chain's block tracking once it is no longer monitored, so it
+    /// doesn't linger as stale in the health panel.
+    pub fn forget_chain(&self, chain_name: &str) {
+        if let Ok(mut heights) = self.chain_heights.lock() {
+            heights.remove(chain_name);
+        }
+        if let Ok(mut times) = self.last_block_times.lock() {
+            times.remove(chain_name);
+        }
+        if let Ok(mut lags) = self.block_lags.lock() {
+            lags.remove(chain_name);
+        }
+        if let Ok(mut expected) = self.expected_block_times.lock() {
+            expected.remove(chain_name);
+        }
+        if let Ok(mut fees) = self.base_fees.lock() {
+            fees.remove(chain_name);
+        }
+        if let Ok(mut limiters) = self.rpc_limiters.lock() {
+            limiters.remove(chain_name);
+        }
+        if let Ok(mut statuses) = self.chain_statuses.lock() {
+            statuses.remove(chain_name);
+        }
+    }
+
+    pub fn set_chain_status(&self, chain_name: &str, status: ChainStatus) {
+        if let Ok(mut statuses) = self.chain_statuses.lock() {
+            statuses.insert(chain_name.to_string(), status);
+        }
+    }
+
+    /// (ChainName, status) of every chain that isn't connected, sorted by name.
+    pub fn unconnected_chains(&self) -> Vec<(String, ChainStatus)> {
+        let mut chains: Vec<_> = match self.chain_statuses.lock() {
+            Ok(statuses) => statuses.iter()
+                .filter(|(_, status)| **status != ChainStatus::Connected)
+                .map(|(chain, status)| (chain.clone(), *status))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        chains.sort_by(|a, b| a.0.cmp(&b.0));
+        chains
+    }
+
+    pub fn set_rpc_limiter(&self, chain_name: &str, limiter: RpcLimiter) {
+        if let Ok(mut limiters) = self.rpc_limiters.lock() {
+            limiters.insert(chain_name.to_string(), limiter);
+        }
+    }
+
+    /// The chain's shared RPC limiter; unlimited for chains without one.
+    pub fn rpc_limiter(&self, chain_name: &str) -> RpcLimiter {
+        self.rpc_limiters.lock().ok()
+            .and_then(|limiters| limiters.get(chain_name).cloned())
+            .unwrap_or_default()
+    }
+
+    /// (ChainName, RPC requests in flight), sorted by name.
+    pub fn rpc_in_flight(&self) -> Vec<(String, usize)> {
+        let mut in_flight: Vec<_> = match self.rpc_limiters.lock() {
+            Ok(limiters) => limiters.iter().map(|(chain, l)| (chain.clone(), l.in_flight())).collect(),
+            Err(_) => Vec::new(),
+        };
+        in_flight.sort();
+        in_flight
+    }
+
+    pub fn set_expected_block_time(&self, chain_name: &str, block_time: Duration) {
+        if let Ok(mut expected) = self.expected_block_times.lock() {
+            expected.insert(chain_name.to_string(), block_time);
+        }
+    }
+
+    /// (ChainName, age of its last block in seconds, stale) for every chain
+    /// that has reported a block, sorted by name. The age counts from the
+    /// block's own timestamp when known, else from its arrival. A chain is
+    /// stale once that is more than `STALE_BLOCK_FACTOR` x its expected block time.
+    pub fn chain_health(&self, now: Instant) -> Vec<(String, u64, bool)> {
+        let expected = self.expected_block_times.lock().map(|e| e.clone()).unwrap_or_default();
+        let lags = self.block_lags.lock().map(|l| l.clone()).unwrap_or_default();
+        let mut health: Vec<(String, u64, bool)> = match self.last_block_times.lock() {
+            Ok(times) => times.iter()
+                .map(|(chain, last)| {
+                    let age = now.saturating_duration_since(*last) + lags.get(chain).copied().unwrap_or_default();
+                    let budget = expected.get(chain).copied().unwrap_or(DEFAULT_BLOCK_TIME) * STALE_BLOCK_FACTOR;
+                    (chain.clone(), age.as_secs(), age > budget)
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        health.sort();
+        health
+    }
+
+    /// Stores the latest code hash for a contract. Returns the previous hash
+    /// only when it differs (the first sighting is just a baseline).
+    pub fn update_code_hash(&self, chain: &str, address: Address, hash: B256) -> Option<B256> {
+        let mut hashes = self.code_hashes.lock().ok()?;
+        match hashes.insert((chain.to_string(), address), hash) {
+            Some(previous) if previous != hash => Some(previous),
+            _ => None,
+        }
+    }
+
+    /// Stores a token's latest total supply. Returns the previous one only
+    /// when it differs (the first reading is just a baseline).
+    pub fn update_total_supply(&self, chain: &str, address: Address, supply: U256) -> Option<U256> {
+        let mut supplies = self.total_supplies.lock().ok()?;
+        match supplies.insert((chain.to_string(), address), supply) {
+            Some(previous) if previous != supply => Some(previous),
+            _ => None,
+        }
+    }
+
+    pub fn record_event(&self, chain_name: &str) {
+        if let Ok(mut processed) = self.events_processed.lock() {
+            *processed.entry(chain_name.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drops history rows older than the configured retention.
+    pub fn expire_alerts(&self, now: Instant) {
+        let Some(retention) = self.retention else { return };
+        if let Ok(mut history) = self.alert_history.lock() {
+            history.retain(|entry| now.saturating_duration_since(entry.time) <= retention);
         }
     }
 
-    pub fn add_alert(&self, severity: Severity, chain: String, message: String) {
+    /// Records an alert. Passing the triggering log's `origin` shows it in
+    /// the TUI details view and, for a real tx, lets a later reorg retract
+    /// the alert via `retract_tx`.
+    pub fn add_alert(&self, severity: Severity, chain: String, message: String, origin: Option<AlertOrigin>) {
+        if let Some(tx_hash) = origin.as_ref().map(|o| o.tx_hash).filter(|h| !h.is_zero()) {
+            if let Ok(mut links) = self.tx_alerts.lock() {
+                // Only entries still in the history can be retracted
+                if links.len() >= self.history_capacity {
+                    links.pop_front();
+                }
+                links.push_back((tx_hash, message.clone()));
+            }
+        }
         if let Ok(mut counts) = self.severity_counts.lock() {
             *counts.entry(severity.clone()).or_insert(0) += 1;
         }
 
         if let Ok(mut history) = self.alert_history.lock() {
-            // Deduplication Logic (Check Chain AND Message)
-            if let Some(last) = history.back_mut() {
-                if last.0 == severity && last.1 == chain && last.2 == message {
-                    last.3 = Instant::now(); // Update time
-                    last.4 += 1;             // Increment count
-                    return;
-                }
+            // Deduplication Logic (Chain AND Message, anywhere in the window);
+            // the repeat moves to the back so the newest activity shows first
+            let now = Instant::now();
+            if let Some(mut entry) = take_recent(&mut history, self.dedup_window, &severity, &chain, &message, now) {
+                entry.time = now;                  // Update time
+                entry.count += 1;                  // Increment count
+                entry.status = AlertStatus::Open;  // Firing again reopens it
+                entry.origin = origin.or(entry.origin);
+                let count = entry.count;
+                history.push_back(entry);
+                drop(history);
+                self.persist(AlertRecord::new(severity, chain, message, count));
+                return;
             }
 
-            if history.len() >= 50 {
+            if history.len() >= self.history_capacity {
                 history.pop_front();
             }
-            history.push_back((severity, chain, message, Instant::now(), 1));
+            history.push_back(AlertEntry::new(severity.clone(), chain.clone(), message.clone(), Instant::now(), 1, origin));
+        }
+        self.persist(AlertRecord::new(severity, chain, message, 1));
+    }
+
+    /// Queues a record for the history file, if one is configured. Never
+    /// waits on disk; once the writer has stopped on a write failure,
+    /// persistence stays off for the rest of the run.
+    fn persist(&self, record: AlertRecord) {
+        let Ok(mut sink) = self.history_sink.lock() else { return };
+        let Some(writer) = sink.as_ref() else { return };
+        if writer.records.send(record).is_err() {
+            *sink = None;
+        }
+    }
+
+    /// Writes out every queued history record and closes the file; alerts
+    /// recorded afterwards are not persisted. Blocks until the writer is done.
+    pub fn close_history(&self) {
+        let writer = self.history_sink.lock().ok().and_then(|mut sink| sink.take());
+        if let Some(HistoryWriter { records, thread }) = writer {
+            drop(records);
+            let _ = thread.join();
+        }
+    }
+
+    /// Records an alert for a recoverable condition (e.g. a missing heartbeat)
+    /// so it can later be marked resolved via `resolve_condition`.
+    pub fn raise_condition(&self, key: &str, severity: Severity, chain: String, message: String) {
+        self.add_alert(severity, chain, message.clone(), None);
+        if let Ok(mut open) = self.open_conditions.lock() {
+            open.entry(key.to_string()).or_default().push(message);
+        }
+    }
+
+    pub fn condition_open(&self, key: &str) -> bool {
+        self.open_conditions.lock().map(|open| open.contains_key(key)).unwrap_or(false)
+    }
+
+    /// Closes a condition, marking every alert it raised as resolved.
+    /// Returns false if the condition was not open.
+    pub fn resolve_condition(&self, key: &str) -> bool {
+        let messages = match self.open_conditions.lock() {
+            Ok(mut open) => match open.remove(key) {
+                Some(messages) => messages,
+                None => return false,
+            },
+            Err(_) => return false,
+        };
+
+        if let Ok(mut history) = self.alert_history.lock() {
+            for entry in history.iter_mut().filter(|e| messages.contains(&e.message)) {
+                entry.status = AlertStatus::Resolved;
+            }
+        }
+        true
+    }
+
+    /// Marks every alert raised by `tx_hash` as retracted after a reorg
+    /// removed its log. Returns the retracted (Severity, Message).
+    pub fn retract_tx(&self, tx_hash: B256) -> Vec<(Severity, String)> {
+        let messages: Vec<String> = match self.tx_alerts.lock() {
+            Ok(mut links) => {
+                let messages = links.iter().filter(|(h, _)| *h == tx_hash).map(|(_, m)| m.clone()).collect();
+                links.retain(|(h, _)| *h != tx_hash);
+                messages
+            }
+            Err(_) => return Vec::new(),
+        };
+
+        let mut retracted = Vec::new();
+        if let Ok(mut history) = self.alert_history.lock() {
+            for entry in history.iter_mut().filter(|e| messages.contains(&e.message) && e.status != AlertStatus::Retracted) {
+                entry.status = AlertStatus::Retracted;
+                retracted.push((entry.severity.clone(), entry.message.clone()));
+            }
+        }
+        retracted
+    }
+    /// The dashboard history as history-file records, oldest first; each
+    /// row's timestamp is its last occurrence.
+    pub fn history_records(&self) -> Vec<AlertRecord> {
+        let now_epoch = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        match self.alert_history.lock() {
+            Ok(history) => history.iter()
+                .map(|entry| AlertRecord {
+                    severity: entry.severity.clone(),
+                    chain: entry.chain.clone(),
+                    message: entry.message.clone(),
+                    timestamp: now_epoch.saturating_sub(entry.time.elapsed().as_secs()),
+                    count: entry.count,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The last `n` recorded alerts as (Severity, ChainName, Message), oldest first.
+    pub fn recent_alerts(&self, n: usize) -> Vec<(Severity, String, String)> {
+        match self.alert_history.lock() {
+            Ok(history) => history.iter()
+                .skip(history.len().saturating_sub(n))
+                .map(|entry| (entry.severity.clone(), entry.chain.clone(), entry.message.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn cached_deployment_block(&self, chain: &str, address: Address) -> Option<u64> {
+        self.deployment_blocks.lock().ok()?.get(&(chain.to_string(), address)).copied()
+    }
+
+    pub fn cache_deployment_block(&self, chain: &str, address: Address, block: u64) {
+        if let Ok(mut blocks) = self.deployment_blocks.lock() {
+            blocks.insert((chain.to_string(), address), block);
+        }
+    }
+
+    /// Acknowledges the alert, or clears its acknowledgement if it already had
+    /// one. Returns whether it is now acknowledged.
+    pub fn toggle_acknowledged(&self, severity: &Severity, chain: &str, message: &str) -> bool {
+        let Ok(mut acknowledged) = self.acknowledged.lock() else {
+            return false;
+        };
+        let key = (severity.clone(), chain.to_string(), message.to_string());
+        if acknowledged.remove(&key) {
+            return false;
+        }
+        acknowledged.insert(key);
+        true
+    }
+
+    /// Acknowledges the alert (from a channel, e.g. a Telegram button).
+    pub fn acknowledge(&self, severity: &Severity, chain: &str, message: &str) {
+        if let Ok(mut acknowledged) = self.acknowledged.lock() {
+            acknowledged.insert((severity.clone(), chain.to_string(), message.to_string()));
+        }
+    }
+
+    pub fn is_acknowledged(&self, severity: &Severity, chain: &str, message: &str) -> bool {
+        self.acknowledged.lock()
+            .map(|acknowledged| acknowledged.contains(&(severity.clone(), chain.to_string(), message.to_string())))
+            .unwrap_or(false)
+    }
+
+    pub fn token_metadata(&self, chain: &str, address: Address) -> Option<TokenMetadata> {
+        self.token_metadata.lock().ok()?.get(&(chain.to_string(), address)).cloned()
+    }
+
+    pub fn cache_token_metadata(&self, chain: &str, address: Address, metadata: TokenMetadata) {
+        if let Ok(mut tokens) = self.token_metadata.lock() {
+            tokens.insert((chain.to_string(), address), metadata);
         }
     }
+
     pub fn record_rule_hit(&self, rule_name: String) {
         if let Ok(mut hits) = self.rule_hits.lock() {
             *hits.entry(rule_name).or_insert(0) += 1;
         }
     }
+
+    /// Counts an alert `rule` is about to forward and decides whether it may:
+    /// more than `max_hits` within `window` mutes the rule for `cooldown`.
+    /// The first hit after the cooldown unmutes it and starts a fresh window.
+    pub fn gate_rule_alert(&self, rule: &str, max_hits: usize, window: Duration, cooldown: Duration) -> MuteDecision {
+        let Ok(mut rates) = self.rule_rates.lock() else {
+            return MuteDecision::Forward;
+        };
+        let rate = rates.entry(rule.to_string()).or_default();
+        let now = Instant::now();
+
+        let mut decision = MuteDecision::Forward;
+        if let Some(until) = rate.muted_until {
+            if now < until {
+                rate.suppressed += 1;
+                return MuteDecision::Suppressed;
+            }
+            decision = MuteDecision::Unmuted(rate.suppressed);
+            *rate = RuleRate::default();
+        }
+
+        while rate.hits.front().is_some_and(|t| now.duration_since(*t) > window) {
+            rate.hits.pop_front();
+        }
+        rate.hits.push_back(now);
+        if rate.hits.len() > max_hits {
+            rate.hits.clear();
+            rate.muted_until = Some(now + cooldown);
+            rate.suppressed = 1;
+            return MuteDecision::Muted;
+        }
+        decision
+    }
+}
+
+/// Removes and returns the newest entry matching (severity, chain, message)
+/// last seen within `window` of `now`. History is time-ordered, so the scan
+/// stops at the first entry outside the window.
+fn take_recent(
+    history: &mut VecDeque<AlertEntry>,
+    window: Duration,
+    severity: &Severity,
+    chain: &str,
+    message: &str,
+    now: Instant,
+) -> Option<AlertEntry> {
+    let index = history.iter()
+        .rev()
+        .take_while(|entry| now.saturating_duration_since(entry.time) <= window)
+        .position(|entry| entry.severity == *severity && entry.chain == chain && entry.message == message)?;
+    history.remove(history.len() - 1 - index)
+}
+
+/// Every record in a JSON-lines alert history file, in file order. Lines
+/// that don't parse (e.g. one cut short by a crash) are skipped.
+pub fn read_history_file(path: &Path) -> std::io::Result<Vec<AlertRecord>> {
+    let file = File::open(path)?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (Severity, Chain, Message, Count) of each record, for comparisons.
+    fn summary(records: &[AlertRecord]) -> Vec<(Severity, &str, &str, u64)> {
+        records.iter().map(|r| (r.severity.clone(), r.chain.as_str(), r.message.as_str(), r.count)).collect()
+    }
+
+    #[test]
+    fn history_file_round_trips_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.jsonl");
+
+        let state = AppState::new().with_history_file(&path, 10);
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), None);
+        state.add_alert(Severity::Critical, "polygon".into(), "Ownership transferred".into(), None);
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), None);
+        state.close_history();
+
+        // One line per alert; the repeat carries its updated count
+        let records = read_history_file(&path).unwrap();
+        assert_eq!(summary(&records), [
+            (Severity::High, "ethereum", "Large Transfer", 1),
+            (Severity::Critical, "polygon", "Ownership transferred", 1),
+            (Severity::High, "ethereum", "Large Transfer", 2),
+        ]);
+
+        let restarted = AppState::new().with_history_file(&path, 10);
+        assert_eq!(summary(&restarted.history_records()), [
+            (Severity::Critical, "polygon", "Ownership transferred", 1),
+            (Severity::High, "ethereum", "Large Transfer", 2),
+        ]);
+        restarted.add_alert(Severity::Low, "base".into(), "Approval".into(), None);
+        restarted.close_history();
+        assert_eq!(read_history_file(&path).unwrap().len(), 4, "appended, not truncated");
+    }
+
+    #[test]
+    fn unwritable_history_file_is_not_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory can't be opened for appending
+        let state = AppState::new().with_history_file(dir.path(), 10);
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer".into(), None);
+        state.close_history();
+        assert_eq!(state.history_records().len(), 1);
+    }
+
+    #[test]
+    fn repeats_within_the_window_collapse_into_one_row() {
+        let state = AppState::new();
+        for message in ["A", "B", "A"] {
+            state.add_alert(Severity::High, "ethereum".into(), message.into(), None);
+        }
+        // The repeat moved to the back with its count bumped
+        assert_eq!(summary(&state.history_records()), [
+            (Severity::High, "ethereum", "B", 1),
+            (Severity::High, "ethereum", "A", 2),
+        ]);
+
+        // Same message on another chain or at another severity is its own row
+        state.add_alert(Severity::High, "polygon".into(), "A".into(), None);
+        state.add_alert(Severity::Low, "ethereum".into(), "A".into(), None);
+        assert_eq!(state.history_records().len(), 4);
+    }
+
+    #[test]
+    fn expired_entries_start_fresh_and_age_out() {
+        let state = AppState::new().with_history_limits(Duration::from_millis(50), Some(Duration::from_secs(60)));
+        state.add_alert(Severity::High, "ethereum".into(), "A".into(), None);
+        std::thread::sleep(Duration::from_millis(80));
+        state.add_alert(Severity::High, "ethereum".into(), "A".into(), None);
+        assert_eq!(summary(&state.history_records()), [
+            (Severity::High, "ethereum", "A", 1),
+            (Severity::High, "ethereum", "A", 1),
+        ]);
+
+        state.expire_alerts(Instant::now() + Duration::from_secs(30));
+        assert_eq!(state.history_records().len(), 2);
+        state.expire_alerts(Instant::now() + Duration::from_secs(61));
+        assert!(state.history_records().is_empty());
+    }
+
+    #[test]
+    fn history_evicts_the_oldest_past_its_capacity() {
+        let state = AppState::new().with_history_capacity(3);
+        for (i, message) in ["A", "B", "C", "D", "E"].into_iter().enumerate() {
+            let origin = AlertOrigin {
+                tx_hash: B256::repeat_byte(i as u8 + 1),
+                block_number: 1,
+                contract_address: Address::ZERO,
+                contract_name: String::new(),
+            };
+            state.add_alert(Severity::High, "ethereum".into(), message.into(), Some(origin));
+        }
+        let messages: Vec<String> = state.history_records().into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, ["C", "D", "E"]);
+
+        // Evicted rows can no longer be retracted; kept ones can
+        assert!(state.retract_tx(B256::repeat_byte(1)).is_empty());
+        assert_eq!(state.retract_tx(B256::repeat_byte(3)), [(Severity::High, "C".to_string())]);
+    }
+
+    #[test]
+    fn bursting_rules_mute_then_unmute_after_the_cooldown() {
+        let state = AppState::new();
+        let (window, cooldown) = (Duration::from_secs(60), Duration::from_millis(50));
+        let gate = |rule| state.gate_rule_alert(rule, 3, window, cooldown);
+
+        for _ in 0..3 {
+            assert_eq!(gate("ThresholdRule"), MuteDecision::Forward);
+        }
+        assert_eq!(gate("ThresholdRule"), MuteDecision::Muted);
+        assert_eq!(gate("ThresholdRule"), MuteDecision::Suppressed);
+        assert_eq!(gate("ThresholdRule"), MuteDecision::Suppressed);
+        assert_eq!(gate("BlacklistRule"), MuteDecision::Forward, "other rules are unaffected");
+
+        std::thread::sleep(Duration::from_millis(60));
+        // The muting hit and the two after it
+        assert_eq!(gate("ThresholdRule"), MuteDecision::Unmuted(3));
+        assert_eq!(gate("ThresholdRule"), MuteDecision::Forward, "a fresh window");
+    }
+
+    #[test]
+    fn staleness_scales_with_each_chains_block_time() {
+        let state = AppState::new();
+        state.set_expected_block_time("ethereum", Duration::from_secs(12));
+        state.set_expected_block_time("polygon", Duration::from_secs(2));
+        for chain in ["ethereum", "polygon", "unconfigured"] {
+            state.update_block(chain, 100, None);
+        }
+        let stale_at = |secs| -> Vec<(String, bool)> {
+            let now = Instant::now() + Duration::from_secs(secs);
+            state.chain_health(now).into_iter().map(|(chain, _, stale)| (chain, stale)).collect()
+        };
+
+        assert_eq!(stale_at(0), [("ethereum".into(), false), ("polygon".into(), false), ("unconfigured".into(), false)]);
+        // Three missed 2s blocks on polygon, well within Ethereum's budget
+        assert_eq!(stale_at(10), [("ethereum".into(), false), ("polygon".into(), true), ("unconfigured".into(), false)]);
+        // Unconfigured chains assume Ethereum's 12s
+        assert_eq!(stale_at(40), [("ethereum".into(), true), ("polygon".into(), true), ("unconfigured".into(), true)]);
+    }
 }
@@ -0,0 +1,619 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use eyre::Result;
+use tokio::sync::{mpsc, watch};
+use tracing::{error, info, warn};
+
+use crate::config::{AppConfig, ChainConfig, ContractConfig};
+use crate::events::abi::AbiEvents;
+use crate::events::NormalizedEvent;
+use crate::listener::confirmations::confirm_events;
+use crate::listener::rpc_batch::RpcBatcher;
+use crate::listener::rpc_limit::RpcLimiter;
+use crate::listener::{
+    backfill_logs, backoff_delay, connect_auto, watch_blocks_polling, watch_blocks_resilient, watch_code, watch_logs_polling, watch_logs_resilient, watch_supply,
+    AddressWatch, ChainProvider, ContractWatch, GasSpikeWatch, RetryPolicy, RpcEndpoint,
+};
+use crate::state::{AppState, ChainStatus};
+use crate::tokens::snapshot_token;
+use crate::{parse_severity, spawn_until_shutdown};
+
+/// What changed between two configs, by chain and contract name. A chain or
+/// contract whose settings changed is listed as both removed and added, so
+/// it is restarted with the new settings.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub added_chains: Vec<String>,
+    pub removed_chains: Vec<String>,
+    pub added_contracts: Vec<String>,
+    pub removed_contracts: Vec<String>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub fn diff(old: &AppConfig, new: &AppConfig) -> ConfigDiff {
+    let mut diff = ConfigDiff::default();
+    for (name, chain) in &old.chains {
+        if new.chains.get(name) != Some(chain) {
+            diff.removed_chains.push(name.clone());
+        }
+    }
+    for (name, chain) in &new.chains {
+        if old.chains.get(name) != Some(chain) {
+            diff.added_chains.push(name.clone());
+        }
+    }
+
+    // Contracts restart with their chain, even if their own config is unchanged
+    let restarted = |contract: &ContractConfig| diff.removed_chains.contains(&contract.chain) || diff.added_chains.contains(&contract.chain);
+    let mut removed = Vec::new();
+    for contract in &old.contracts {
+        if restarted(contract) || !new.contracts.iter().any(|c| c == contract) {
+            removed.push(contract.name.clone());
+        }
+    }
+    let mut added = Vec::new();
+    for contract in &new.contracts {
+        if restarted(contract) || !old.contracts.iter().any(|c| c == contract) {
+            added.push(contract.name.clone());
+        }
+    }
+    diff.removed_contracts = removed;
+    diff.added_contracts = added;
+
+    diff.added_chains.sort();
+    diff.removed_chains.sort();
+    diff
+}
+
+/// First wait before reconnecting a chain that is down; doubles with each
+/// failed attempt, capped like the listeners' reconnects.
+const CONNECT_RETRY_BASE: Duration = Duration::from_secs(2);
+
+/// Reconnect schedule of a chain whose endpoint is down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectRetry {
+    pub attempt: u32,
+    pub next_try: Instant,
+}
+
+impl ConnectRetry {
+    /// After the first failed connect.
+    pub fn new(now: Instant) -> Self {
+        Self { attempt: 0, next_try: now + backoff_delay(CONNECT_RETRY_BASE, 0) }
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        now >= self.next_try
+    }
+
+    /// Another attempt failed at `now`; backs off further.
+    pub fn failed(&mut self, now: Instant) {
+        self.attempt += 1;
+        self.next_try = now + backoff_delay(CONNECT_RETRY_BASE, self.attempt);
+    }
+}
+
+/// A chain that couldn't connect, with the contracts to start once it does.
+struct DownChain {
+    config: ChainConfig,
+    watches: Vec<ContractWatch>,
+    retry: ConnectRetry,
+}
+
+/// A running chain: its connection and where its listeners send events.
+struct ChainHandle {
+    provider: ChainProvider,
+    batcher: RpcBatcher, // Coalesces the contracts' code and supply reads
+    events: mpsc::Sender<NormalizedEvent>, // Through the confirmation buffer, if any
+    config: ChainConfig,
+    stop: watch::Sender<bool>,
+}
+
+/// Listeners for one subscribed address, shared by the contracts in `contracts`.
+struct GroupHandle {
+    chain: String,
+    contracts: Vec<String>,
+    stop: watch::Sender<bool>,
+}
+
+/// Owns every chain and contract listener so they can be started and
+/// stopped individually on config reload. Dropping it releases its event
+/// senders; running listeners keep their own.
+pub struct Supervisor {
+    state: Arc<AppState>,
+    tx: mpsc::Sender<NormalizedEvent>,
+    shutdown: watch::Receiver<bool>,
+    chains: HashMap<String, ChainHandle>,
+    groups: Vec<GroupHandle>,
+    down: HashMap<String, DownChain>,
+    supply_poll: Option<Duration>, // From [rules.supply_change]; None = no supply polling
+}
+
+impl Supervisor {
+    pub fn new(state: Arc<AppState>, tx: mpsc::Sender<NormalizedEvent>, shutdown: watch::Receiver<bool>) -> Self {
+        Self { state, tx, shutdown, chains: HashMap::new(), groups: Vec::new(), down: HashMap::new(), supply_poll: None }
+    }
+
+    /// Starts every chain in `config` and the contracts on it. A chain that
+    /// can't connect is retried in the background (see `retry_down`).
+    pub async fn start(&mut self, config: &AppConfig) -> Result<()> {
+        self.supply_poll = supply_poll(config);
+        for (chain_name, chain_cfg) in &config.chains {
+            let connected = self.start_chain(chain_name, chain_cfg).await;
+            let contracts: Vec<&ContractConfig> = config.contracts.iter().filter(|c| c.chain == *chain_name).collect();
+            let watches = contract_watches(chain_name, chain_cfg, &contracts)?;
+            if connected {
+                self.start_contracts(chain_name, watches);
+            } else {
+                self.mark_down(chain_name, chain_cfg, watches);
+            }
+        }
+        Ok(())
+    }
+
+    /// When the next down chain is due for a reconnect attempt, if any is down.
+    pub fn next_retry(&self) -> Option<Instant> {
+        self.down.values().map(|d| d.retry.next_try).min()
+    }
+
+    /// Resolves once a down chain is due for another attempt; never while
+    /// every chain is up.
+    pub async fn retry_due(&self) {
+        match self.next_retry() {
+            Some(at) => tokio::time::sleep_until(at.into()).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Tries to connect every down chain whose backoff has elapsed. A chain
+    /// that comes up starts its block listener and contracts; one that
+    /// doesn't waits longer before the next attempt.
+    pub async fn retry_down(&mut self) {
+        let now = Instant::now();
+        let due: Vec<String> = self.down.iter().filter(|(_, d)| d.retry.due(now)).map(|(name, _)| name.clone()).collect();
+        for chain_name in due {
+            let Some(mut down) = self.down.remove(&chain_name) else { continue };
+            if self.start_chain(&chain_name, &down.config).await {
+                info!("{} is reachable again, starting its listeners", chain_name);
+                self.start_contracts(&chain_name, down.watches);
+            } else {
+                down.retry.failed(Instant::now());
+                warn!("{} still unreachable, retrying in {:?}", chain_name, down.retry.next_try.saturating_duration_since(Instant::now()));
+                self.down.insert(chain_name, down);
+            }
+        }
+    }
+
+    fn mark_down(&mut self, chain_name: &str, chain_cfg: &ChainConfig, watches: Vec<ContractWatch>) {
+        let retry = ConnectRetry::new(Instant::now());
+        warn!("{} is down, retrying in the background ({} contract(s) waiting)", chain_name, watches.len());
+        self.down.insert(chain_name.to_string(), DownChain { config: chain_cfg.clone(), watches, retry });
+    }
+
+    /// Moves the running listeners from `old` to `new`. Everything that can
+    /// fail up front (ABI files) is checked before anything is stopped.
+    pub async fn apply(&mut self, old: &AppConfig, new: &AppConfig) -> Result<ConfigDiff> {
+        let diff = diff(old, new);
+        // Applies to contracts started from here on
+        self.supply_poll = supply_poll(new);
+        // Unchanged chains that failed to connect earlier get another try
+        let mut chains_to_start = diff.added_chains.clone();
+        chains_to_start.extend(
+            new.chains.keys().filter(|name| !self.chains.contains_key(*name) && !diff.added_chains.contains(name)).cloned(),
+        );
+        // Those are tried right away; a removed chain that was down stops retrying
+        self.down.retain(|name, _| !chains_to_start.contains(name) && !diff.removed_chains.contains(name));
+
+        // Removing one contract of a shared subscription restarts the others
+        let mut to_start: HashSet<String> = diff.added_contracts.iter().cloned().collect();
+        to_start.extend(new.contracts.iter().filter(|c| chains_to_start.contains(&c.chain)).map(|c| c.name.clone()));
+        for group in &self.groups {
+            if group.contracts.iter().any(|c| diff.removed_contracts.contains(c)) {
+                to_start.extend(group.contracts.iter().filter(|c| !diff.removed_contracts.contains(c)).cloned());
+            }
+        }
+        let mut watches: HashMap<String, Vec<ContractWatch>> = HashMap::new();
+        for (chain_name, chain_cfg) in &new.chains {
+            let contracts: Vec<&ContractConfig> = new.contracts.iter()
+                .filter(|c| c.chain == *chain_name && to_start.contains(&c.name))
+                .collect();
+            if !contracts.is_empty() {
+                watches.insert(chain_name.clone(), contract_watches(chain_name, chain_cfg, &contracts)?);
+            }
+        }
+
+        for chain_name in &diff.removed_chains {
+            self.stop_chain(chain_name);
+        }
+        let removed: Vec<usize> = self.groups.iter()
+            .enumerate()
+            .filter(|(_, g)| g.contracts.iter().any(|c| diff.removed_contracts.contains(c)))
+            .map(|(i, _)| i)
+            .collect();
+        for i in removed.into_iter().rev() {
+            let group = self.groups.remove(i);
+            info!("  Stopping listeners for {} on {}", group.contracts.join(", "), group.chain);
+            group.stop.send_replace(true);
+        }
+
+        for chain_name in &chains_to_start {
+            if !self.start_chain(chain_name, &new.chains[chain_name]).await {
+                let waiting = watches.remove(chain_name).unwrap_or_default();
+                self.mark_down(chain_name, &new.chains[chain_name], waiting);
+            }
+        }
+        for (chain_name, watches) in watches {
+            self.start_contracts(&chain_name, watches);
+        }
+        Ok(diff)
+    }
+
+    /// Connects to a chain and starts its block listener and, if configured,
+    /// its confirmation buffer. Returns false if the connection failed.
+    async fn start_chain(&mut self, chain_name: &str, chain_cfg: &ChainConfig) -> bool {
+        info!("Initializing Chain: {}", chain_name);
+        self.state.set_expected_block_time(chain_name, Duration::from_secs(chain_cfg.expected_block_time));
+        if chain_cfg.max_concurrent_requests > 0 {
+            info!("  {} allows {} concurrent RPC request(s)", chain_name, chain_cfg.max_concurrent_requests);
+        }
+        self.state.set_rpc_limiter(chain_name, RpcLimiter::new(chain_cfg.max_concurrent_requests));
+
+        let endpoint = RpcEndpoint { url: chain_cfg.rpc_url.clone(), auth_header: chain_cfg.auth_header.clone() };
+        self.state.set_chain_status(chain_name, ChainStatus::Connecting);
+        let provider = match connect_auto(&endpoint).await {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Failed to connect to {}: {}", chain_name, e);
+                self.state.set_chain_status(chain_name, ChainStatus::Down);
+                return false;
+            }
+        };
+        self.state.set_chain_status(chain_name, ChainStatus::Connected);
+        let (stop, _) = watch::channel(false);
+        let poll_interval = Duration::from_secs(chain_cfg.poll_interval_secs);
+
+        // Task B0: Confirmation buffer (hold events until `confirmations` deep).
+        // It exits once every sender feeding it (listeners + this handle) is gone.
+        let events = if chain_cfg.confirmations > 0 {
+            info!("  {} events wait for {} confirmation(s)", chain_name, chain_cfg.confirmations);
+            let (chain_tx, chain_rx) = mpsc::channel(100);
+            let (state_confirm, c_name, confirmations, tx_confirm) =
+                (self.state.clone(), chain_name.to_string(), chain_cfg.confirmations, self.tx.clone());
+            match &provider {
+                ChainProvider::Ws(ws) => {
+                    let provider_confirm = ws.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = confirm_events(provider_confirm, state_confirm, c_name, confirmations, chain_rx, tx_confirm).await {
+                            error!("Confirmation buffer failed: {}", e);
+                        }
+                    });
+                }
+                ChainProvider::Http(http) => {
+                    let provider_confirm = http.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = confirm_events(provider_confirm, state_confirm, c_name, confirmations, chain_rx, tx_confirm).await {
+                            error!("Confirmation buffer failed: {}", e);
+                        }
+                    });
+                }
+            }
+            chain_tx
+        } else {
+            self.tx.clone()
+        };
+
+        // Task A: Block Listener (Per Chain; WS reconnects with backoff, HTTP polls),
+        // feeding base-fee spikes into the same event path as the logs
+        let gas_spike = chain_cfg.gas_spike.as_ref().map(|config| Arc::new(GasSpikeWatch {
+            chain_id: chain_cfg.chain_id,
+            chain_name: chain_name.to_string(),
+            config: config.clone(),
+            severity: parse_severity(&config.severity),
+            tx: events.clone(),
+        }));
+        let state_clone = self.state.clone();
+        let c_name = chain_name.to_string();
+        let policy = retry_policy(chain_cfg);
+        match &provider {
+            ChainProvider::Ws(ws) => {
+                let provider_blocks = ws.clone();
+                self.spawn(&stop, async move {
+                    if let Err(e) = watch_blocks_resilient(provider_blocks, endpoint, state_clone, c_name, gas_spike, policy).await {
+                        error!("Block listener failed: {}", e);
+                    }
+                });
+            }
+            ChainProvider::Http(http) => {
+                info!("  {} has no WebSocket endpoint, polling every {:?}", chain_name, poll_interval);
+                let provider_blocks = http.clone();
+                self.spawn(&stop, async move {
+                    if let Err(e) = watch_blocks_polling(provider_blocks, state_clone, c_name, gas_spike, poll_interval).await {
+                        error!("Block listener failed: {}", e);
+                    }
+                });
+            }
+        }
+
+        let window = Duration::from_millis(chain_cfg.rpc_batch_window_ms);
+        let rpc = self.state.rpc_limiter(chain_name);
+        let batcher = match &provider {
+            ChainProvider::Ws(ws) => RpcBatcher::spawn(ws.clone(), rpc, window),
+            ChainProvider::Http(http) => RpcBatcher::spawn(http.clone(), rpc, window),
+        };
+
+        let config = chain_cfg.clone();
+        self.chains.insert(chain_name.to_string(), ChainHandle { provider, batcher, events, config, stop });
+        true
+    }
+
+    fn stop_chain(&mut self, chain_name: &str) {
+        let Some(chain) = self.chains.remove(chain_name) else {
+            // Never connected; only its status is left to clear
+            self.state.forget_chain(chain_name);
+            return;
+        };
+        info!("Stopping Chain: {}", chain_name);
+        chain.stop.send_replace(true);
+        self.state.forget_chain(chain_name);
+        self.groups.retain(|group| {
+            if group.chain == chain_name {
+                group.stop.send_replace(true);
+            }
+            group.chain != chain_name
+        });
+    }
+
+    /// Task B: Log Listener (one subscription per address on the chain), plus
+    /// its bytecode watch and optional backfill.
+    fn start_contracts(&mut self, chain_name: &str, contracts: Vec<ContractWatch>) {
+        let Some(chain) = self.chains.get(chain_name) else {
+            warn!("Not watching {} contract(s) on {}: chain is not connected", contracts.len(), chain_name);
+            return;
+        };
+        let chain_cfg = &chain.config;
+        let poll_interval = Duration::from_secs(chain_cfg.poll_interval_secs);
+        let policy = retry_policy(chain_cfg);
+        let mut groups = Vec::new();
+
+        for group in AddressWatch::group(contracts) {
+            if group.contracts.len() > 1 {
+                info!("  {} configs share {}, subscribing once", group.contracts.len(), group.address);
+            }
+            let names = group.contracts.iter().map(|c| c.name.clone()).collect();
+            let (stop, _) = watch::channel(false);
+            let watch = Arc::new(group);
+            let rpc_endpoint = RpcEndpoint { url: chain_cfg.rpc_url.clone(), auth_header: chain_cfg.auth_header.clone() };
+            let tx_clone = chain.events.clone();
+            let state_logs = self.state.clone();
+
+            // Task B2: Bytecode watch (proxy upgrades, self-destructs)
+            if chain_cfg.code_poll_interval_secs > 0 {
+                let code_interval = Duration::from_secs(chain_cfg.code_poll_interval_secs);
+                let (batcher, state_code, watch_code_cfg, tx_code) = (chain.batcher.clone(), self.state.clone(), watch.clone(), chain.events.clone());
+                self.spawn(&stop, async move {
+                    if let Err(e) = watch_code(batcher, state_code, watch_code_cfg, tx_code, code_interval).await {
+                        error!("Code watcher failed: {}", e);
+                    }
+                });
+            }
+
+            // Task B4: totalSupply watch for [rules.supply_change]
+            if let Some(supply_interval) = self.supply_poll {
+                let (batcher, state_supply, watch_supply_cfg, tx_supply) = (chain.batcher.clone(), self.state.clone(), watch.clone(), chain.events.clone());
+                self.spawn(&stop, async move {
+                    if let Err(e) = watch_supply(batcher, state_supply, watch_supply_cfg, tx_supply, supply_interval).await {
+                        error!("Supply watcher failed: {}", e);
+                    }
+                });
+            }
+
+            // Task B3: ERC-20 metadata (symbol, decimals) for alerts and the TUI
+            let (state_token, chain_token, token) = (self.state.clone(), chain_name.to_string(), watch.address);
+            match &chain.provider {
+                ChainProvider::Ws(ws) => self.spawn(&stop, snapshot_token(ws.clone(), state_token, chain_token, token)),
+                ChainProvider::Http(http) => self.spawn(&stop, snapshot_token(http.clone(), state_token, chain_token, token)),
+            }
+
+            // Backfill (if configured) runs first so history lands before live events
+            let (backfill, max_range) = (chain_cfg.backfill_blocks, chain_cfg.max_block_range);
+            match &chain.provider {
+                ChainProvider::Ws(ws) => {
+                    let provider_logs = ws.clone();
+                    self.spawn(&stop, async move {
+                        if backfill > 0 {
+                            if let Err(e) = backfill_logs(provider_logs.as_ref(), &state_logs, &watch, &tx_clone, backfill, max_range).await {
+                                warn!("Backfill failed, continuing live only: {}", e);
+                            }
+                        }
+                        if let Err(e) = watch_logs_resilient(provider_logs, rpc_endpoint, state_logs, watch, tx_clone, policy).await {
+                            error!("Log listener failed: {}", e);
+                        }
+                    });
+                }
+                ChainProvider::Http(http) => {
+                    let (provider_logs, rpc) = (http.clone(), self.state.rpc_limiter(chain_name));
+                    self.spawn(&stop, async move {
+                        let mut after_block = None;
+                        if backfill > 0 {
+                            match backfill_logs(provider_logs.as_ref(), &state_logs, &watch, &tx_clone, backfill, max_range).await {
+                                Ok(head) => after_block = Some(head),
+                                Err(e) => warn!("Backfill failed, continuing live only: {}", e),
+                            }
+                        }
+                        if let Err(e) = watch_logs_polling(provider_logs, rpc, watch, tx_clone, poll_interval, max_range, after_block).await {
+                            error!("Log listener failed: {}", e);
+                        }
+                    });
+                }
+            }
+            groups.push(GroupHandle { chain: chain_name.to_string(), contracts: names, stop });
+        }
+        self.groups.extend(groups);
+    }
+
+    /// Spawns `task` until shutdown or until `stop` is set.
+    fn spawn<F>(&self, stop: &watch::Sender<bool>, task: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let mut stopped = stop.subscribe();
+        spawn_until_shutdown(self.shutdown.clone(), async move {
+            tokio::select! {
+                _ = task => {}
+                _ = stopped.wait_for(|stop| *stop) => {}
+            }
+        });
+    }
+}
+
+/// How often to poll token supplies: only when `[rules.supply_change]` is set.
+fn supply_poll(config: &AppConfig) -> Option<Duration> {
+    config.rules.supply_change.as_ref().map(|rule| Duration::from_secs(rule.poll_interval_secs))
+}
+
+/// WebSocket reconnect policy for a chain: retry forever, and treat
+/// `stall_timeout_blocks` expected block times without a block as a dead
+/// subscription.
+fn retry_policy(chain_cfg: &ChainConfig) -> RetryPolicy {
+    let stall = Duration::from_secs(chain_cfg.expected_block_time).saturating_mul(chain_cfg.stall_timeout_blocks);
+    RetryPolicy { stall_timeout: (!stall.is_zero()).then_some(stall), ..RetryPolicy::default() }
+}
+
+/// Builds the listener settings for `contracts` on one chain, loading ABIs.
+pub fn contract_watches(chain_name: &str, chain_cfg: &ChainConfig, contracts: &[&ContractConfig]) -> Result<Vec<ContractWatch>> {
+    let mut watches = Vec::new();
+    for contract in contracts {
+        info!("  Watching Contract: {} on {} (events: {})", contract.name, chain_name, contract.events.join(", "));
+        let abi = match &contract.abi {
+            Some(path) => {
+                let abi = AbiEvents::load(std::path::Path::new(path))?;
+                info!("  Loaded {} event(s) from ABI {}", abi.len(), path);
+                abi
+            }
+            None => AbiEvents::default(),
+        };
+        let watch = ContractWatch {
+            name: contract.name.clone(),
+            address: contract.address,
+            chain_id: chain_cfg.chain_id,
+            chain_name: chain_name.to_string(),
+            base_severity: contract.base_severity.iter()
+                .map(|(event, sev)| (event.clone(), parse_severity(sev)))
+                .collect(),
+            watch_addresses: contract.watch_addresses.clone(),
+            abi,
+            forward_unknown: contract.forward_unknown,
+            decimals: contract.decimals,
+            events: contract.events.clone(),
+        };
+        let unknown = watch.unknown_events();
+        if !unknown.is_empty() {
+            warn!("  {}: no signature for {} (add it to the ABI); streaming every log instead", contract.name, unknown.join(", "));
+        }
+        watches.push(watch);
+    }
+    Ok(watches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config with `chains` and `contracts` (TOML lines) and the required rules.
+    fn config(chains: &str, contracts: &[(&str, &str, &str)]) -> AppConfig {
+        let mut text = format!("[chains]\n{}\n", chains);
+        for (name, address, chain) in contracts {
+            text += &format!("[[contracts]]\nname = \"{}\"\naddress = \"{}\"\nchain = \"{}\"\nevents = [\"Transfer\"]\n", name, address, chain);
+        }
+        text += "[rules.transfer_threshold]\nmin_value = \"1000\"\nseverity = \"Medium\"\n[rules.ownership_change]\nseverity = \"High\"\n[alerts]\nwebhook_url = \"\"\n";
+        AppConfig::from_toml(&text)
+    }
+
+    const USDT: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const DAI: &str = "0x6B175474E89094C44Da98b954EedeAC495271d0F";
+    const ETHEREUM: &str = "ethereum = { rpc_url = \"wss://eth.example\", chain_id = 1 }";
+
+    #[test]
+    fn diff_lists_added_removed_and_changed_contracts() {
+        let old = config(ETHEREUM, &[("USDT", USDT, "ethereum"), ("USDC", USDC, "ethereum")]);
+        assert!(diff(&old, &old).is_empty());
+
+        // USDC dropped, DAI added, USDT moved to a new address
+        let new = config(ETHEREUM, &[("USDT", DAI, "ethereum"), ("DAI", DAI, "ethereum")]);
+        assert_eq!(diff(&old, &new), ConfigDiff {
+            removed_contracts: vec!["USDT".into(), "USDC".into()],
+            added_contracts: vec!["USDT".into(), "DAI".into()],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn changed_chains_restart_their_contracts() {
+        let polygon = "polygon = { rpc_url = \"wss://polygon.example\", chain_id = 137 }";
+        let old = config(&format!("{}\n{}", ETHEREUM, polygon), &[("USDT", USDT, "ethereum"), ("USDC", USDC, "polygon")]);
+        let new = config(&format!("{}\n{}", ETHEREUM, polygon.replace("polygon.example", "polygon-2.example")), &[("USDT", USDT, "ethereum"), ("USDC", USDC, "polygon")]);
+        assert_eq!(diff(&old, &new), ConfigDiff {
+            added_chains: vec!["polygon".into()],
+            removed_chains: vec!["polygon".into()],
+            added_contracts: vec!["USDC".into()],
+            removed_contracts: vec!["USDC".into()],
+        });
+    }
+
+    #[test]
+    fn connect_retries_back_off_until_due() {
+        let start = Instant::now();
+        let mut retry = ConnectRetry::new(start);
+        assert!(!retry.due(start));
+        assert!(retry.due(start + CONNECT_RETRY_BASE));
+        retry.failed(start);
+        assert_eq!((retry.attempt, retry.next_try), (1, start + CONNECT_RETRY_BASE * 2));
+        retry.failed(start);
+        assert_eq!(retry.next_try, start + CONNECT_RETRY_BASE * 4);
+    }
+
+    #[tokio::test]
+    async fn down_chains_are_retried_until_they_connect() {
+        // A port nothing listens on
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let chains = format!("ethereum = {{ rpc_url = \"ws://{}\", chain_id = 1 }}", closed);
+        let state = Arc::new(AppState::new());
+        let (tx, _rx) = mpsc::channel(10);
+        let (_shutdown, shutdown_rx) = watch::channel(false);
+        let mut supervisor = Supervisor::new(state.clone(), tx, shutdown_rx);
+
+        supervisor.start(&config(&chains, &[("USDT", USDT, "ethereum")])).await.unwrap();
+        assert_eq!(state.unconnected_chains(), [("ethereum".to_string(), ChainStatus::Down)]);
+        assert!(supervisor.groups.is_empty(), "the contract waits for its chain");
+        let first = supervisor.next_retry().unwrap();
+
+        // Not yet due: nothing is tried
+        supervisor.retry_down().await;
+        assert_eq!(supervisor.next_retry(), Some(first));
+
+        // Due, still down: it backs off further
+        supervisor.down.get_mut("ethereum").unwrap().retry.next_try = Instant::now();
+        supervisor.retry_down().await;
+        assert_eq!(supervisor.down["ethereum"].retry.attempt, 1);
+        assert!(supervisor.next_retry().unwrap() > Instant::now() + CONNECT_RETRY_BASE);
+        assert_eq!(state.unconnected_chains(), [("ethereum".to_string(), ChainStatus::Down)]);
+
+        // The endpoint is back (an HTTP node needs no handshake to connect)
+        let server = wiremock::MockServer::start().await;
+        let down = supervisor.down.get_mut("ethereum").unwrap();
+        down.config.rpc_url = server.uri();
+        down.retry.next_try = Instant::now();
+        supervisor.retry_down().await;
+        assert!(state.unconnected_chains().is_empty());
+        assert_eq!(supervisor.next_retry(), None);
+        assert!(supervisor.chains.contains_key("ethereum"));
+        assert_eq!(supervisor.groups.len(), 1, "its contract started");
+    }
+}
@@ -0,0 +1,253 @@
+use alloy::primitives::{address, Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use alloy::transports::Transport;
+use futures_util::future::join_all;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::listener::rpc_batch::RpcBatcher;
+use crate::state::AppState;
+
+sol! {
+    interface IERC20Metadata {
+        function name() external view returns (string);
+        function symbol() external view returns (string);
+        function decimals() external view returns (uint8);
+        function totalSupply() external view returns (uint256);
+    }
+
+    interface IMulticall3 {
+        struct Call3 { address target; bool allowFailure; bytes callData; }
+        struct Result { bool success; bytes returnData; }
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+/// Multicall3's deterministic deployment, present on nearly every EVM chain.
+const MULTICALL3: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// ERC-20 metadata read at startup. Each field is `None` when its view
+/// function reverted or returned something undecodable (e.g. the bytes32
+/// `symbol()` of some early tokens).
+#[derive(Debug, Clone, Default)]
+pub struct TokenMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub total_supply: Option<U256>,
+}
+
+impl TokenMetadata {
+    /// "USDT (0xdAC1…)" when the symbol is known, else the bare address.
+    pub fn label(metadata: Option<&Self>, address: Address) -> String {
+        match metadata.and_then(|m| m.symbol.as_deref()) {
+            Some(symbol) => format!("{} ({})", symbol, address),
+            None => address.to_string(),
+        }
+    }
+}
+
+/// Reads name, symbol, decimals and totalSupply of `address`. The four calls
+/// go out as one Multicall3 `aggregate3` when the chain has it, otherwise as
+/// concurrent `eth_call`s. Returns None for contracts that answer none of
+/// them (not an ERC-20, or not a contract at all).
+pub async fn fetch_token_metadata<T, P>(provider: &P, address: Address) -> Option<TokenMetadata>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let calls: [Bytes; 4] = [
+        IERC20Metadata::nameCall {}.abi_encode().into(),
+        IERC20Metadata::symbolCall {}.abi_encode().into(),
+        IERC20Metadata::decimalsCall {}.abi_encode().into(),
+        IERC20Metadata::totalSupplyCall {}.abi_encode().into(),
+    ];
+    let results = match aggregate(provider, address, &calls).await {
+        Some(results) => results,
+        None => join_all(calls.iter().map(|data| call(provider, address, data.clone()))).await,
+    };
+
+    let metadata = TokenMetadata {
+        name: decode::<IERC20Metadata::nameCall>(&results[0]).map(|r| r._0),
+        symbol: decode::<IERC20Metadata::symbolCall>(&results[1]).map(|r| r._0),
+        decimals: decode::<IERC20Metadata::decimalsCall>(&results[2]).map(|r| r._0),
+        total_supply: decode::<IERC20Metadata::totalSupplyCall>(&results[3]).map(|r| r._0),
+    };
+    let any = metadata.name.is_some() || metadata.symbol.is_some() || metadata.decimals.is_some() || metadata.total_supply.is_some();
+    any.then_some(metadata)
+}
+
+/// Current `totalSupply()` of `address`, read in the chain's next batch;
+/// None if the call reverts or fails.
+pub async fn fetch_total_supply(batcher: &RpcBatcher, address: Address) -> Option<U256> {
+    let out = batcher.call(address, IERC20Metadata::totalSupplyCall {}.abi_encode().into()).await.ok();
+    decode::<IERC20Metadata::totalSupplyCall>(&out).map(|r| r._0)
+}
+
+/// Runs `calls` against `target` in one `aggregate3`, each allowed to fail.
+/// None when Multicall3 itself is missing or the batch can't be decoded.
+async fn aggregate<T, P>(provider: &P, target: Address, calls: &[Bytes]) -> Option<Vec<Option<Bytes>>>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let batch = IMulticall3::aggregate3Call {
+        calls: calls.iter()
+            .map(|data| IMulticall3::Call3 { target, allowFailure: true, callData: data.clone() })
+            .collect(),
+    };
+    let out = call(provider, MULTICALL3, batch.abi_encode().into()).await?;
+    let results = IMulticall3::aggregate3Call::abi_decode_returns(&out, true).ok()?.returnData;
+    if results.len() != calls.len() {
+        return None;
+    }
+    Some(results.into_iter().map(|r| r.success.then_some(r.returnData)).collect())
+}
+
+/// A single `eth_call`; reverts and transport errors both come back as None.
+async fn call<T, P>(provider: &P, to: Address, data: Bytes) -> Option<Bytes>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let tx = TransactionRequest::default().to(to).input(TransactionInput::new(data));
+    provider.call(&tx).await.ok()
+}
+
+fn decode<C: SolCall>(out: &Option<Bytes>) -> Option<C::Return> {
+    C::abi_decode_returns(out.as_ref()?, true).ok()
+}
+
+/// Fetches and caches `address`'s metadata unless a previous start already
+/// did; logs what was found so the startup output names each token.
+pub async fn snapshot_token<T, P>(provider: Arc<P>, state: Arc<AppState>, chain_name: String, address: Address)
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    if state.token_metadata(&chain_name, address).is_some() {
+        return;
+    }
+    // One permit covers the batch (a single request unless Multicall3 is missing)
+    match state.rpc_limiter(&chain_name).run(fetch_token_metadata(provider.as_ref(), address)).await {
+        Some(metadata) => {
+            info!(
+                "[{}] {} is {} ({}, {} decimals)",
+                chain_name,
+                address,
+                metadata.symbol.as_deref().unwrap_or("?"),
+                metadata.name.as_deref().unwrap_or("unnamed"),
+                metadata.decimals.map_or("?".to_string(), |d| d.to_string()),
+            );
+            state.cache_token_metadata(&chain_name, address, metadata);
+        }
+        None => info!("[{}] {} exposes no ERC-20 metadata", chain_name, address),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::ProviderBuilder;
+    use serde_json::{json, Value};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+
+    /// A node where USDT answers the ERC-20 views except those in `reverts`.
+    /// With `multicall`, Multicall3 is deployed and batches them.
+    struct TokenNode {
+        multicall: bool,
+        reverts: Vec<[u8; 4]>,
+    }
+
+    impl TokenNode {
+        /// Return data of one view call on `to`; None is a revert.
+        fn view(&self, to: Address, data: &[u8]) -> Option<Vec<u8>> {
+            let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+            if to != USDT || self.reverts.contains(&selector) {
+                return None;
+            }
+            match selector {
+                IERC20Metadata::nameCall::SELECTOR => Some(IERC20Metadata::nameCall::abi_encode_returns(&("Tether USD".to_string(),))),
+                IERC20Metadata::symbolCall::SELECTOR => Some(IERC20Metadata::symbolCall::abi_encode_returns(&("USDT".to_string(),))),
+                IERC20Metadata::decimalsCall::SELECTOR => Some(IERC20Metadata::decimalsCall::abi_encode_returns(&(6u8,))),
+                IERC20Metadata::totalSupplyCall::SELECTOR => Some(IERC20Metadata::totalSupplyCall::abi_encode_returns(&(U256::from(1_000_000u64),))),
+                _ => None,
+            }
+        }
+    }
+
+    impl Respond for TokenNode {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["method"], "eth_call");
+            let tx = &body["params"][0];
+            let to: Address = tx["to"].as_str().unwrap().parse().unwrap();
+            let data: Bytes = tx.get("input").or(tx.get("data")).and_then(Value::as_str).unwrap().parse().unwrap();
+
+            let out = if to == MULTICALL3 && self.multicall {
+                let batch = IMulticall3::aggregate3Call::abi_decode(&data, true).unwrap();
+                let results: Vec<IMulticall3::Result> = batch.calls.iter()
+                    .map(|c| match self.view(c.target, &c.callData) {
+                        Some(out) => IMulticall3::Result { success: true, returnData: out.into() },
+                        None => IMulticall3::Result { success: false, returnData: Bytes::new() },
+                    })
+                    .collect();
+                Some(IMulticall3::aggregate3Call::abi_encode_returns(&(results,)))
+            } else if to == MULTICALL3 {
+                // No code at the address: empty return data
+                Some(Vec::new())
+            } else {
+                self.view(to, &data)
+            };
+            let reply = match out {
+                Some(out) => json!({ "jsonrpc": "2.0", "id": body["id"], "result": Bytes::from(out) }),
+                None => json!({ "jsonrpc": "2.0", "id": body["id"], "error": { "code": 3, "message": "execution reverted" } }),
+            };
+            ResponseTemplate::new(200).set_body_json(reply)
+        }
+    }
+
+    async fn node(multicall: bool, reverts: Vec<[u8; 4]>) -> (MockServer, alloy::providers::ReqwestProvider) {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(TokenNode { multicall, reverts })
+            .mount(&server)
+            .await;
+        let provider = ProviderBuilder::new().on_http(server.uri().parse().unwrap());
+        (server, provider)
+    }
+
+    #[tokio::test]
+    async fn metadata_is_read_in_one_multicall_and_cached() {
+        let (server, provider) = node(true, Vec::new()).await;
+        let state = Arc::new(AppState::new());
+        snapshot_token(Arc::new(provider), state.clone(), "ethereum".into(), USDT).await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        let metadata = state.token_metadata("ethereum", USDT).unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Tether USD"));
+        assert_eq!(metadata.symbol.as_deref(), Some("USDT"));
+        assert_eq!(metadata.decimals, Some(6));
+        assert_eq!(metadata.total_supply, Some(U256::from(1_000_000u64)));
+        assert_eq!(TokenMetadata::label(Some(&metadata), USDT), format!("USDT ({})", USDT));
+    }
+
+    #[tokio::test]
+    async fn reverting_views_leave_gaps_without_multicall() {
+        let (server, provider) = node(false, vec![IERC20Metadata::symbolCall::SELECTOR]).await;
+        let metadata = fetch_token_metadata(&provider, USDT).await.unwrap();
+        // The failed aggregate3, then each view on its own
+        assert_eq!(server.received_requests().await.unwrap().len(), 5);
+        assert_eq!(metadata.symbol, None);
+        assert_eq!(metadata.decimals, Some(6));
+        assert_eq!(TokenMetadata::label(Some(&metadata), USDT), USDT.to_string());
+
+        // Not a token at all
+        assert!(fetch_token_metadata(&provider, Address::repeat_byte(1)).await.is_none());
+    }
+}
@@ -1,29 +1,37 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Style, Modifier},
-    widgets::{Block, Borders, Paragraph, BarChart, Table, Row, Cell},
-    Terminal,
+    backend::TestBackend,
+    widgets::{Block, Borders, Clear, Paragraph, BarChart, Table, TableState, Row, Cell, Wrap},
+    Frame, Terminal,
 };
-use crate::state::AppState;
-use crate::events::Severity;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::watch;
+use crate::state::{AlertEntry, AlertStatus, AppState, ChainStatus};
+use crate::events::{contract_label, Severity};
+use crate::tokens::TokenMetadata;
 use eyre::Result;
 
-pub fn run_tui(state: Arc<AppState>) -> Result<()> {
+/// How many of the most recent alerts `r` re-dispatches.
+const REPLAY_COUNT: usize = 10;
+
+/// Runs until `q`/Ctrl-C or until `shutdown` is set (e.g. by SIGTERM).
+pub fn run_tui(state: Arc<AppState>, replay_tx: UnboundedSender<usize>, shutdown: watch::Receiver<bool>) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let res = run_app(&mut terminal, state);
+    let res = run_app(&mut terminal, state, replay_tx, shutdown);
 
     disable_raw_mode()?;
     execute!(
@@ -39,203 +47,663 @@ pub fn run_tui(state: Arc<AppState>) -> Result<()> {
     Ok(())
 }
 
+/// View-only state owned by the TUI (never shared with the pipeline).
+#[derive(Debug, Clone)]
+pub struct UiState {
+    pub started: Instant,
+    pub filter_index: usize, // 0 = All
+    pub hide_low: bool,      // Low severity stays in the log file only
+    pub sort_by_severity: bool, // false = newest first
+    pub selected: usize,        // Highlighted row of the alert table
+    pub scroll: usize,          // Rows of the alert table scrolled past
+    pub details: bool,          // Details popup open for the selected row
+    pub acknowledge: bool,      // Toggle acknowledgement of the selected row on the next draw
+    pub viewport: usize,        // Alert rows that fit on screen, set on each draw
+}
+
+impl UiState {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            filter_index: 0,
+            hide_low: true,
+            sort_by_severity: false,
+            selected: 0,
+            scroll: 0,
+            details: false,
+            acknowledge: false,
+            viewport: 0,
+        }
+    }
+
+    /// Applies one key press; `chains` is how many filter tabs there are
+    /// (ALL included). Returns what the caller should do beyond redrawing.
+    pub fn handle_key(&mut self, key: KeyEvent, chains: usize) -> KeyAction {
+        match key.code {
+            KeyCode::Char('q') => return KeyAction::Quit,
+            // Raw mode swallows SIGINT, so Ctrl-C arrives as a key
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return KeyAction::Quit,
+            KeyCode::Tab => {
+                // Cycle, wrapping back to ALL
+                self.filter_index = (self.filter_index + 1) % chains.max(1);
+                self.selected = 0;
+                self.scroll = 0;
+                self.details = false;
+            }
+            // Moving past the end is clamped on the next draw
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected += 1,
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(self.viewport.max(1)),
+            KeyCode::PageDown => self.selected += self.viewport.max(1),
+            KeyCode::Enter => self.details = true,
+            KeyCode::Esc => self.details = false,
+            KeyCode::Char('a') => self.acknowledge = true,
+            KeyCode::Char('s') => {
+                self.sort_by_severity = !self.sort_by_severity;
+            }
+            // Re-send recent alerts to the channels (e.g. after a webhook outage)
+            KeyCode::Char('r') => return KeyAction::Replay,
+            _ => {}
+        }
+        KeyAction::None
+    }
+}
+
+/// What a key press asks of the event loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    None,
+    Quit,
+    Replay,
+}
+
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     state: Arc<AppState>,
+    replay_tx: UnboundedSender<usize>,
+    shutdown: watch::Receiver<bool>,
 ) -> std::io::Result<()> {
-    let start_time = Instant::now();
-    
-    let mut current_filter_index = 0; // 0 = All
+    let mut ui = UiState::new();
 
     loop {
-        terminal.draw(|f| {
-            // ... (Layout remains same) ...
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(1)
-                .constraints(
-                    [
-                        Constraint::Length(3), // Header
-                        Constraint::Length(10), // Report
-                        Constraint::Min(10),   // Table
-                    ]
-                    .as_ref(),
-                )
-                .split(f.size());
-
-            // --- Chain Filtering Logic ---
-            let mut active_chains: Vec<String> = vec!["ALL".to_string()];
-            if let Ok(heights) = state.chain_heights.lock() {
-                let mut chains: Vec<String> = heights.keys().cloned().collect();
-                chains.sort();
-                active_chains.extend(chains);
-            }
-            // Ensure index is valid
-            if current_filter_index >= active_chains.len() {
-                current_filter_index = 0;
-            }
-            let selected_filter = &active_chains[current_filter_index];
-
-
-            // --- Header ---
-            let block_info = if let Ok(heights) = state.chain_heights.lock() {
-                if heights.is_empty() {
-                    "No Chains Active".to_string()
-                } else {
-                    heights.iter()
-                        .map(|(k, v)| format!("{}: #{}", k, v))
-                        .collect::<Vec<String>>()
-                        .join(" | ")
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+        terminal.draw(|f| draw_dashboard(f, &state, &mut ui))?;
+
+        if event::poll(Duration::from_millis(200))? {
+            if let Event::Key(key) = event::read()? {
+                match ui.handle_key(key, active_chains(&state).len()) {
+                    KeyAction::Quit => return Ok(()),
+                    KeyAction::Replay => { let _ = replay_tx.send(REPLAY_COUNT); }
+                    KeyAction::None => {}
                 }
-            } else {
-                "State Locked".to_string()
-            };
+            }
+        }
+    }
+}
 
-            let uptime = start_time.elapsed().as_secs();
-            
-            let header_layout = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(25), Constraint::Percentage(25)])
-                .split(chunks[0]);
-
-            let block_widget = Paragraph::new(format!("BLOCKS: {}", block_info))
-                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-                .block(Block::default().borders(Borders::ALL));
-            
-            let uptime_widget = Paragraph::new(format!("UPTIME: {}s", uptime))
-                 .style(Style::default().fg(Color::White))
-                 .block(Block::default().borders(Borders::ALL));
-
-            // Status now shows Filter
-            let filter_text = format!("FILTER: [{}] (Tab)", selected_filter);
-            let status_widget = Paragraph::new(filter_text)
-                 .style(Style::default().fg(if selected_filter == "ALL" { Color::Green } else { Color::Yellow }).add_modifier(Modifier::BOLD))
-                 .block(Block::default().title(" Status ").borders(Borders::ALL));
-
-            f.render_widget(block_widget, header_layout[0]);
-            f.render_widget(uptime_widget, header_layout[1]);
-            f.render_widget(status_widget, header_layout[2]);
-
-            // --- Middle Section (Bar Chart & Health) ---
-             let mid_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
-                .split(chunks[1]);
-
-            // Summary Bar Chart
-            let counts = state.severity_counts.lock().unwrap();
-            let crit = *counts.get(&Severity::Critical).unwrap_or(&0);
-            let high = *counts.get(&Severity::High).unwrap_or(&0);
-            let med = *counts.get(&Severity::Medium).unwrap_or(&0);
-            let low = *counts.get(&Severity::Low).unwrap_or(&0);
-
-            // BarChart requires (label, u64) tuples.
-            // Using a simple BarChart from ratatui
-            let data = [
-                ("Critical", crit),
-                ("High", high),
-                ("Medium", med),
-                ("Low", low),
-            ];
-            
-            let bar_chart = BarChart::default()
-                .block(Block::default().title(" Risk Distribution ").borders(Borders::ALL))
-                .data(&data)
-                .bar_width(10)
-                .bar_style(Style::default().fg(Color::Yellow))
-                .value_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
-            
-            f.render_widget(bar_chart, mid_chunks[0]);
+/// Renders a single dashboard frame into an off-screen buffer and returns it
+/// as plain text (one line per row). Used by `--render-once` for CI captures.
+pub fn render_once(state: &AppState, width: u16, height: u16) -> Result<String> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    terminal.draw(|f| draw_dashboard(f, state, &mut UiState::new()))?;
 
-            // Health Panel
-            let last_block_time = state.last_block_time.lock().unwrap();
-            let block_age = last_block_time.elapsed().as_secs();
-            let health_style = if block_age < 15 { Style::default().fg(Color::Green) } else { Style::default().fg(Color::Red) };
-            
-            let health_text = vec![
-                ratatui::text::Line::from(vec![
-                    ratatui::text::Span::raw("Last Block: "),
-                    ratatui::text::Span::styled(format!("{}s ago", block_age), health_style.add_modifier(Modifier::BOLD))
-                ]),
-                ratatui::text::Line::from(""),
-                ratatui::text::Line::from(vec![
-                    ratatui::text::Span::raw("Rate Limiting: "),
-                    ratatui::text::Span::styled("Active", Style::default().fg(Color::Cyan))
-                ]),
-                 ratatui::text::Line::from(vec![
-                    ratatui::text::Span::raw("Mode: "),
-                    ratatui::text::Span::styled("Live Monitoring", Style::default().fg(Color::Magenta))
-                ]),
-            ];
-            
-            let health_p = Paragraph::new(health_text)
-                .block(Block::default().title(" System Health ").borders(Borders::ALL));
-            f.render_widget(health_p, mid_chunks[1]);
+    let buffer = terminal.backend().buffer();
+    let text = buffer.content
+        .chunks(buffer.area.width as usize)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>().trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+    Ok(text)
+}
 
+/// "ALL" followed by every chain that has reported a block, sorted.
+fn active_chains(state: &AppState) -> Vec<String> {
+    let mut active_chains: Vec<String> = vec!["ALL".to_string()];
+    if let Ok(heights) = state.chain_heights.lock() {
+        let mut chains: Vec<String> = heights.keys().cloned().collect();
+        chains.sort();
+        active_chains.extend(chains);
+    }
+    active_chains
+}
+
+/// A `percent_x` by `percent_y` rectangle centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let [area] = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center).areas(area);
+    let [area] = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center).areas(area);
+    area
+}
+
+/// Largest valid scroll offset: the last page still fills the viewport.
+fn clamp_scroll(scroll: usize, rows: usize, viewport: usize) -> usize {
+    scroll.min(rows.saturating_sub(viewport))
+}
+
+pub fn draw_dashboard(f: &mut Frame, state: &AppState, ui: &mut UiState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Length(3), // Header
+                Constraint::Length(10), // Report
+                Constraint::Min(10),   // Table
+            ]
+            .as_ref(),
+        )
+        .split(f.area());
+
+    // --- Chain Filtering Logic ---
+    let active_chains = active_chains(state);
+    let selected_filter = active_chains.get(ui.filter_index).map(String::as_str).unwrap_or("ALL");
+
+
+    // --- Header ---
+    let block_info = if let Ok(heights) = state.chain_heights.lock() {
+        if heights.is_empty() {
+            "No Chains Active".to_string()
+        } else {
+            heights.iter()
+                .map(|(k, v)| format!("{}: #{}", k, v))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        }
+    } else {
+        "State Locked".to_string()
+    };
+
+    let uptime = ui.started.elapsed().as_secs();
+    
+    let header_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(25), Constraint::Percentage(25)])
+        .split(chunks[0]);
+
+    let block_widget = Paragraph::new(format!("BLOCKS: {}", block_info))
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .block(Block::default().borders(Borders::ALL));
+    
+    let uptime_widget = Paragraph::new(format!("UPTIME: {}s", uptime))
+         .style(Style::default().fg(Color::White))
+         .block(Block::default().borders(Borders::ALL));
+
+    // Status now shows Filter
+    let filter_text = format!("FILTER: [{}] (Tab)", selected_filter);
+    let status_widget = Paragraph::new(filter_text)
+         .style(Style::default().fg(if selected_filter == "ALL" { Color::Green } else { Color::Yellow }).add_modifier(Modifier::BOLD))
+         .block(Block::default().title(" Status ").borders(Borders::ALL));
+
+    f.render_widget(block_widget, header_layout[0]);
+    f.render_widget(uptime_widget, header_layout[1]);
+    f.render_widget(status_widget, header_layout[2]);
 
-            // --- Footer (Recent Alerts Table) ---
-            let history = state.alert_history.lock().unwrap();
-            // Upgraded headers to include Chain
-            let headers = Row::new(vec!["CHAIN", "SEVERITY", "TIME AGO", "MESSAGE"])
-                .style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow))
-                .bottom_margin(1);
+    // --- Middle Section (Bar Chart & Health) ---
+     let mid_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(25), Constraint::Percentage(30)].as_ref())
+        .split(chunks[1]);
+
+    // Summary Bar Chart
+    let counts = state.severity_counts.lock().unwrap();
+    let crit = *counts.get(&Severity::Critical).unwrap_or(&0);
+    let high = *counts.get(&Severity::High).unwrap_or(&0);
+    let med = *counts.get(&Severity::Medium).unwrap_or(&0);
+    let low = *counts.get(&Severity::Low).unwrap_or(&0);
+
+    // BarChart requires (label, u64) tuples.
+    // Using a simple BarChart from ratatui
+    let data = [
+        ("Critical", crit),
+        ("High", high),
+        ("Medium", med),
+        ("Low", low),
+    ];
+    
+    let bar_chart = BarChart::default()
+        .block(Block::default().title(" Risk Distribution ").borders(Borders::ALL))
+        .data(&data)
+        .bar_width(10)
+        .bar_style(Style::default().fg(Color::Yellow))
+        .value_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    
+    f.render_widget(bar_chart, mid_chunks[0]);
+
+    // Rule Hits Panel (busiest rule first)
+    let mut hits: Vec<(String, u64)> = state.rule_hits.lock()
+        .map(|h| h.iter().map(|(rule, n)| (rule.clone(), *n)).collect())
+        .unwrap_or_default();
+    hits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let hit_rows: Vec<Row> = hits.into_iter()
+        .map(|(rule, n)| Row::new(vec![
+            Cell::from(rule),
+            Cell::from(n.to_string()).style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+        ]))
+        .collect();
+    let hits_table = Table::new(hit_rows, [Constraint::Fill(1), Constraint::Length(6)])
+        .block(Block::default().title(" Rule Hits ").borders(Borders::ALL));
+    f.render_widget(hits_table, mid_chunks[1]);
+
+    // Health Panel (one line per chain, red once its blocks go stale)
+    let mut health_text: Vec<ratatui::text::Line> = state.chain_health(Instant::now())
+        .into_iter()
+        .map(|(chain, age, stale)| {
+            let health_style = if stale { Style::default().fg(Color::Red) } else { Style::default().fg(Color::Green) };
+            ratatui::text::Line::from(vec![
+                ratatui::text::Span::raw(format!("{}: ", chain)),
+                ratatui::text::Span::styled(format!("{}s ago", age), health_style.add_modifier(Modifier::BOLD))
+            ])
+        })
+        .collect();
+    // Chains whose endpoint hasn't come up yet (retried in the background)
+    for (chain, status) in state.unconnected_chains() {
+        let (label, color) = match status {
+            ChainStatus::Down => ("down, retrying", Color::Red),
+            _ => ("connecting", Color::Yellow),
+        };
+        health_text.push(ratatui::text::Line::from(vec![
+            ratatui::text::Span::raw(format!("{}: ", chain)),
+            ratatui::text::Span::styled(label, Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        ]));
+    }
+    if health_text.is_empty() {
+        health_text.push(ratatui::text::Line::from("Last Block: waiting..."));
+    }
+    health_text.extend([
+        ratatui::text::Line::from(""),
+        ratatui::text::Line::from(vec![
+            ratatui::text::Span::raw("Rate Limiting: "),
+            ratatui::text::Span::styled("Active", Style::default().fg(Color::Cyan))
+        ]),
+         ratatui::text::Line::from(vec![
+            ratatui::text::Span::raw("Mode: "),
+            ratatui::text::Span::styled("Live Monitoring", Style::default().fg(Color::Magenta))
+        ]),
+    ]);
+    
+    let health_p = Paragraph::new(health_text)
+        .block(Block::default().title(" System Health ").borders(Borders::ALL));
+    f.render_widget(health_p, mid_chunks[2]);
+
+
+    // --- Footer (Recent Alerts Table) ---
+    state.expire_alerts(std::time::Instant::now());
+    let history = state.alert_history.lock().unwrap();
+    // Upgraded headers to include Chain
+    let headers = Row::new(vec!["CHAIN", "SEVERITY", "TIME AGO", "CONTRACT", "MESSAGE"])
+        .style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow))
+        .bottom_margin(1);
+    
+    let mut visible: Vec<_> = history.iter()
+        .rev()
+        .filter(|entry| !ui.hide_low || entry.severity != Severity::Low) // Filter Low severity
+        .filter(|entry| selected_filter == "ALL" || entry.chain == selected_filter) // CHAIN FILTER
+        .collect();
+    if ui.sort_by_severity {
+        // Stable sort: Critical first, newest-first within each severity
+        visible.sort_by(|a, b| b.severity.cmp(&a.severity));
+    }
+
+    // Borders plus the header row and its margin
+    ui.viewport = (chunks[2].height as usize).saturating_sub(4);
+    ui.selected = ui.selected.min(visible.len().saturating_sub(1));
+    // Scroll just enough to keep the selection on screen
+    if ui.selected < ui.scroll {
+        ui.scroll = ui.selected;
+    } else if ui.selected >= ui.scroll + ui.viewport {
+        ui.scroll = ui.selected + 1 - ui.viewport.max(1);
+    }
+    ui.scroll = clamp_scroll(ui.scroll, visible.len(), ui.viewport);
+    let selected_entry = visible.get(ui.selected).map(|entry| (*entry).clone());
+    if std::mem::take(&mut ui.acknowledge) {
+        if let Some(entry) = &selected_entry {
+            state.toggle_acknowledged(&entry.severity, &entry.chain, &entry.message);
+        }
+    }
+
+    let sort = if ui.sort_by_severity { "Severity" } else { "Newest" };
+    let table_title = if visible.len() > ui.viewport {
+        let last = (ui.scroll + ui.viewport).min(visible.len());
+        format!(" Recent Alerts [Sort: {}] (s) {}-{} of {} (Up/Down/PgUp/PgDn, Enter: details, a: ack) ", sort, ui.scroll + 1, last, visible.len())
+    } else {
+        format!(" Recent Alerts [Sort: {}] (s) (Enter: details, a: ack) ", sort)
+    };
+
+    let rows: Vec<Row> = visible.into_iter()
+        .map(|AlertEntry { severity: sev, chain, message: msg, time, count, status, origin }| {
+            let age = time.elapsed().as_secs();
+            let contract = match origin {
+                Some(origin) if !origin.contract_name.is_empty() => origin.contract_name.clone(),
+                Some(origin) if !origin.contract_address.is_zero() => format!("{:.8}…", origin.contract_address.to_string()),
+                _ => "-".to_string(),
+            };
+            let color = match sev {
+                Severity::Critical => Color::Red,
+                Severity::High => Color::LightRed,
+                Severity::Medium => Color::Yellow,
+                _ => Color::Blue,
+            };
             
-            let rows: Vec<Row> = history.iter()
-                .rev()
-                .filter(|(sev, _, _, _, _)| *sev != Severity::Low) // Filter Low severity
-                .filter(|(_, chain, _, _, _)| selected_filter == "ALL" || chain == selected_filter) // CHAIN FILTER
-                .take(15) // Strict Cap
-                .map(|(sev, chain, msg, time, count)| {
-                    let age = time.elapsed().as_secs();
-                    let color = match sev {
-                        Severity::Critical => Color::Red,
-                        Severity::High => Color::LightRed,
-                        Severity::Medium => Color::Yellow,
-                        _ => Color::Blue,
-                    };
-                    
-                    let mut display_msg = msg.clone();
-                    if display_msg.len() > 50 {
-                        display_msg.truncate(47);
-                        display_msg.push_str("...");
-                    }
-                    
-                    if *count > 1 {
-                        display_msg = format!("{} (x{})", display_msg, count);
-                    }
-
-                    Row::new(vec![
-                        Cell::from(chain.clone()).style(Style::default().fg(Color::Cyan)),
-                        Cell::from(format!("{:?}", sev)).style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
-                        Cell::from(format!("{}s", age)).style(Style::default().fg(Color::DarkGray)),
-                        Cell::from(display_msg),
-                    ])
-                }).collect();
+            // Cut on characters: a byte index could split a multi-byte one
+            let mut display_msg = msg.clone();
+            if display_msg.chars().count() > 50 {
+                display_msg = display_msg.chars().take(47).collect();
+                display_msg.push_str("...");
+            }
             
-            let table = Table::new(rows, [
-                Constraint::Length(10), // Chain
-                Constraint::Length(12), // Severity
-                Constraint::Length(10), // Time
-                Constraint::Fill(1),    // Message
+            if *count > 1 {
+                display_msg = format!("{} (x{})", display_msg, count);
+            }
+
+            // Resolved and reorged alerts are greyed out and struck through
+            let tag = match status {
+                AlertStatus::Open => None,
+                AlertStatus::Resolved => Some("[RESOLVED]"),
+                AlertStatus::Retracted => Some("[REORGED]"),
+            };
+            if let Some(tag) = tag {
+                let dim = Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT);
+                return Row::new(vec![
+                    Cell::from(chain.clone()),
+                    Cell::from(format!("{:?}", sev)),
+                    Cell::from(format!("{}s", age)),
+                    Cell::from(contract),
+                    Cell::from(format!("{} {}", display_msg, tag)),
+                ]).style(dim);
+            }
+            // Acknowledged alerts stay readable, just dimmed
+            if state.is_acknowledged(sev, chain, msg) {
+                return Row::new(vec![
+                    Cell::from(chain.clone()),
+                    Cell::from(format!("{:?}", sev)),
+                    Cell::from(format!("{}s", age)),
+                    Cell::from(contract),
+                    Cell::from(format!("{} [ACK]", display_msg)),
+                ]).style(Style::default().fg(Color::DarkGray));
+            }
+
+            Row::new(vec![
+                Cell::from(chain.clone()).style(Style::default().fg(Color::Cyan)),
+                Cell::from(format!("{:?}", sev)).style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                Cell::from(format!("{}s", age)).style(Style::default().fg(Color::DarkGray)),
+                Cell::from(contract).style(Style::default().fg(Color::Cyan)),
+                Cell::from(display_msg),
             ])
-            .header(headers)
-            .block(Block::default().title(" Recent Alerts ").borders(Borders::ALL))
-            .column_spacing(2);
-            
-             f.render_widget(table, chunks[2]);
+        }).collect();
+    
+    let table = Table::new(rows, [
+        Constraint::Length(10), // Chain
+        Constraint::Length(12), // Severity
+        Constraint::Length(10), // Time
+        Constraint::Length(12), // Contract
+        Constraint::Fill(1),    // Message
+    ])
+    .header(headers)
+    .block(Block::default().title(table_title).borders(Borders::ALL))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .column_spacing(2);
+    drop(history);
 
-        })?;
+    let mut table_state = TableState::default().with_offset(ui.scroll).with_selected(selected_entry.as_ref().map(|_| ui.selected));
+    f.render_stateful_widget(table, chunks[2], &mut table_state);
 
-        if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Tab => {
-                        current_filter_index += 1; // Cycle
-                    }
-                    _ => {}
-                }
-            }
+    // --- Details Popup (Enter/Esc) ---
+    match selected_entry {
+        Some(entry) if ui.details => {
+            let token = entry.origin.as_ref().and_then(|o| state.token_metadata(&entry.chain, o.contract_address));
+            let acknowledged = state.is_acknowledged(&entry.severity, &entry.chain, &entry.message);
+            draw_details(f, &entry, token.as_ref(), acknowledged);
         }
+        _ => ui.details = false,
+    }
+}
+
+/// Untruncated view of one alert and the log that raised it.
+fn draw_details(f: &mut Frame, entry: &AlertEntry, token: Option<&TokenMetadata>, acknowledged: bool) {
+    let label = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let field = |name: &str, value: String| ratatui::text::Line::from(vec![
+        ratatui::text::Span::styled(format!("{:<10}", name), label),
+        ratatui::text::Span::raw(value),
+    ]);
+
+    let mut lines = vec![
+        field("Severity", format!("{:?} ({:?}{})", entry.severity, entry.status, if acknowledged { ", acknowledged" } else { "" })),
+        field("Chain", entry.chain.clone()),
+        field("Seen", format!("{}x, last {}s ago", entry.count, entry.time.elapsed().as_secs())),
+    ];
+    match &entry.origin {
+        Some(origin) => {
+            // Synthetic events (code changes, heartbeats) have no transaction
+            let tx = if origin.tx_hash.is_zero() { "-".to_string() } else { origin.tx_hash.to_string() };
+            lines.push(field("Tx", tx));
+            lines.push(field("Block", origin.block_number.to_string()));
+            let contract = if origin.contract_name.is_empty() {
+                TokenMetadata::label(token, origin.contract_address)
+            } else {
+                contract_label(&origin.contract_name, origin.contract_address)
+            };
+            lines.push(field("Contract", contract));
+        }
+        None => lines.push(field("Source", "not recorded (condition or restored alert)".to_string())),
+    }
+    lines.push(ratatui::text::Line::from(""));
+    lines.push(ratatui::text::Line::from(entry.message.clone()));
+
+    let area = centered_rect(70, 50, f.area());
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title(" Alert Details (Esc) ").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_once_prints_one_plain_frame() {
+        let state = AppState::new();
+        state.update_block("ethereum", 19_000_000, None);
+
+        let frame = render_once(&state, 120, 40).unwrap();
+        assert_eq!(frame.split('\n').count(), 40);
+        assert!(frame.lines().all(|line| line.chars().count() <= 120));
+        assert!(frame.contains("BLOCKS: ethereum: #19000000"));
+        assert!(!frame.contains('\u{1b}'), "no terminal escape codes in a capture");
+    }
+
+    /// Draws one dashboard frame of `state` into a `width` x `height` buffer.
+    fn draw(state: &AppState, ui: &mut UiState, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw_dashboard(f, state, ui)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn dashboard_shows_chains_and_alerts() {
+        let state = AppState::new();
+        state.update_block("polygon", 55_000_000, None);
+        state.add_alert(Severity::High, "polygon".into(), "Large Transfer of 5000 USDT".into(), None);
+
+        let screen = draw(&state, &mut UiState::new(), 140, 40);
+        assert!(screen.contains("polygon: #55000000"));
+        assert!(screen.contains("Large Transfer of 5000 USDT"));
+        assert!(screen.contains("High"));
+    }
+
+    #[test]
+    fn scroll_offset_is_clamped_to_the_history() {
+        assert_eq!(clamp_scroll(0, 30, 10), 0);
+        assert_eq!(clamp_scroll(20, 30, 10), 20);
+        assert_eq!(clamp_scroll(25, 30, 10), 20, "the last page stays full");
+        assert_eq!(clamp_scroll(5, 4, 10), 0, "everything fits");
+        assert_eq!(clamp_scroll(5, 0, 0), 0);
+
+        let state = AppState::new();
+        state.update_block("ethereum", 1, None);
+        state.update_block("polygon", 1, None);
+        for i in 0..30 {
+            state.add_alert(Severity::High, "ethereum".into(), format!("Large Transfer #{}", i), None);
+        }
+        state.add_alert(Severity::High, "polygon".into(), "Large Transfer on polygon".into(), None);
+
+        // Paging past the end lands on the last row, with a full last page
+        let mut ui = UiState { selected: 100, ..UiState::new() };
+        draw(&state, &mut ui, 140, 40);
+        assert!(ui.viewport > 0 && ui.viewport < 31);
+        assert_eq!(ui.selected, 30);
+        assert_eq!(ui.scroll, 31 - ui.viewport);
+
+        // The polygon view has one row: both snap back to the top
+        ui.filter_index = 2;
+        draw(&state, &mut ui, 140, 40);
+        assert_eq!((ui.selected, ui.scroll), (0, 0));
+    }
+
+    #[test]
+    fn arrow_and_page_keys_scroll_through_older_alerts() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let state = AppState::new();
+        state.update_block("ethereum", 1, None);
+        for i in 0..30 {
+            state.add_alert(Severity::High, "ethereum".into(), format!("Large Transfer #{}", i), None);
+        }
+
+        // Newest first; the top page has no room for the oldest
+        let mut ui = UiState::new();
+        let screen = draw(&state, &mut ui, 140, 40);
+        assert!(screen.contains("Large Transfer #29") && !screen.contains("Large Transfer #0"));
+        let viewport = ui.viewport;
+
+        ui.handle_key(key(KeyCode::Down), 1);
+        ui.handle_key(key(KeyCode::Down), 1);
+        ui.handle_key(key(KeyCode::Up), 1);
+        draw(&state, &mut ui, 140, 40);
+        assert_eq!((ui.selected, ui.scroll), (1, 0));
+
+        // Paging down keeps the selection in view, down to the oldest alert
+        ui.handle_key(key(KeyCode::PageDown), 1);
+        draw(&state, &mut ui, 140, 40);
+        assert_eq!(ui.selected, 1 + viewport);
+        assert!(ui.scroll <= ui.selected && ui.selected < ui.scroll + viewport);
+        ui.handle_key(key(KeyCode::PageDown), 1);
+        ui.handle_key(key(KeyCode::PageDown), 1);
+        let screen = draw(&state, &mut ui, 140, 40);
+        assert_eq!(ui.selected, 29);
+        assert!(screen.contains("Large Transfer #0") && !screen.contains("Large Transfer #29"));
+
+        // ...and back up to the newest
+        ui.handle_key(key(KeyCode::PageUp), 1);
+        ui.handle_key(key(KeyCode::PageUp), 1);
+        ui.handle_key(key(KeyCode::PageUp), 1);
+        let screen = draw(&state, &mut ui, 140, 40);
+        assert_eq!((ui.selected, ui.scroll), (0, 0));
+        assert!(screen.contains("Large Transfer #29"));
+
+        // Tab and q work as before
+        ui.handle_key(key(KeyCode::Down), 1);
+        ui.handle_key(key(KeyCode::Tab), 1);
+        assert_eq!((ui.selected, ui.scroll), (0, 0));
+        assert_eq!(ui.handle_key(key(KeyCode::Char('q')), 1), KeyAction::Quit);
+    }
+
+    #[test]
+    fn enter_opens_the_details_popup_and_esc_closes_it() {
+        use crate::state::AlertOrigin;
+        use alloy::primitives::{Address, B256};
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        let state = AppState::new();
+        state.update_block("ethereum", 1, None);
+        // Long enough to be cut in the table; byte 47 falls inside an "é"
+        let message = format!("Large Transfer{}: 5000 USDT → treasury", "é".repeat(40));
+        let origin = AlertOrigin {
+            tx_hash: B256::repeat_byte(0xab),
+            block_number: 19_000_000,
+            contract_address: Address::repeat_byte(0xcd),
+            contract_name: "USDT".into(),
+        };
+        state.add_alert(Severity::High, "ethereum".into(), message.clone(), Some(origin));
+
+        let mut ui = UiState::new();
+        let screen = draw(&state, &mut ui, 200, 40);
+        assert!(screen.contains(&format!("Large Transfer{}...", "é".repeat(33))));
+        assert!(!screen.contains("Alert Details"));
+
+        assert_eq!(ui.handle_key(key(KeyCode::Enter), 2), KeyAction::None);
+        assert!(ui.details);
+        let screen = draw(&state, &mut ui, 200, 40);
+        assert!(screen.contains("Alert Details (Esc)"));
+        assert!(screen.contains(&B256::repeat_byte(0xab).to_string()), "the full tx hash");
+        assert!(screen.contains("19000000"));
+
+        ui.handle_key(key(KeyCode::Esc), 2);
+        assert!(!ui.details);
+        assert!(!draw(&state, &mut ui, 200, 40).contains("Alert Details"));
+
+        // Switching tabs closes it too, and an empty view can't hold it open
+        ui.handle_key(key(KeyCode::Enter), 2);
+        ui.handle_key(key(KeyCode::Tab), 2);
+        assert!(!ui.details);
+        ui.details = true;
+        draw(&AppState::new(), &mut ui, 200, 40);
+        assert!(!ui.details);
+
+        assert_eq!(ui.handle_key(key(KeyCode::Char('q')), 2), KeyAction::Quit);
+    }
+
+    #[test]
+    fn acknowledgement_toggles_on_the_selected_row_and_survives_repeats() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let state = AppState::new();
+        state.add_alert(Severity::Critical, "ethereum".into(), "Ownership transferred".into(), None);
+        state.add_alert(Severity::High, "ethereum".into(), "Large Transfer of 5000 USDT".into(), None);
+        let mut ui = UiState::new();
+        draw(&state, &mut ui, 140, 40);
+
+        // Newest first: the second row is the ownership alert
+        ui.handle_key(key(KeyCode::Down), 1);
+        ui.handle_key(key(KeyCode::Char('a')), 1);
+        draw(&state, &mut ui, 140, 40);
+        assert!(state.is_acknowledged(&Severity::Critical, "ethereum", "Ownership transferred"));
+        assert!(!state.is_acknowledged(&Severity::High, "ethereum", "Large Transfer of 5000 USDT"));
+
+        // A repeat folds into the same row, now on top, and stays acknowledged
+        state.add_alert(Severity::Critical, "ethereum".into(), "Ownership transferred".into(), None);
+        assert!(state.is_acknowledged(&Severity::Critical, "ethereum", "Ownership transferred"));
+        ui.handle_key(key(KeyCode::Up), 1);
+        assert!(draw(&state, &mut ui, 140, 40).contains("Ownership transferred (x2)"));
+        ui.handle_key(key(KeyCode::Enter), 1);
+        assert!(draw(&state, &mut ui, 140, 40).contains("Critical (Open, acknowledged)"));
+
+        // Pressing it again clears it
+        ui.handle_key(key(KeyCode::Esc), 1);
+        ui.handle_key(key(KeyCode::Char('a')), 1);
+        draw(&state, &mut ui, 140, 40);
+        assert!(!state.is_acknowledged(&Severity::Critical, "ethereum", "Ownership transferred"));
+    }
+
+    #[test]
+    fn severity_sort_puts_critical_above_newer_low() {
+        let state = AppState::new();
+        state.add_alert(Severity::Critical, "ethereum".into(), "Ownership transferred".into(), None);
+        state.add_alert(Severity::Low, "ethereum".into(), "Small Transfer of 3 USDT".into(), None);
+        let mut ui = UiState { hide_low: false, ..UiState::new() };
+        let row = |screen: &str, text: &str| screen.lines().position(|line| line.contains(text)).unwrap();
+
+        let screen = draw(&state, &mut ui, 140, 40);
+        assert!(row(&screen, "Small Transfer") < row(&screen, "Ownership transferred"), "newest first by default");
+
+        ui.sort_by_severity = true;
+        let screen = draw(&state, &mut ui, 140, 40);
+        assert!(screen.contains("[Sort: Severity]"));
+        assert!(row(&screen, "Ownership transferred") < row(&screen, "Small Transfer"));
     }
 }